@@ -0,0 +1,78 @@
+CREATE TABLE IF NOT EXISTS plans (
+    plan_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now')),
+    inputs_hash TEXT NOT NULL,
+    expected_profit REAL NOT NULL,
+    cost REAL NOT NULL,
+    output TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS plan_builds (
+    plan_id INTEGER NOT NULL,
+    production_line_id INTEGER NOT NULL,
+    location_id INTEGER NOT NULL,
+    market_location_id INTEGER,
+    product_type_id INTEGER NOT NULL,
+    product_name TEXT NOT NULL,
+    builds INTEGER NOT NULL,
+    runs INTEGER NOT NULL,
+    installation_cost REAL NOT NULL,
+    expected_profit REAL,
+    sell_price REAL,
+    revenue_per_price REAL,
+    PRIMARY KEY (plan_id, production_line_id)
+);
+
+CREATE TABLE IF NOT EXISTS plan_materials (
+    plan_id INTEGER NOT NULL,
+    production_line_id INTEGER NOT NULL,
+    type_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    quantity REAL NOT NULL,
+    price REAL NOT NULL,
+    PRIMARY KEY (plan_id, production_line_id, type_id)
+);
+
+CREATE TABLE IF NOT EXISTS plan_purchases (
+    plan_id INTEGER NOT NULL,
+    location_id INTEGER NOT NULL,
+    type_id INTEGER NOT NULL,
+    quantity INTEGER NOT NULL,
+    cost REAL NOT NULL,
+    PRIMARY KEY (plan_id, location_id, type_id)
+);
+
+CREATE TABLE IF NOT EXISTS plan_deliveries (
+    plan_id INTEGER NOT NULL,
+    src_location_id INTEGER NOT NULL,
+    dst_location_id INTEGER NOT NULL,
+    service_name TEXT NOT NULL,
+    type_id INTEGER NOT NULL,
+    quantity INTEGER NOT NULL,
+    PRIMARY KEY (
+        plan_id,
+        src_location_id,
+        dst_location_id,
+        service_name,
+        type_id
+    )
+);
+
+CREATE TABLE IF NOT EXISTS industry_jobs (
+    job_id INTEGER PRIMARY KEY,
+    facility_id INTEGER NOT NULL,
+    product_type_id INTEGER NOT NULL,
+    runs INTEGER NOT NULL,
+    cost REAL NOT NULL,
+    start_date TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS wallet_transactions (
+    transaction_id INTEGER PRIMARY KEY,
+    date TEXT NOT NULL,
+    location_id INTEGER NOT NULL,
+    type_id INTEGER NOT NULL,
+    quantity INTEGER NOT NULL,
+    unit_price REAL NOT NULL,
+    is_buy BOOLEAN NOT NULL
+);
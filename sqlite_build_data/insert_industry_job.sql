@@ -0,0 +1,11 @@
+INSERT OR IGNORE INTO
+    industry_jobs (
+        job_id,
+        facility_id,
+        product_type_id,
+        runs,
+        cost,
+        start_date
+    )
+VALUES
+    (?, ?, ?, ?, ?, ?);
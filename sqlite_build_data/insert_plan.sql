@@ -0,0 +1,6 @@
+INSERT INTO
+    plans (inputs_hash, expected_profit, cost, output)
+VALUES
+    (?, ?, ?, ?)
+RETURNING
+    plan_id;
@@ -0,0 +1,17 @@
+INSERT INTO
+    plan_builds (
+        plan_id,
+        production_line_id,
+        location_id,
+        market_location_id,
+        product_type_id,
+        product_name,
+        builds,
+        runs,
+        installation_cost,
+        expected_profit,
+        sell_price,
+        revenue_per_price
+    )
+VALUES
+    (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?);
@@ -0,0 +1,11 @@
+INSERT INTO
+    plan_deliveries (
+        plan_id,
+        src_location_id,
+        dst_location_id,
+        service_name,
+        type_id,
+        quantity
+    )
+VALUES
+    (?, ?, ?, ?, ?, ?);
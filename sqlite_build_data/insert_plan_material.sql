@@ -0,0 +1,11 @@
+INSERT INTO
+    plan_materials (
+        plan_id,
+        production_line_id,
+        type_id,
+        name,
+        quantity,
+        price
+    )
+VALUES
+    (?, ?, ?, ?, ?, ?);
@@ -0,0 +1,4 @@
+INSERT INTO
+    plan_purchases (plan_id, location_id, type_id, quantity, cost)
+VALUES
+    (?, ?, ?, ?, ?);
@@ -0,0 +1,12 @@
+INSERT OR IGNORE INTO
+    wallet_transactions (
+        transaction_id,
+        date,
+        location_id,
+        type_id,
+        quantity,
+        unit_price,
+        is_buy
+    )
+VALUES
+    (?, ?, ?, ?, ?, ?, ?);
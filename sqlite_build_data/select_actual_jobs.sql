@@ -0,0 +1,10 @@
+SELECT
+    COUNT(*) AS "jobs!: i64",
+    COALESCE(SUM(runs), 0) AS "runs!: i64",
+    COALESCE(SUM(cost), 0.0) AS "cost!: f64"
+FROM
+    industry_jobs
+WHERE
+    facility_id = ?
+    AND product_type_id = ?
+    AND start_date >= ?;
@@ -0,0 +1,18 @@
+SELECT
+    COALESCE(SUM(quantity), 0) AS "quantity!: i64",
+    COALESCE(SUM(quantity * unit_price), 0.0) AS "value!: f64"
+FROM
+    wallet_transactions
+WHERE
+    type_id = ?
+    AND is_buy
+    AND date >= ?
+    AND location_id IN (
+        SELECT
+            location_id
+        FROM
+            plan_purchases
+        WHERE
+            plan_id = ?
+            AND type_id = wallet_transactions.type_id
+    );
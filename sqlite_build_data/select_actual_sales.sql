@@ -0,0 +1,10 @@
+SELECT
+    COALESCE(SUM(quantity), 0) AS "quantity!: i64",
+    COALESCE(SUM(quantity * unit_price), 0.0) AS "value!: f64"
+FROM
+    wallet_transactions
+WHERE
+    type_id = ?
+    AND location_id = ?
+    AND NOT is_buy
+    AND date >= ?;
@@ -0,0 +1,8 @@
+SELECT
+    type_id,
+    probability_multiplier,
+    runs,
+    me,
+    te
+FROM
+    decryptors;
@@ -0,0 +1,7 @@
+SELECT
+    skill_type_id,
+    encryption
+FROM
+    invention_skills
+WHERE
+    type_id = ?;
@@ -0,0 +1,11 @@
+SELECT
+    plan_id,
+    created_at,
+    inputs_hash,
+    expected_profit
+FROM
+    plans
+ORDER BY
+    plan_id DESC
+LIMIT
+    1;
@@ -0,0 +1,6 @@
+SELECT
+    market_group_id
+FROM
+    market_groups
+WHERE
+    type_id = ?;
\ No newline at end of file
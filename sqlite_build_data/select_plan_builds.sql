@@ -0,0 +1,18 @@
+SELECT
+    production_line_id,
+    location_id,
+    market_location_id,
+    product_type_id,
+    product_name,
+    builds,
+    runs,
+    installation_cost,
+    expected_profit,
+    sell_price,
+    revenue_per_price
+FROM
+    plan_builds
+WHERE
+    plan_id = ?
+ORDER BY
+    production_line_id;
@@ -0,0 +1,12 @@
+SELECT
+    type_id,
+    name,
+    quantity,
+    price
+FROM
+    plan_materials
+WHERE
+    plan_id = ?
+    AND production_line_id = ?
+ORDER BY
+    type_id;
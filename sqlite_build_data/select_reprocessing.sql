@@ -0,0 +1,8 @@
+SELECT
+    portion,
+    skill_type_id,
+    minerals
+FROM
+    reprocessing
+WHERE
+    type_id = ?;
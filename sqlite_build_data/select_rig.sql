@@ -0,0 +1,8 @@
+SELECT
+    rig_size,
+    group_id,
+    high_sec
+FROM
+    rigs
+WHERE
+    type_id = ?;
@@ -0,0 +1,6 @@
+SELECT
+    kind
+FROM
+    efficiencies
+WHERE
+    type_id = ?;
@@ -3,7 +3,6 @@ SELECT
     time_efficiency,
     material_efficiency,
     cost_efficiency,
-    probability_multiplier,
     high_sec_multiplier,
     low_sec_multiplier,
     zero_sec_multiplier
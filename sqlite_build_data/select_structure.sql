@@ -0,0 +1,9 @@
+SELECT
+    rig_size,
+    manufacturing,
+    science,
+    reaction
+FROM
+    structures
+WHERE
+    type_id = ?;
@@ -14,6 +14,7 @@ pub struct TypeMarketOrders {
     pub total: f64,
 }
 
+#[derive(Clone)]
 pub struct Api {
     pub adjusted_prices: HashMap<u32, f64>,
     pub cost_indices: HashMap<u32, config::ManufacturingValue>,
@@ -34,6 +35,65 @@ impl Api {
             assets,
         })
     }
+
+    // market prices scaled as the scenario says, the rest is unchanged
+    pub fn with_scenario(
+        &self,
+        scenario: &config::Scenario,
+        market_groups: &HashMap<u32, Vec<u32>>, // by type id
+    ) -> Self {
+        let mut api = self.clone();
+        for (location_id, location_orders) in api.market_orders.iter_mut() {
+            for (type_id, type_orders) in location_orders.iter_mut() {
+                let multiplier = scenario.price_multiplier(
+                    *location_id,
+                    *type_id,
+                    market_groups
+                        .get(type_id)
+                        .map(|groups| groups.as_slice())
+                        .unwrap_or_default(),
+                );
+                for order in type_orders.orders.iter_mut() {
+                    order.price *= multiplier;
+                }
+            }
+        }
+        api
+    }
+}
+
+// as ESI returns them, for comparing plans against what actually happened
+#[derive(Debug, Clone, Deserialize)]
+pub struct IndustryJob {
+    pub job_id: i64,
+    pub facility_id: u64,
+    pub product_type_id: Option<u32>,
+    pub runs: i64,
+    pub cost: Option<f64>,
+    pub start_date: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WalletTransaction {
+    pub transaction_id: i64,
+    pub date: String,
+    pub location_id: u64,
+    pub type_id: u32,
+    pub quantity: i64,
+    pub unit_price: f64,
+    pub is_buy: bool,
+}
+
+pub fn read_industry_jobs(
+) -> Result<Vec<IndustryJob>, Box<dyn std::error::Error>> {
+    Ok(serde_json::from_reader(File::open("industry_jobs.json")?)?)
+}
+
+pub fn read_wallet_transactions(
+) -> Result<Vec<WalletTransaction>, Box<dyn std::error::Error>> {
+    Ok(serde_json::from_reader(File::open(
+        "wallet_transactions.json",
+    )?)?)
 }
 
 fn read_adjusted_prices(
@@ -1,66 +1,312 @@
-use futures::{stream::FuturesUnordered, TryFutureExt, TryStreamExt};
-use std::collections::{HashMap, HashSet};
+use futures::{
+    future::Either, stream::FuturesUnordered, FutureExt, StreamExt,
+    TryFutureExt, TryStreamExt,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    iter,
+};
 
 use crate::{
     config::{self, Item},
     industry_db,
 };
 
+pub async fn validate_locations(
+    cfg_locations: impl Iterator<Item = &config::Location>,
+    cfg_characters: &HashMap<String, config::Character>,
+    db: &impl industry_db::IndustryDatabase,
+) -> Result<(), crate::Error> {
+    let mut db_validate_futs = FuturesUnordered::new();
+    for location in cfg_locations {
+        let location_production = match &location.production {
+            Some(location_production) => location_production,
+            None => continue,
+        };
+        for production_line in location_production.production_lines.iter() {
+            let name = match &production_line.character {
+                Some(name) => name,
+                None => continue,
+            };
+            let reachable = match cfg_characters.get(name) {
+                Some(character) => character
+                    .location_ids
+                    .as_ref()
+                    .map(|ids| ids.contains(&location.id))
+                    .unwrap_or(true),
+                None => {
+                    return Err(crate::Error::InvalidConfig(format!(
+                        "character {} is unknown",
+                        name
+                    )))
+                }
+            };
+            if !reachable {
+                return Err(crate::Error::InvalidConfig(format!(
+                    "character {} can't reach {}",
+                    name, location.name
+                )));
+            }
+        }
+        let kinds = location_production
+            .production_lines
+            .iter()
+            .map(|production_line| production_line.kind)
+            .collect::<Vec<_>>();
+        db_validate_futs.push(async move {
+            db.validate_fitting(
+                location.system_id,
+                location_production.structure_type_id,
+                location_production.rigs,
+                &kinds,
+            )
+            .await
+            .map_err(|e| match e {
+                crate::Error::InvalidConfig(reason) => {
+                    crate::Error::InvalidConfig(format!(
+                        "{}: {}",
+                        location.name, reason
+                    ))
+                }
+                e => e,
+            })
+        });
+    }
+    while db_validate_futs.try_next().await?.is_some() {}
+    Ok(())
+}
+
 pub async fn get_db_lines(
     cfg_locations: impl Iterator<Item = &config::Location>,
     cfg_skills: &HashMap<u32, u8>,
+    cfg_characters: &HashMap<String, config::Character>,
     cfg_max_time: std::time::Duration,
     db: &impl industry_db::IndustryDatabase,
-) -> Result<HashMap<u32, industry_db::Line>, crate::Error> {
+) -> Result<HashMap<u32, Vec<industry_db::Line>>, crate::Error> {
+    let cfg_locations = cfg_locations.collect::<Vec<_>>();
     let mut db_line_futs = FuturesUnordered::new();
-    for location in cfg_locations {
+    for location in cfg_locations.iter() {
         let location_production = match &location.production {
             Some(location_production) => location_production,
             None => continue,
         };
         for production_line in location_production.production_lines.iter() {
-            let fut = db
-                .compute_line(
+            let implants = character_implants(production_line, cfg_characters)?;
+            let fut = match production_line.auto_decryptor
+                && production_line.kind == config::ManufacturingKind::Invention
+            {
+                true => Either::Left(db.compute_decryptor_lines(
                     location.system_id,
                     location_production.structure_type_id,
                     location_production.rigs,
                     location_production.tax,
                     cfg_skills,
-                    production_line.kind,
+                    implants,
                     production_line.transput,
                     cfg_max_time,
-                    production_line.decryptor,
-                )
-                .map_ok(move |line| (production_line.id, line));
+                    production_line.relic,
+                )),
+                false => Either::Right(
+                    db.compute_line(
+                        location.system_id,
+                        location_production.structure_type_id,
+                        location_production.rigs,
+                        location_production.tax,
+                        cfg_skills,
+                        implants,
+                        production_line.kind,
+                        production_line.transput,
+                        cfg_max_time,
+                        production_line.decryptor,
+                        production_line.relic,
+                    )
+                    .map_ok(|line| vec![line]),
+                ),
+            }
+            .map_ok(move |lines| (production_line.id, lines));
             db_line_futs.push(fut);
         }
     }
     let mut db_lines = HashMap::new();
     while let Some(result) = db_line_futs.try_next().await? {
-        let (id, line) = result;
-        db_lines.insert(id, line);
+        let (id, lines) = result;
+        db_lines.insert(id, lines);
+    }
+
+    // lines fed by a line picking its own decryptor get a variant for every
+    // blueprint it can invent, so the decryptor is picked by their profit
+    let auto_decryptor_ids = cfg_locations
+        .iter()
+        .flat_map(|location| location.production.iter())
+        .flat_map(|production| production.production_lines.iter())
+        .filter(|production_line| {
+            production_line.auto_decryptor
+                && production_line.kind == config::ManufacturingKind::Invention
+        })
+        .map(|production_line| production_line.id)
+        .collect::<HashSet<_>>();
+    let mut db_variant_futs = FuturesUnordered::new();
+    for location in cfg_locations.iter() {
+        let location_production = match &location.production {
+            Some(location_production) => location_production,
+            None => continue,
+        };
+        for production_line in location_production.production_lines.iter() {
+            let implants = character_implants(production_line, cfg_characters)?;
+            let mut blueprints = production_line
+                .import_src_blueprint_production_line_ids
+                .iter()
+                .filter(|id| auto_decryptor_ids.contains(id))
+                .flat_map(|id| db_lines[id].iter().map(|line| line.product))
+                .filter(|&blueprint| {
+                    blueprint != production_line.transput.blueprint
+                })
+                .collect::<Vec<_>>();
+            blueprints.sort();
+            blueprints.dedup();
+            for blueprint in blueprints {
+                db_variant_futs.push(
+                    db.compute_line(
+                        location.system_id,
+                        location_production.structure_type_id,
+                        location_production.rigs,
+                        location_production.tax,
+                        cfg_skills,
+                        implants,
+                        production_line.kind,
+                        config::Transput::new(
+                            blueprint,
+                            production_line.transput.product,
+                        ),
+                        cfg_max_time,
+                        production_line.decryptor,
+                        production_line.relic,
+                    )
+                    .map(move |line| (production_line.id, line)),
+                );
+            }
+        }
+    }
+    let mut db_variants = Vec::new();
+    while let Some((id, line)) = db_variant_futs.next().await {
+        // blueprints that can't be used by the line are left out
+        if let Ok(line) = line {
+            db_variants.push((id, line));
+        }
+    }
+    db_variants.sort_by_key(|(id, line)| (*id, line.blueprint));
+    for (id, line) in db_variants {
+        db_lines.get_mut(&id).unwrap().push(line);
     }
     Ok(db_lines)
 }
 
-pub async fn get_db_volumes_and_names(
+fn character_implants<'cfg>(
+    production_line: &config::ProductionLine,
+    cfg_characters: &'cfg HashMap<String, config::Character>,
+) -> Result<&'cfg [u32], crate::Error> {
+    match &production_line.character {
+        Some(name) => match cfg_characters.get(name) {
+            Some(character) => Ok(character.implants.as_slice()),
+            None => Err(crate::Error::InvalidConfig(format!(
+                "character {} is unknown",
+                name
+            ))),
+        },
+        None => Ok(&[]),
+    }
+}
+
+// reprocessing of ores and of the products made at each location
+pub async fn get_db_reprocessing<'cfg>(
+    cfg_locations: impl Iterator<Item = &'cfg config::Location>,
+    cfg_skills: &HashMap<u32, u8>,
+    cfg_characters: &HashMap<String, config::Character>,
+    cfg_ores: &[u32],
+    db: &impl industry_db::IndustryDatabase,
+) -> Result<HashMap<u64, HashMap<Item, industry_db::Reprocessing>>, crate::Error>
+{
+    let mut db_reprocessing_futs = FuturesUnordered::new();
+    for location in cfg_locations {
+        let location_reprocessing = match &location.reprocessing {
+            Some(location_reprocessing) => location_reprocessing,
+            None => continue,
+        };
+        let implant_bonus = match &location_reprocessing.character {
+            Some(name) => match cfg_characters.get(name) {
+                Some(character) => character.reprocessing_implant_bonus,
+                None => {
+                    return Err(crate::Error::InvalidConfig(format!(
+                        "character {} is unknown",
+                        name
+                    )))
+                }
+            },
+            None => 0.0,
+        };
+        let products = location
+            .production
+            .iter()
+            .flat_map(|p| p.production_lines.iter())
+            .map(|production_line| production_line.transput.product)
+            .filter(|item| !item.is_blueprint());
+        let mut seen_items = HashSet::new();
+        for item in cfg_ores.iter().map(|&id| Item::new(id)).chain(products) {
+            if seen_items.insert(item) {
+                db_reprocessing_futs.push(
+                    db.compute_reprocessing(
+                        location.system_id,
+                        location_reprocessing,
+                        cfg_skills,
+                        implant_bonus,
+                        item,
+                    )
+                    .map_ok(move |reprocessing| (location.id, reprocessing)),
+                );
+            }
+        }
+    }
+    let mut db_reprocessing = HashMap::new();
+    while let Some(result) = db_reprocessing_futs.try_next().await? {
+        if let (id, Some(reprocessing)) = result {
+            db_reprocessing
+                .entry(id)
+                .or_insert_with(HashMap::new)
+                .insert(reprocessing.item, reprocessing);
+        }
+    }
+    Ok(db_reprocessing)
+}
+
+pub async fn get_db_volumes_and_names<'db>(
     cfg_locations: impl Iterator<Item = &config::Location>,
-    db_lines: impl Iterator<Item = &industry_db::Line>,
+    db_lines: impl Iterator<Item = &'db industry_db::Line>,
+    db_reprocessing: impl Iterator<Item = &'db industry_db::Reprocessing>,
     db: &impl industry_db::IndustryDatabase,
 ) -> Result<(HashMap<Item, f64>, HashMap<Item, String>), crate::Error> {
     let mut seen_items = HashSet::new();
 
+    // ores and reprocessed materials may not be used by any line
+    let reprocessing_items = db_reprocessing.flat_map(|r| {
+        r.materials.iter().copied().chain(iter::once((r.item, 0)))
+    });
+
     let mut db_volume_futs_1 = FuturesUnordered::new();
     let mut db_name_futs_1 = FuturesUnordered::new();
-    for line in db_lines {
-        for &(item, _) in line.minerals.iter() {
-            if seen_items.insert(item) {
-                db_volume_futs_1.push(
-                    db.get_volume(item).map_ok(move |volume| (item, volume)),
-                );
-                db_name_futs_1
-                    .push(db.get_name(item).map_ok(move |name| (item, name)));
-            }
+    for (item, _) in db_lines
+        .flat_map(|line| {
+            line.minerals
+                .iter()
+                .copied()
+                .chain(iter::once((line.product, 0)))
+        })
+        .chain(reprocessing_items)
+    {
+        if seen_items.insert(item) {
+            db_volume_futs_1
+                .push(db.get_volume(item).map_ok(move |volume| (item, volume)));
+            db_name_futs_1
+                .push(db.get_name(item).map_ok(move |name| (item, name)));
         }
     }
 
@@ -141,3 +387,55 @@ pub async fn get_db_volumes_and_names(
 
     Ok((db_volumes, db_names))
 }
+
+// market groups of each type, for price overrides matching them
+pub async fn get_db_market_groups(
+    type_ids: impl Iterator<Item = u32>,
+    db: &impl industry_db::IndustryDatabase,
+) -> Result<HashMap<u32, Vec<u32>>, crate::Error> {
+    let mut seen_type_ids = HashSet::new();
+    let mut db_market_group_futs = FuturesUnordered::new();
+    for type_id in type_ids {
+        if seen_type_ids.insert(type_id) {
+            db_market_group_futs.push(
+                db.get_market_groups(type_id)
+                    .map_ok(move |groups| (type_id, groups)),
+            );
+        }
+    }
+    let mut db_market_groups = HashMap::new();
+    while let Some((type_id, groups)) = db_market_group_futs.try_next().await? {
+        db_market_groups.insert(type_id, groups);
+    }
+    Ok(db_market_groups)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn production_line(character: &str) -> config::ProductionLine {
+        serde_yaml::from_str(&format!(
+            "{{id: 1, blueprint: {{type_id: 1101, runs: 10}}, \
+             product: {{type_id: 101}}, kind: Manufacturing, \
+             export_kind: Product, export_pipe_id: 1, parallel: 1, \
+             character: {}}}",
+            character
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn character_implants_by_name() {
+        let characters: HashMap<String, config::Character> =
+            serde_yaml::from_str("{alice: {implants: [27167]}, bob: {}}")
+                .unwrap();
+        let implants =
+            character_implants(&production_line("alice"), &characters);
+        assert_eq!(implants.unwrap(), &[27167]);
+        let implants = character_implants(&production_line("bob"), &characters);
+        assert!(implants.unwrap().is_empty());
+        let implants = character_implants(&production_line("eve"), &characters);
+        assert!(matches!(implants, Err(crate::Error::InvalidConfig(_))));
+    }
+}
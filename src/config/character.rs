@@ -0,0 +1,17 @@
+use serde::Deserialize;
+
+use super::*;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Character {
+    #[serde(default)]
+    pub implants: Vec<u32>, // implant type ids, looked up like skills
+    #[serde(default)]
+    pub slots: Option<IndustrySlots>, // jobs this character can run
+    #[serde(default)]
+    pub location_ids: Option<Vec<u64>>, // locations it can reach, any if none
+    #[serde(default)]
+    pub alpha_clone: Option<bool>, // the config's alpha_clone if none
+    #[serde(default)]
+    pub reprocessing_implant_bonus: f64, // e.g. 0.04 for RX-804
+}
@@ -1,4 +1,5 @@
 use serde::Deserialize;
+use std::time::Duration;
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct DeliveryRoute {
@@ -6,12 +7,16 @@ pub struct DeliveryRoute {
     pub service_name: String,
     #[serde(flatten)]
     pub rate: DeliveryRate,
+    #[serde(default)]
+    pub transit_time: Duration, // expected time from pickup to delivery
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct DeliveryRate {
     pub m3_rate: f64,         // m3_fee = m3_rate * m3
     pub collateral_rate: f64, // collateral_fee = collateral_rate * collateral
+    #[serde(skip)]
+    pub capital_rate: f64, // capital_fee = capital_rate * value, while in transit
 }
 
 impl DeliveryRate {
@@ -19,6 +24,7 @@ impl DeliveryRate {
         Self {
             m3_rate: 0.0,
             collateral_rate: 0.0,
+            capital_rate: 0.0,
         }
     }
 }
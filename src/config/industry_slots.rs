@@ -24,52 +24,62 @@ impl IndustrySlot {
     }
 }
 
-#[derive(Debug, Clone, Deserialize)]
+// slots of each kind, uncapped when omitted
+#[derive(Debug, Clone, Default, Deserialize)]
 pub struct IndustrySlots {
-    pub manufacturing: usize,
-    pub reaction: usize,
-    pub science: usize,
+    #[serde(default)]
+    pub manufacturing: Option<usize>,
+    #[serde(default)]
+    pub reaction: Option<usize>,
+    #[serde(default)]
+    pub science: Option<usize>,
 }
 
 impl IndustrySlots {
-    pub fn use_slot(&mut self, slot: IndustrySlot) -> bool {
+    fn slot(&mut self, slot: IndustrySlot) -> &mut Option<usize> {
         match slot {
-            IndustrySlot::Manufacturing => match self.manufacturing {
-                0 => false,
-                _ => {
-                    self.manufacturing -= 1;
-                    true
-                }
-            },
-            IndustrySlot::Reaction => match self.reaction {
-                0 => false,
-                _ => {
-                    self.reaction -= 1;
-                    true
-                }
-            },
-            IndustrySlot::Science => match self.science {
-                0 => false,
-                _ => {
-                    self.science -= 1;
-                    true
-                }
-            },
+            IndustrySlot::Manufacturing => &mut self.manufacturing,
+            IndustrySlot::Reaction => &mut self.reaction,
+            IndustrySlot::Science => &mut self.science,
         }
     }
 
-    pub fn can_use_slot(&self, slot: IndustrySlot) -> bool {
+    pub fn get(&self, slot: IndustrySlot) -> Option<usize> {
         match slot {
-            IndustrySlot::Manufacturing => self.manufacturing > 0,
-            IndustrySlot::Reaction => self.reaction > 0,
-            IndustrySlot::Science => self.science > 0,
+            IndustrySlot::Manufacturing => self.manufacturing,
+            IndustrySlot::Reaction => self.reaction,
+            IndustrySlot::Science => self.science,
+        }
+    }
+
+    pub fn use_slot(&mut self, slot: IndustrySlot) -> bool {
+        match self.slot(slot) {
+            None => true,
+            Some(0) => false,
+            Some(available) => {
+                *available -= 1;
+                true
+            }
         }
     }
 
+    pub fn can_use_slot(&self, slot: IndustrySlot) -> bool {
+        self.get(slot)
+            .map(|available| available > 0)
+            .unwrap_or(true)
+    }
+
     pub fn can_use_slots(&self, slots: &Self) -> bool {
-        self.manufacturing >= slots.manufacturing
-            && self.reaction >= slots.reaction
-            && self.science >= slots.science
+        [
+            IndustrySlot::Manufacturing,
+            IndustrySlot::Reaction,
+            IndustrySlot::Science,
+        ]
+        .into_iter()
+        .all(|slot| match self.get(slot) {
+            Some(available) => available >= slots.get(slot).unwrap_or(0),
+            None => true,
+        })
     }
 
     pub fn use_slot_unwrap(&mut self, slot: IndustrySlot) {
@@ -79,45 +89,31 @@ impl IndustrySlots {
     }
 
     pub fn available(&self, slot: IndustrySlot) -> bool {
-        match slot {
-            IndustrySlot::Manufacturing => match self.manufacturing {
-                0 => false,
-                _ => true,
-            },
-            IndustrySlot::Reaction => match self.reaction {
-                0 => false,
-                _ => true,
-            },
-            IndustrySlot::Science => match self.science {
-                0 => false,
-                _ => true,
-            },
-        }
+        self.can_use_slot(slot)
     }
 
     pub fn from_slot(slot: IndustrySlot) -> Self {
-        match slot {
-            IndustrySlot::Manufacturing => IndustrySlots {
-                manufacturing: 1,
-                reaction: 0,
-                science: 0,
-            },
-            IndustrySlot::Reaction => IndustrySlots {
-                manufacturing: 0,
-                reaction: 1,
-                science: 0,
-            },
-            IndustrySlot::Science => IndustrySlots {
-                manufacturing: 0,
-                reaction: 0,
-                science: 1,
-            },
-        }
+        let mut slots = IndustrySlots {
+            manufacturing: Some(0),
+            reaction: Some(0),
+            science: Some(0),
+        };
+        *slots.slot(slot) = Some(1);
+        slots
     }
 
+    // uncapped slots stay uncapped, omitted ones add nothing
     pub fn add(&mut self, slots: IndustrySlots) {
-        self.manufacturing += slots.manufacturing;
-        self.reaction += slots.reaction;
-        self.science += slots.science;
+        for slot in [
+            IndustrySlot::Manufacturing,
+            IndustrySlot::Reaction,
+            IndustrySlot::Science,
+        ] {
+            if let (Some(available), Some(added)) =
+                (self.slot(slot), slots.get(slot))
+            {
+                *available += added;
+            }
+        }
     }
 }
@@ -11,9 +11,11 @@ pub struct Location {
     pub production: Option<LocationProduction>,
     pub market: Option<LocationMarket>,
     #[serde(default)]
-    pub routes: HashMap<u64, DeliveryRoute>,
+    pub reprocessing: Option<LocationReprocessing>,
     #[serde(default)]
-    pub pipes: HashMap<u32, Vec<u32>>,
+    pub routes: BTreeMap<u64, DeliveryRoute>, // ordered, so runs are repeatable
+    #[serde(default)]
+    pub pipes: BTreeMap<u32, Vec<u32>>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -22,6 +24,8 @@ pub struct LocationProduction {
     pub rigs: [Option<u32>; 3],
     pub structure_type_id: u32,
     #[serde(default)]
+    pub slots: Option<IndustrySlots>, // our share of the structure's jobs
+    #[serde(default)]
     pub production_lines: Vec<ProductionLine>,
 }
 
@@ -31,12 +35,27 @@ pub struct LocationMarket {
     pub brokers_fee: f64,
 }
 
+#[derive(Debug, Clone, Deserialize)]
+pub struct LocationReprocessing {
+    pub tax: f64, // portion of the estimated value of the materials
+    #[serde(default)]
+    pub structure_bonus: f64, // e.g. 0.055 for a tatara
+    #[serde(default)]
+    pub rig_yield: f64, // 1.0 for a T1 rig, 3.0 for a T2 rig
+    #[serde(default)]
+    pub character: Option<String>, // whose clone reprocesses, for its implant
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
 pub struct ManufacturingValue {
     manufacturing: f64,
     invention: f64,
     reaction: f64,
     copy: f64,
+    #[serde(default)]
+    research_me: f64,
+    #[serde(default)]
+    research_te: f64,
 }
 
 impl ManufacturingValue {
@@ -46,6 +65,8 @@ impl ManufacturingValue {
             ManufacturingKind::Invention => self.invention,
             ManufacturingKind::Reaction => self.reaction,
             ManufacturingKind::Copy => self.copy,
+            ManufacturingKind::ResearchMe => self.research_me,
+            ManufacturingKind::ResearchTe => self.research_te,
         }
     }
 }
@@ -1,27 +1,52 @@
 use serde::Deserialize;
-use std::{collections::HashMap, time::Duration};
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::Duration,
+};
 
+mod character;
 mod delivery_route;
 mod industry_slots;
 mod item;
 mod location;
+mod objective;
 mod production_line;
+mod scenario;
 
+pub use character::*;
 pub use delivery_route::*;
 pub use industry_slots::*;
 pub use item::*;
 pub use location::*;
+pub use objective::*;
 pub use production_line::*;
+pub use scenario::*;
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct Config {
     pub locations: Vec<Location>,
     pub skills: HashMap<u32, u8>,
+    #[serde(default)]
+    pub characters: HashMap<String, Character>,
     pub slots: IndustrySlots,
     pub max_time: Duration, // time that all production lines are running for
     pub daily_flex_time: Duration, // extra time required for daily startables under 24 hours
     pub min_profit: f64,
     pub min_margin: f64,
+    #[serde(default)]
+    pub objective: Objective,
+    #[serde(default = "default_scc_surcharge")]
+    pub scc_surcharge: f64, // portion of job EIV paid to the SCC
+    #[serde(default)]
+    pub capital_cost: f64, // daily cost of the value tied up in transit
+    #[serde(default)]
+    pub alpha_clone: bool, // for lines without an alpha or omega character
+    #[serde(default)]
+    pub reprocessing_ores: Vec<u32>, // ores that may be bought instead of minerals
+}
+
+fn default_scc_surcharge() -> f64 {
+    0.04
 }
 
 impl Config {
@@ -30,4 +55,26 @@ impl Config {
             "config.yaml",
         )?)?)
     }
+
+    pub fn settings(&self) -> Settings {
+        Settings {
+            max_time: self.max_time,
+            daily_flex_time: self.daily_flex_time,
+            scc_surcharge: self.scc_surcharge,
+            capital_cost: self.capital_cost,
+            alpha_clone: self.alpha_clone,
+            objective: self.objective,
+        }
+    }
+}
+
+// the settings every line of a plan is computed with
+#[derive(Debug, Clone, Copy)]
+pub struct Settings {
+    pub max_time: Duration,
+    pub daily_flex_time: Duration,
+    pub scc_surcharge: f64,
+    pub capital_cost: f64,
+    pub alpha_clone: bool,
+    pub objective: Objective,
 }
@@ -0,0 +1,11 @@
+use super::*;
+
+// what the planner maximizes when picking builds
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+pub enum Objective {
+    #[default]
+    Profit,
+    ProfitPerSlotHour, // over the slots of the build and its intermediates
+    ReturnOnCapital,   // profit / cost
+    ProfitWithMinRoi(f64), // profit, if profit / cost is at least this
+}
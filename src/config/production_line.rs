@@ -13,8 +13,25 @@ pub struct ProductionLine {
     pub import_src_market_pipe_ids: Vec<u32>,
     #[serde(default)]
     pub import_src_production_line_ids: HashMap<u32, u32>,
+    #[serde(default)]
+    pub import_src_blueprint_production_line_ids: Vec<u32>, // copy or invention
     pub decryptor: Option<u32>,
+    #[serde(default)]
+    pub auto_decryptor: bool, // pick the most profitable decryptor, or none
     pub parallel: i64,
+    #[serde(default)]
+    pub invention_target: Option<InventionTarget>,
+    #[serde(default)]
+    pub relic: bool, // the blueprint is a T3 relic, of its type's quality
+    #[serde(default)]
+    pub character: Option<String>, // whose clone installs the job
+}
+
+// number of BPCs an invention line should produce with some confidence
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct InventionTarget {
+    pub bpcs: i64,
+    pub confidence: f64, // 0.0 - 1.0
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
@@ -29,6 +46,8 @@ pub enum ManufacturingKind {
     Invention,
     Reaction,
     Copy,
+    ResearchMe,
+    ResearchTe,
 }
 
 impl ManufacturingKind {
@@ -36,10 +55,19 @@ impl ManufacturingKind {
         match self {
             ManufacturingKind::Invention => true,
             ManufacturingKind::Copy => true,
+            ManufacturingKind::ResearchMe => true,
+            ManufacturingKind::ResearchTe => true,
             ManufacturingKind::Manufacturing => false,
             ManufacturingKind::Reaction => false,
         }
     }
+
+    pub const fn is_research(&self) -> bool {
+        matches!(
+            self,
+            ManufacturingKind::ResearchMe | ManufacturingKind::ResearchTe
+        )
+    }
 }
 
 impl Into<IndustrySlot> for ManufacturingKind {
@@ -47,6 +75,8 @@ impl Into<IndustrySlot> for ManufacturingKind {
         match self {
             ManufacturingKind::Copy => IndustrySlot::Science,
             ManufacturingKind::Invention => IndustrySlot::Science,
+            ManufacturingKind::ResearchMe => IndustrySlot::Science,
+            ManufacturingKind::ResearchTe => IndustrySlot::Science,
             ManufacturingKind::Reaction => IndustrySlot::Reaction,
             ManufacturingKind::Manufacturing => IndustrySlot::Manufacturing,
         }
@@ -0,0 +1,228 @@
+use serde::Deserialize;
+
+use super::*;
+
+// a what-if run, overrides layered on top of the config and API data
+#[derive(Debug, Clone, Deserialize)]
+pub struct Scenario {
+    pub name: String,
+    #[serde(default)]
+    pub slots: Option<IndustrySlots>, // replaces the configured slots
+    #[serde(default)]
+    pub extra_slots: Option<IndustrySlots>, // added to every capped pool
+    #[serde(default)]
+    pub prices: Vec<PriceOverride>,
+    #[serde(default)]
+    pub production_taxes: HashMap<u64, ManufacturingValue>, // by location id
+    #[serde(default)]
+    pub market_taxes: HashMap<u64, LocationMarket>, // by location id
+    #[serde(default)]
+    pub add_production_lines: HashMap<u64, Vec<ProductionLine>>, // by location id
+    #[serde(default)]
+    pub remove_production_line_ids: Vec<u32>,
+}
+
+// scales market order prices, matching every type or market if left empty
+// a type matches if it's listed, or under one of the market groups
+#[derive(Debug, Clone, Deserialize)]
+pub struct PriceOverride {
+    #[serde(default)]
+    pub type_ids: Vec<u32>,
+    #[serde(default)]
+    pub market_group_ids: Vec<u32>, // any level, e.g. Minerals or Materials
+    #[serde(default)]
+    pub location_ids: Vec<u64>,
+    pub multiplier: f64,
+}
+
+impl Scenario {
+    pub fn read_all() -> Result<Vec<Self>, Box<dyn std::error::Error>> {
+        Ok(serde_yaml::from_reader(std::fs::File::open(
+            "scenarios.yaml",
+        )?)?)
+    }
+
+    // the config as it would be in this scenario
+    pub fn apply(&self, cfg: &Config) -> Config {
+        let mut cfg = cfg.clone();
+        if let Some(slots) = &self.slots {
+            cfg.slots = slots.clone();
+        }
+        // location and character pools are raised too, so they don't cap
+        // the extra slots away
+        if let Some(extra_slots) = &self.extra_slots {
+            cfg.slots.add(extra_slots.clone());
+            for character in cfg.characters.values_mut() {
+                if let Some(slots) = &mut character.slots {
+                    slots.add(extra_slots.clone());
+                }
+            }
+            for location in cfg.locations.iter_mut() {
+                if let Some(slots) = location
+                    .production
+                    .as_mut()
+                    .and_then(|production| production.slots.as_mut())
+                {
+                    slots.add(extra_slots.clone());
+                }
+            }
+        }
+        for location in cfg.locations.iter_mut() {
+            if let (Some(market), Some(taxes)) =
+                (&mut location.market, self.market_taxes.get(&location.id))
+            {
+                market.sales_tax = taxes.sales_tax;
+                market.brokers_fee = taxes.brokers_fee;
+            }
+            let production = match &mut location.production {
+                Some(production) => production,
+                None => continue,
+            };
+            if let Some(tax) = self.production_taxes.get(&location.id) {
+                production.tax = *tax;
+            }
+            production
+                .production_lines
+                .retain(|pl| !self.remove_production_line_ids.contains(&pl.id));
+            if let Some(lines) = self.add_production_lines.get(&location.id) {
+                production.production_lines.extend(lines.iter().cloned());
+            }
+        }
+        cfg
+    }
+
+    pub fn uses_market_groups(&self) -> bool {
+        self.prices.iter().any(|p| !p.market_group_ids.is_empty())
+    }
+
+    pub fn price_multiplier(
+        &self,
+        location_id: u64,
+        type_id: u32,
+        market_groups: &[u32], // of the type, with their parents
+    ) -> f64 {
+        self.prices
+            .iter()
+            .filter(|p| {
+                (p.type_ids.is_empty() && p.market_group_ids.is_empty())
+                    || p.type_ids.contains(&type_id)
+                    || p.market_group_ids
+                        .iter()
+                        .any(|group| market_groups.contains(group))
+            })
+            .filter(|p| {
+                p.location_ids.is_empty()
+                    || p.location_ids.contains(&location_id)
+            })
+            .map(|p| p.multiplier)
+            .product()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = "
+locations:
+  - id: 1
+    name: hub
+    system_id: 10
+    market: {sales_tax: 0.036, brokers_fee: 0.015}
+  - id: 2
+    name: factory
+    system_id: 20
+    production:
+      tax: {manufacturing: 0.01, invention: 0.01, reaction: 0.01, copy: 0.01}
+      rigs: [null, null, null]
+      structure_type_id: 35825
+      slots: {manufacturing: 4, science: 2}
+      production_lines:
+        - {id: 1, blueprint: {type_id: 1101, runs: 10}, product: {type_id: 101},
+           kind: Manufacturing, export_kind: Product, export_pipe_id: 1,
+           parallel: 1}
+        - {id: 2, blueprint: {type_id: 1102, runs: 10}, product: {type_id: 102},
+           kind: Manufacturing, export_kind: Product, export_pipe_id: 1,
+           parallel: 1}
+skills: {}
+characters:
+  alice: {slots: {manufacturing: 10, reaction: 0}}
+  bob: {}
+slots: {manufacturing: 20, reaction: 5}
+max_time: {secs: 604800, nanos: 0}
+daily_flex_time: {secs: 3600, nanos: 0}
+min_profit: 0.0
+min_margin: 1.0
+";
+
+    fn scenario(yaml: &str) -> Scenario {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn apply_extra_slots_to_every_pool() {
+        let cfg: Config = serde_yaml::from_str(CONFIG).unwrap();
+        // only manufacturing is given
+        let cfg = scenario("{name: more, extra_slots: {manufacturing: 2}}")
+            .apply(&cfg);
+        assert_eq!(cfg.slots.manufacturing, Some(22));
+        assert_eq!(cfg.slots.reaction, Some(5));
+        assert_eq!(cfg.slots.science, None);
+        let alice = cfg.characters["alice"].slots.as_ref().unwrap();
+        assert_eq!(alice.manufacturing, Some(12));
+        assert_eq!(alice.reaction, Some(0));
+        assert!(cfg.characters["bob"].slots.is_none());
+        let production = cfg.locations[1].production.as_ref().unwrap();
+        let factory = production.slots.as_ref().unwrap();
+        assert_eq!(factory.manufacturing, Some(6));
+        assert_eq!(factory.science, Some(2));
+    }
+
+    #[test]
+    fn apply_overrides() {
+        let cfg: Config = serde_yaml::from_str(CONFIG).unwrap();
+        let cfg = scenario(
+            "{name: moved, slots: {manufacturing: 1},
+              market_taxes: {1: {sales_tax: 0.02, brokers_fee: 0.01}},
+              production_taxes: {2: {manufacturing: 0.05, invention: 0.05,
+                                     reaction: 0.05, copy: 0.05}},
+              remove_production_line_ids: [1]}",
+        )
+        .apply(&cfg);
+        assert_eq!(cfg.slots.manufacturing, Some(1));
+        assert_eq!(cfg.slots.reaction, None);
+        let market = cfg.locations[0].market.as_ref().unwrap();
+        assert_eq!(market.sales_tax, 0.02);
+        assert_eq!(market.brokers_fee, 0.01);
+        let production = cfg.locations[1].production.as_ref().unwrap();
+        let tax = production.tax.kind_value(ManufacturingKind::Manufacturing);
+        assert_eq!(tax, 0.05);
+        let ids = production
+            .production_lines
+            .iter()
+            .map(|pl| pl.id)
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec![2]);
+    }
+
+    #[test]
+    fn price_multiplier_matches() {
+        // 18 is Minerals, under 1857 Materials
+        let scenario = scenario(
+            "{name: prices, prices: [
+                {multiplier: 1.1},
+                {type_ids: [34], multiplier: 2.0},
+                {market_group_ids: [1857], location_ids: [1], multiplier: 0.5}
+            ]}",
+        );
+        assert!(scenario.uses_market_groups());
+        let multiplier = scenario.price_multiplier(1, 34, &[18, 1857]);
+        assert!((multiplier - 1.1).abs() < 1e-9);
+        let multiplier = scenario.price_multiplier(2, 34, &[18, 1857]);
+        assert!((multiplier - 2.2).abs() < 1e-9);
+        let multiplier = scenario.price_multiplier(1, 35, &[18, 1857]);
+        assert!((multiplier - 0.55).abs() < 1e-9);
+        let multiplier = scenario.price_multiplier(1, 101, &[]);
+        assert!((multiplier - 1.1).abs() < 1e-9);
+    }
+}
@@ -3,7 +3,7 @@ pub type StdError = Box<dyn std::error::Error>;
 #[derive(Debug)]
 pub enum Error {
     IndustryDbError(Box<dyn std::error::Error>),
-    Unimplemented,
+    InvalidConfig(String),
 }
 
 impl std::fmt::Display for Error {
@@ -0,0 +1,352 @@
+use super::*;
+use serde::Serialize;
+use std::collections::HashMap;
+
+// the latest plan against the jobs and transactions since it was made
+#[derive(Serialize)]
+pub struct Comparison {
+    pub plan_id: i64,
+    pub created_at: String,
+    pub inputs_hash: String,
+    pub expected_profit: f64,
+    pub realized_profit: f64,
+    pub lines: Vec<LineComparison>,
+}
+
+// actuals are matched to the plan's own locations, and split between the
+// lines they could belong to, by planned runs or material quantities
+#[derive(Serialize)]
+pub struct LineComparison {
+    pub production_line_id: i64,
+    pub product: String,
+    pub planned_builds: i64,
+    pub planned_runs: i64,
+    pub actual_jobs: f64,
+    pub actual_runs: f64,
+    pub planned_installation_cost: f64,
+    pub actual_installation_cost: f64,
+    // None for intermediates, their profit is in their consumers
+    pub expected_profit: Option<f64>,
+    // the expected profit, repriced at the actual average prices
+    pub realized_profit: Option<f64>,
+    pub planned_sell_price: Option<f64>,
+    pub actual_sell_price: Option<f64>, // None if nothing was sold
+    pub sold: f64,
+    pub materials: Vec<MaterialComparison>,
+}
+
+#[derive(Serialize)]
+pub struct MaterialComparison {
+    pub item: String,
+    pub quantity: f64, // per build
+    pub planned_price: f64,
+    pub actual_price: Option<f64>, // None if nothing was bought
+    pub bought: f64,
+}
+
+impl History {
+    pub async fn compare_latest(&self) -> sqlx::Result<Option<Comparison>> {
+        let mut conn = self.inner.acquire().await?;
+        let plan =
+            match sqlx::query_file!("sqlite_build_data/select_latest_plan.sql")
+                .fetch_optional(&mut *conn)
+                .await?
+            {
+                Some(plan) => plan,
+                None => return Ok(None),
+            };
+        let since = plan.created_at.as_str();
+
+        let builds = sqlx::query_file!(
+            "sqlite_build_data/select_plan_builds.sql",
+            plan.plan_id,
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let mut build_materials = Vec::new();
+        for build in builds.iter() {
+            build_materials.push(
+                sqlx::query_file!(
+                    "sqlite_build_data/select_plan_materials.sql",
+                    plan.plan_id,
+                    build.production_line_id,
+                )
+                .fetch_all(&mut *conn)
+                .await?,
+            );
+        }
+
+        // what each actual is split by
+        let mut job_runs = HashMap::new();
+        let mut sale_runs = HashMap::new();
+        let mut material_quantities = HashMap::new();
+        for (build, materials) in builds.iter().zip(build_materials.iter()) {
+            *job_runs
+                .entry((build.location_id, build.product_type_id))
+                .or_insert(0) += build.runs;
+            if let Some(market_location_id) = build.market_location_id {
+                *sale_runs
+                    .entry((market_location_id, build.product_type_id))
+                    .or_insert(0) += build.runs;
+            }
+            for material in materials.iter() {
+                *material_quantities.entry(material.type_id).or_insert(0.0) +=
+                    material.quantity * build.builds as f64;
+            }
+        }
+        let share = |part: f64, whole: f64| match whole > 0.0 {
+            true => part / whole,
+            false => 0.0,
+        };
+
+        let mut lines = Vec::new();
+        for (build, plan_materials) in builds.into_iter().zip(build_materials) {
+            let jobs = sqlx::query_file!(
+                "sqlite_build_data/select_actual_jobs.sql",
+                build.location_id,
+                build.product_type_id,
+                since,
+            )
+            .fetch_one(&mut *conn)
+            .await?;
+            let job_share = share(
+                build.runs as f64,
+                job_runs[&(build.location_id, build.product_type_id)] as f64,
+            );
+
+            let (sold, actual_sell_price) = match build.market_location_id {
+                Some(market_location_id) => {
+                    let sales = sqlx::query_file!(
+                        "sqlite_build_data/select_actual_sales.sql",
+                        build.product_type_id,
+                        market_location_id,
+                        since,
+                    )
+                    .fetch_one(&mut *conn)
+                    .await?;
+                    let sale_share = share(
+                        build.runs as f64,
+                        sale_runs[&(market_location_id, build.product_type_id)]
+                            as f64,
+                    );
+                    let (sold, price) =
+                        average_price(sales.quantity, sales.value);
+                    (sold * sale_share, price)
+                }
+                None => (0.0, None),
+            };
+
+            let mut materials = Vec::new();
+            for material in plan_materials {
+                let purchases = sqlx::query_file!(
+                    "sqlite_build_data/select_actual_purchases.sql",
+                    material.type_id,
+                    since,
+                    plan.plan_id,
+                )
+                .fetch_one(&mut *conn)
+                .await?;
+                let (bought, actual_price) =
+                    average_price(purchases.quantity, purchases.value);
+                let material_share = share(
+                    material.quantity * build.builds as f64,
+                    material_quantities[&material.type_id],
+                );
+                materials.push(MaterialComparison {
+                    item: material.name,
+                    quantity: material.quantity,
+                    planned_price: material.price,
+                    actual_price,
+                    bought: bought * material_share,
+                });
+            }
+
+            let mut line = LineComparison {
+                production_line_id: build.production_line_id,
+                product: build.product_name,
+                planned_builds: build.builds,
+                planned_runs: build.runs,
+                actual_jobs: jobs.jobs as f64 * job_share,
+                actual_runs: jobs.runs as f64 * job_share,
+                planned_installation_cost: build.installation_cost,
+                actual_installation_cost: jobs.cost * job_share,
+                expected_profit: build.expected_profit,
+                realized_profit: None,
+                planned_sell_price: build.sell_price,
+                actual_sell_price,
+                sold,
+                materials,
+            };
+            line.realized_profit = match (
+                build.expected_profit,
+                build.sell_price,
+                build.revenue_per_price,
+            ) {
+                (Some(profit), Some(sell_price), Some(revenue_per_price)) => {
+                    Some(line.realized_profit(
+                        profit,
+                        sell_price,
+                        revenue_per_price,
+                    ))
+                }
+                _ => None,
+            };
+            lines.push(line);
+        }
+
+        Ok(Some(Comparison {
+            plan_id: plan.plan_id,
+            created_at: plan.created_at,
+            inputs_hash: plan.inputs_hash,
+            expected_profit: plan.expected_profit,
+            realized_profit: lines
+                .iter()
+                .filter_map(|l| l.realized_profit)
+                .sum(),
+            lines,
+        }))
+    }
+}
+
+impl LineComparison {
+    // prices without transactions are taken as planned, and installation
+    // costs are compared per run, so unfinished plans aren't losses
+    fn realized_profit(
+        &self,
+        profit: f64,
+        sell_price: f64,
+        revenue_per_price: f64,
+    ) -> f64 {
+        let builds = self.planned_builds as f64;
+        let sell_change =
+            self.actual_sell_price.unwrap_or(sell_price) - sell_price;
+        let material_change = self
+            .materials
+            .iter()
+            .map(|m| {
+                (m.actual_price.unwrap_or(m.planned_price) - m.planned_price)
+                    * m.quantity
+            })
+            .sum::<f64>();
+        let installation_change = match self.actual_runs > 0.0 {
+            true => {
+                self.actual_installation_cost
+                    - self.planned_installation_cost * self.actual_runs
+                        / self.planned_runs as f64
+            }
+            false => 0.0,
+        };
+        profit + (sell_change * revenue_per_price - material_change) * builds
+            - installation_change
+    }
+}
+
+impl Comparison {
+    pub fn print(&self) {
+        println!(
+            "Plan {} from {}, expected {:.2}, realized {:.2}",
+            self.plan_id,
+            self.created_at,
+            self.expected_profit,
+            self.realized_profit,
+        );
+        println!(
+            "{:<40} {:>18} {:>18} {:>10}",
+            "product", "expected", "realized", "runs"
+        );
+        for line in self.lines.iter() {
+            let (Some(expected), Some(realized)) =
+                (line.expected_profit, line.realized_profit)
+            else {
+                continue;
+            };
+            println!(
+                "{:<40} {:>18.2} {:>18.2} {:>4.0}/{:<5}",
+                line.product,
+                expected,
+                realized,
+                line.actual_runs,
+                line.planned_runs,
+            );
+        }
+    }
+
+    pub fn write(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let file = std::fs::File::create("comparison.json")?;
+        serde_json::to_writer_pretty(file, self)?;
+        Ok(())
+    }
+}
+
+// (quantity, average unit price) of matching transactions
+fn average_price(quantity: i64, value: f64) -> (f64, Option<f64>) {
+    match quantity {
+        0 => (0.0, None),
+        quantity => (quantity as f64, Some(value / quantity as f64)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2 builds of 10 runs, 4,000 ISK expected for both
+    fn line(
+        actual_runs: f64,
+        actual_installation_cost: f64,
+        actual_sell_price: Option<f64>,
+        actual_material_price: Option<f64>,
+    ) -> LineComparison {
+        LineComparison {
+            production_line_id: 1,
+            product: "Product".to_string(),
+            planned_builds: 2,
+            planned_runs: 20,
+            actual_jobs: actual_runs / 10.0,
+            actual_runs,
+            planned_installation_cost: 1_000.0,
+            actual_installation_cost,
+            expected_profit: Some(4_000.0),
+            realized_profit: None,
+            planned_sell_price: Some(1_000.0),
+            actual_sell_price,
+            sold: 0.0,
+            materials: vec![
+                MaterialComparison {
+                    item: "Tritanium".to_string(),
+                    quantity: 500.0,
+                    planned_price: 5.0,
+                    actual_price: actual_material_price,
+                    bought: 0.0,
+                },
+                MaterialComparison {
+                    item: "Pyerite".to_string(),
+                    quantity: 100.0,
+                    planned_price: 10.0,
+                    actual_price: None,
+                    bought: 0.0,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn realized_profit_as_planned() {
+        // nothing happened yet, or everything went to plan
+        let untouched = line(0.0, 0.0, None, None);
+        assert_eq!(untouched.realized_profit(4_000.0, 1_000.0, 9.0), 4_000.0);
+        let done = line(20.0, 1_000.0, Some(1_000.0), Some(5.0));
+        assert_eq!(done.realized_profit(4_000.0, 1_000.0, 9.0), 4_000.0);
+    }
+
+    #[test]
+    fn realized_profit_repriced() {
+        // sold 50 ISK lower, 9 ISK of revenue each, tritanium 0.5 ISK
+        // higher, half the runs installed for 100 ISK more than planned
+        let line = line(10.0, 600.0, Some(950.0), Some(5.5));
+        let realized = line.realized_profit(4_000.0, 1_000.0, 9.0);
+        let expected = 4_000.0 + (-50.0 * 9.0 - 0.5 * 500.0) * 2.0 - 100.0;
+        assert!((realized - expected).abs() < 1e-9, "{}", realized);
+    }
+}
@@ -0,0 +1,213 @@
+use crate::{api_data, runtime::PlanRecord};
+use sha2::{Digest, Sha256};
+use sqlx::{self, SqlitePool};
+
+mod compare;
+
+// what a plan is made from, besides the static database
+const INPUT_FILES: [&str; 5] = [
+    "config.yaml",
+    "adjusted_prices.json",
+    "cost_indices.json",
+    "market_orders.json",
+    "assets.json",
+];
+
+// the plans made so far, and what actually happened since
+pub struct History {
+    inner: SqlitePool,
+}
+
+impl History {
+    pub async fn connect() -> sqlx::Result<History> {
+        let inner =
+            SqlitePool::connect("sqlite:history.sqlite?mode=rwc").await?;
+        sqlx::raw_sql(include_str!(
+            "../../sqlite_build_data/create_history.sql"
+        ))
+        .execute(&inner)
+        .await?;
+        Ok(History { inner })
+    }
+
+    pub async fn record_plan(
+        &self,
+        inputs_hash: &str,
+        record: &PlanRecord,
+    ) -> sqlx::Result<i64> {
+        let mut tx = self.inner.begin().await?;
+        let plan_id = sqlx::query_file!(
+            "sqlite_build_data/insert_plan.sql",
+            inputs_hash,
+            record.profit,
+            record.cost,
+            record.output,
+        )
+        .fetch_one(&mut *tx)
+        .await?
+        .plan_id;
+
+        for build in record.builds.iter() {
+            let location_id = build.location_id as i64;
+            let market_location_id =
+                build.market_location_id.map(|id| id as i64);
+            sqlx::query_file!(
+                "sqlite_build_data/insert_plan_build.sql",
+                plan_id,
+                build.production_line_id,
+                location_id,
+                market_location_id,
+                build.product.type_id,
+                build.product_name,
+                build.builds,
+                build.runs,
+                build.installation_cost,
+                build.profit,
+                build.sell_price,
+                build.revenue_per_price,
+            )
+            .execute(&mut *tx)
+            .await?;
+            for material in build.materials.iter() {
+                sqlx::query_file!(
+                    "sqlite_build_data/insert_plan_material.sql",
+                    plan_id,
+                    build.production_line_id,
+                    material.item.type_id,
+                    material.name,
+                    material.quantity,
+                    material.price,
+                )
+                .execute(&mut *tx)
+                .await?;
+            }
+        }
+
+        for purchase in record.purchases.iter() {
+            let location_id = purchase.location_id as i64;
+            sqlx::query_file!(
+                "sqlite_build_data/insert_plan_purchase.sql",
+                plan_id,
+                location_id,
+                purchase.type_id,
+                purchase.quantity,
+                purchase.cost,
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        for delivery in record.deliveries.iter() {
+            let (src_location_id, dst_location_id) = (
+                delivery.src_location_id as i64,
+                delivery.dst_location_id as i64,
+            );
+            sqlx::query_file!(
+                "sqlite_build_data/insert_plan_delivery.sql",
+                plan_id,
+                src_location_id,
+                dst_location_id,
+                delivery.service_name,
+                delivery.item.type_id,
+                delivery.quantity,
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(plan_id)
+    }
+
+    // already known jobs and transactions are skipped
+    pub async fn ingest(
+        &self,
+        jobs: &[api_data::IndustryJob],
+        transactions: &[api_data::WalletTransaction],
+    ) -> sqlx::Result<()> {
+        let mut tx = self.inner.begin().await?;
+        for job in jobs.iter() {
+            // jobs without a product, like research, are never planned
+            let product_type_id = match job.product_type_id {
+                Some(product_type_id) => product_type_id,
+                None => continue,
+            };
+            let facility_id = job.facility_id as i64;
+            let cost = job.cost.unwrap_or(0.0);
+            sqlx::query_file!(
+                "sqlite_build_data/insert_industry_job.sql",
+                job.job_id,
+                facility_id,
+                product_type_id,
+                job.runs,
+                cost,
+                job.start_date,
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        for transaction in transactions.iter() {
+            let location_id = transaction.location_id as i64;
+            sqlx::query_file!(
+                "sqlite_build_data/insert_wallet_transaction.sql",
+                transaction.transaction_id,
+                transaction.date,
+                location_id,
+                transaction.type_id,
+                transaction.quantity,
+                transaction.unit_price,
+                transaction.is_buy,
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await
+    }
+}
+
+// changes whenever any of the input files do, the same across builds
+pub fn inputs_hash() -> std::io::Result<String> {
+    let mut contents = Vec::new();
+    for path in INPUT_FILES {
+        contents.push((path, std::fs::read(path)?));
+    }
+    Ok(hash_inputs(&contents))
+}
+
+fn hash_inputs(contents: &[(&str, Vec<u8>)]) -> String {
+    let mut hasher = Sha256::new();
+    for (path, content) in contents {
+        // lengths first, so content can't shift between files
+        hasher.update((path.len() as u64).to_le_bytes());
+        hasher.update(path.as_bytes());
+        hasher.update((content.len() as u64).to_le_bytes());
+        hasher.update(content);
+    }
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inputs_hash_is_stable() {
+        let contents = [("config.yaml", b"slots: {}".to_vec())];
+        assert_eq!(
+            hash_inputs(&contents),
+            "81a8a71655a2f43332a6a4917bc83bc3270b02f7ce5edb5311bb695623025fe9"
+        );
+    }
+
+    #[test]
+    fn inputs_hash_tells_files_apart() {
+        let a = [("a", b"bc".to_vec()), ("d", Vec::new())];
+        let b = [("a", b"b".to_vec()), ("cd", Vec::new())];
+        assert_ne!(hash_inputs(&a), hash_inputs(&b));
+        assert_eq!(hash_inputs(&a).len(), 64);
+    }
+}
@@ -5,6 +5,8 @@ pub struct DatabaseParamsInclude {
     pub installation_minerals: bool,
     pub efficiencies: bool,
     pub security: bool, // if this is false, then system_id parameter is ignored
+    pub invention_skills: bool, // ignored unless kind is invention
+    pub decryptors: bool, // ignored unless kind is invention
 }
 
 pub trait InnerDatabase: Send + Sync {
@@ -17,11 +19,27 @@ pub trait InnerDatabase: Send + Sync {
         system_id: u32, // ignored if include_security is false
         include: DatabaseParamsInclude,
     ) -> Result<DatabaseResponse, Self::Error>;
+    async fn get_fitting(
+        &self,
+        structure_id: u32,
+        rigs: [Option<u32>; 3],
+        system_id: u32,
+    ) -> Result<FittingResponse, Self::Error>;
+    async fn get_reprocessing(
+        &self,
+        item: u32,
+        system_id: u32,
+    ) -> Result<Option<ReprocessingResponse>, Self::Error>;
     async fn get_volume(
         &self,
         item: u32,
     ) -> Result<Option<Volume>, Self::Error>;
     async fn get_name(&self, item: u32) -> Result<String, Self::Error>;
+    // the type's market group and each of its parents
+    async fn get_market_groups(
+        &self,
+        item: u32,
+    ) -> Result<Vec<u32>, Self::Error>;
 }
 
 impl<T> IndustryDatabase for T
@@ -37,12 +55,15 @@ where
         tax: config::ManufacturingValue,
         // config
         skills: &HashMap<u32, u8>,
+        implants: &[u32],
         // production line
         kind: config::ManufacturingKind,
         transput: config::Transput,
         max_duration: Duration,
         decryptor: Option<u32>,
+        relic: bool,
     ) -> Result<Line, crate::Error> {
+        let relic = relic_quality(self, transput.blueprint, relic).await?;
         let rep = self
             .get(
                 transput.product.type_id,
@@ -54,17 +75,130 @@ where
             .await
             .map_err(|e| crate::Error::IndustryDbError(e.into()))?;
         Line::from_rep(
-            rep,
+            &rep,
             structure_id,
             rigs,
             tax,
             skills,
-            kind,
-            transput,
-            max_duration,
-            decryptor,
+            implants,
+            JobParams {
+                kind,
+                transput,
+                max_duration,
+                decryptor,
+                relic,
+            },
         )
     }
+    async fn compute_decryptor_lines(
+        &self,
+        // location config
+        system_id: u32,
+        structure_id: u32,
+        rigs: [Option<u32>; 3],
+        tax: config::ManufacturingValue,
+        // config
+        skills: &HashMap<u32, u8>,
+        implants: &[u32],
+        // production line
+        transput: config::Transput,
+        max_duration: Duration,
+        relic: bool,
+    ) -> Result<Vec<Line>, crate::Error> {
+        let kind = ManufacturingKind::Invention;
+        let relic = relic_quality(self, transput.blueprint, relic).await?;
+        let rep = self
+            .get(
+                transput.product.type_id,
+                transput.blueprint,
+                kind,
+                system_id,
+                Line::database_params_include(),
+            )
+            .await
+            .map_err(|e| crate::Error::IndustryDbError(e.into()))?;
+        let base_product = invention_base_product(rep.product, relic);
+        // no decryptor, followed by every decryptor
+        // decryptors that can't be used for this blueprint are skipped
+        let mut lines = Vec::new();
+        let mut first_err = None;
+        for line in iter::once(None)
+            .chain(rep.decryptors.iter().map(|&(d, _)| Some(d)))
+            .map(|decryptor| {
+                let product = match decryptor {
+                    Some(decryptor) => {
+                        decryptor_product(base_product, decryptor)
+                    }
+                    None => base_product,
+                };
+                Line::from_rep(
+                    &rep,
+                    structure_id,
+                    rigs,
+                    tax,
+                    skills,
+                    implants,
+                    JobParams {
+                        kind,
+                        transput: Transput::new(transput.blueprint, product),
+                        max_duration,
+                        decryptor: decryptor.map(|d| d.type_id),
+                        relic,
+                    },
+                )
+            })
+        {
+            match line {
+                Ok(line) => lines.push(line),
+                Err(e) => {
+                    first_err.get_or_insert(e);
+                }
+            }
+        }
+        match (lines.is_empty(), first_err) {
+            (true, Some(e)) => Err(e),
+            _ => Ok(lines),
+        }
+    }
+    async fn validate_fitting(
+        &self,
+        // location config
+        system_id: u32,
+        structure_id: u32,
+        rigs: [Option<u32>; 3],
+        // production lines
+        kinds: &[config::ManufacturingKind],
+    ) -> Result<(), crate::Error> {
+        self.get_fitting(structure_id, rigs, system_id)
+            .await
+            .map_err(|e| crate::Error::IndustryDbError(e.into()))?
+            .validate(structure_id, kinds)
+            .map_err(crate::Error::InvalidConfig)
+    }
+    async fn compute_reprocessing(
+        &self,
+        // location config
+        system_id: u32,
+        reprocessing: &config::LocationReprocessing,
+        // config
+        skills: &HashMap<u32, u8>,
+        implant_bonus: f64,
+        item: Item,
+    ) -> Result<Option<Reprocessing>, crate::Error> {
+        let rep = self
+            .get_reprocessing(item.type_id, system_id)
+            .await
+            .map_err(|e| crate::Error::IndustryDbError(e.into()))?;
+        Ok(rep.map(|rep| {
+            Reprocessing::from_rep(
+                &rep,
+                item,
+                reprocessing,
+                skills,
+                implant_bonus,
+            )
+        }))
+    }
     async fn get_volume(
         &self,
         item: Item,
@@ -86,4 +220,182 @@ where
             false => name,
         })
     }
+    async fn get_market_groups(
+        &self,
+        type_id: u32,
+    ) -> Result<Vec<u32>, crate::Error> {
+        self.get_market_groups(type_id)
+            .await
+            .map_err(|e| crate::Error::IndustryDbError(e.into()))
+    }
+}
+
+// the quality of a relic is in its name
+async fn relic_quality(
+    db: &impl InnerDatabase,
+    blueprint: Item,
+    relic: bool,
+) -> Result<Option<RelicQuality>, crate::Error> {
+    if !relic {
+        return Ok(None);
+    }
+    let name = db
+        .get_name(blueprint.type_id)
+        .await
+        .map_err(|e| crate::Error::IndustryDbError(e.into()))?;
+    match RelicQuality::from_name(&name) {
+        Some(quality) => Ok(Some(quality)),
+        None => Err(crate::Error::InvalidConfig(format!(
+            "{} is not a relic",
+            name
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const T1_BLUEPRINT: u32 = 1201;
+    const T2_BLUEPRINT: u32 = 1202;
+    const WRECKED_RELIC: u32 = 30_000;
+
+    // an invention blueprint with a usable and an unusable decryptor
+    struct InventionDb;
+
+    impl InnerDatabase for InventionDb {
+        type Error = crate::Error;
+        async fn get(
+            &self,
+            _product_id: u32,
+            _blueprint: Item,
+            _kind: ManufacturingKind,
+            _system_id: u32,
+            _include: DatabaseParamsInclude,
+        ) -> Result<DatabaseResponse, Self::Error> {
+            Ok(DatabaseResponse {
+                product: Item::new_blueprint(T2_BLUEPRINT, 10, 2, 4),
+                probability: 0.34,
+                portion: 1,
+                duration: Duration::from_secs(3600),
+                minerals: vec![(Item::new(20410), 2)],
+                installation_minerals: vec![(Item::new(34), 100)],
+                efficiencies: HashMap::new(),
+                security: 1.0,
+                invention_skills: InventionSkills::default(),
+                decryptors: vec![
+                    // changes nothing, so its product can't be told apart
+                    (Item::new_blueprint(34200, 0, 0, 0), 1.0),
+                    (Item::new_blueprint(34201, 0, 2, 0), 0.9),
+                ],
+            })
+        }
+        async fn get_fitting(
+            &self,
+            _structure_id: u32,
+            _rigs: [Option<u32>; 3],
+            _system_id: u32,
+        ) -> Result<FittingResponse, Self::Error> {
+            unimplemented!()
+        }
+        async fn get_reprocessing(
+            &self,
+            _item: u32,
+            _system_id: u32,
+        ) -> Result<Option<ReprocessingResponse>, Self::Error> {
+            unimplemented!()
+        }
+        async fn get_volume(
+            &self,
+            _item: u32,
+        ) -> Result<Option<Volume>, Self::Error> {
+            unimplemented!()
+        }
+        async fn get_name(&self, item: u32) -> Result<String, Self::Error> {
+            Ok(match item {
+                WRECKED_RELIC => "Wrecked Hull Section",
+                _ => "Some Blueprint",
+            }
+            .to_string())
+        }
+        async fn get_market_groups(
+            &self,
+            _item: u32,
+        ) -> Result<Vec<u32>, Self::Error> {
+            unimplemented!()
+        }
+    }
+
+    fn tax() -> config::ManufacturingValue {
+        serde_yaml::from_str(
+            "{manufacturing: 0.0, invention: 0.0, reaction: 0.0, copy: 0.0}",
+        )
+        .unwrap()
+    }
+
+    async fn relic_line(relic: u32, runs: i16) -> Result<Line, crate::Error> {
+        InventionDb
+            .compute_line(
+                10,
+                35825,
+                [None; 3],
+                tax(),
+                &HashMap::new(),
+                &[],
+                ManufacturingKind::Invention,
+                Transput::new(
+                    Item::new(relic),
+                    Item::new_blueprint(T2_BLUEPRINT, runs, 2, 4),
+                ),
+                Duration::from_secs(86_400),
+                None,
+                true,
+            )
+            .await
+    }
+
+    #[tokio::test]
+    async fn relic_quality_from_type() {
+        let line = relic_line(WRECKED_RELIC, 3).await.unwrap();
+        assert_eq!(line.product, Item::new_blueprint(T2_BLUEPRINT, 3, 2, 4));
+        // the relic's quality decides the runs, not the config
+        let line = relic_line(WRECKED_RELIC, 20).await;
+        assert!(matches!(line, Err(crate::Error::InvalidConfig(_))));
+    }
+
+    #[tokio::test]
+    async fn relic_must_be_a_relic() {
+        let line = relic_line(T1_BLUEPRINT, 3).await;
+        assert!(matches!(line, Err(crate::Error::InvalidConfig(_))));
+    }
+
+    #[tokio::test]
+    async fn decryptor_lines_skip_unusable_decryptors() {
+        let lines = InventionDb
+            .compute_decryptor_lines(
+                10,
+                35825,
+                [None; 3],
+                tax(),
+                &HashMap::new(),
+                &[],
+                Transput::new(
+                    Item::new_blueprint(T1_BLUEPRINT, 10, 0, 0),
+                    Item::new_blueprint(T2_BLUEPRINT, 10, 2, 4),
+                ),
+                Duration::from_secs(86_400),
+                false,
+            )
+            .await
+            .unwrap();
+        let products = lines.iter().map(|l| l.product).collect::<Vec<_>>();
+        assert_eq!(
+            products,
+            [
+                Item::new_blueprint(T2_BLUEPRINT, 10, 2, 4),
+                Item::new_blueprint(T2_BLUEPRINT, 10, 4, 4),
+            ]
+        );
+        assert_eq!(lines[1].decryptor, Some(Item::new(34201)));
+    }
 }
@@ -72,7 +72,6 @@ pub struct Efficiency {
     zero_sec_multiplier: f64,
     low_sec_multiplier: f64,
     high_sec_multiplier: f64,
-    probability_multiplier: f64,
 }
 
 impl Efficiency {
@@ -80,7 +79,6 @@ impl Efficiency {
         time_efficiency: f64,
         material_efficiency: f64,
         cost_efficiency: f64,
-        probability_multiplier: f64,
         high_sec_multiplier: f64,
         low_sec_multiplier: f64,
         zero_sec_multiplier: f64,
@@ -92,7 +90,6 @@ impl Efficiency {
             zero_sec_multiplier: zero_sec_multiplier,
             low_sec_multiplier: low_sec_multiplier,
             high_sec_multiplier: high_sec_multiplier,
-            probability_multiplier: probability_multiplier,
         }
     }
 
@@ -114,22 +111,11 @@ impl Efficiency {
         *e *= 1.0 - mult * level_mult * security_mult
     }
 
-    fn add_probability_inner(&self, probability: &mut f64, level_mult: f64) {
-        if self.probability_multiplier > 0.0 {
-            *probability += self.probability_multiplier * level_mult;
-        }
-    }
-
-    // pub fn add_probability(&self, probability: &mut f64, level: SkillLevel) {
-    //     self.add_probability_inner(probability, level.into());
-    // }
-
     pub fn add_efficiencies(
         &self,
         me: &mut f64,
         te: &mut f64,
         ce: &mut f64,
-        probability: &mut f64,
         level: SkillLevel,
         security: Security,
     ) {
@@ -144,6 +130,120 @@ impl Efficiency {
         if self.cost_efficiency > 0.0 {
             self.mult(ce, level_mult, security_mult, self.cost_efficiency);
         }
-        self.add_probability_inner(probability, level_mult);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // (time, material, cost) bonuses with (high, low, zero) multipliers
+    const RAITARU: Efficiency = efficiency(0.15, 0.01, 0.03, [1.0; 3]);
+    const AZBEL: Efficiency = efficiency(0.20, 0.01, 0.04, [1.0; 3]);
+    const SOTIYO: Efficiency = efficiency(0.30, 0.01, 0.05, [1.0; 3]);
+    const T1_ME_RIG: Efficiency = efficiency(0.0, 0.02, 0.0, RIG_SECURITY);
+    const T2_ME_RIG: Efficiency = efficiency(0.0, 0.024, 0.0, RIG_SECURITY);
+    const T1_TE_RIG: Efficiency = efficiency(0.20, 0.0, 0.0, RIG_SECURITY);
+    const T2_TE_RIG: Efficiency = efficiency(0.24, 0.0, 0.0, RIG_SECURITY);
+    const RIG_SECURITY: [f64; 3] = [1.0, 1.9, 2.1];
+    // industry and advanced industry, time bonus per level
+    const INDUSTRY: Efficiency = efficiency(0.04, 0.0, 0.0, [1.0; 3]);
+
+    // (structure, rigs, security, expected (me, te, ce))
+    type Case<'a> = (
+        &'a Efficiency,
+        &'a [&'a Efficiency],
+        Security,
+        (f64, f64, f64),
+    );
+
+    const fn efficiency(
+        time: f64,
+        material: f64,
+        cost: f64,
+        [high, low, zero]: [f64; 3],
+    ) -> Efficiency {
+        Efficiency {
+            material_efficiency: material,
+            time_efficiency: time,
+            cost_efficiency: cost,
+            zero_sec_multiplier: zero,
+            low_sec_multiplier: low,
+            high_sec_multiplier: high,
+        }
+    }
+
+    fn apply(
+        efficiencies: &[(&Efficiency, u8)],
+        security: Security,
+    ) -> (f64, f64, f64) {
+        let (mut me, mut te, mut ce) = (1.0, 1.0, 1.0);
+        for &(efficiency, level) in efficiencies {
+            efficiency.add_efficiencies(
+                &mut me,
+                &mut te,
+                &mut ce,
+                level.into(),
+                security,
+            );
+        }
+        (me, te, ce)
+    }
+
+    #[test]
+    fn efficiency_structures_and_rigs() {
+        use Security::*;
+        // as in game
+        let cases: &[Case] = &[
+            (&RAITARU, &[], High, (0.99, 0.85, 0.97)),
+            (&AZBEL, &[], Low, (0.99, 0.80, 0.96)),
+            (&SOTIYO, &[], Zero, (0.99, 0.70, 0.95)),
+            (&RAITARU, &[&T1_ME_RIG], High, (0.99 * 0.98, 0.85, 0.97)),
+            (&RAITARU, &[&T1_ME_RIG], Low, (0.99 * 0.962, 0.85, 0.97)),
+            (&RAITARU, &[&T1_ME_RIG], Zero, (0.99 * 0.958, 0.85, 0.97)),
+            (&AZBEL, &[&T2_ME_RIG], High, (0.99 * 0.976, 0.80, 0.96)),
+            (&AZBEL, &[&T2_ME_RIG], Low, (0.99 * 0.9544, 0.80, 0.96)),
+            (&AZBEL, &[&T2_ME_RIG], Zero, (0.99 * 0.9496, 0.80, 0.96)),
+            (&RAITARU, &[&T1_TE_RIG], High, (0.99, 0.85 * 0.80, 0.97)),
+            (&RAITARU, &[&T1_TE_RIG], Low, (0.99, 0.85 * 0.62, 0.97)),
+            (&RAITARU, &[&T1_TE_RIG], Zero, (0.99, 0.85 * 0.58, 0.97)),
+            (&SOTIYO, &[&T2_TE_RIG], High, (0.99, 0.70 * 0.76, 0.95)),
+            (&SOTIYO, &[&T2_TE_RIG], Low, (0.99, 0.70 * 0.544, 0.95)),
+            (&SOTIYO, &[&T2_TE_RIG], Zero, (0.99, 0.70 * 0.496, 0.95)),
+            (
+                &SOTIYO,
+                &[&T2_ME_RIG, &T2_TE_RIG],
+                Zero,
+                (0.99 * 0.9496, 0.70 * 0.496, 0.95),
+            ),
+        ];
+        for (i, (structure, rigs, security, expected)) in
+            cases.iter().enumerate()
+        {
+            let efficiencies = std::iter::once(*structure)
+                .chain(rigs.iter().copied())
+                .map(|e| (e, 1))
+                .collect::<Vec<_>>();
+            let (me, te, ce) = apply(&efficiencies, *security);
+            assert!((me - expected.0).abs() < 1e-9, "case {}: me {}", i, me);
+            assert!((te - expected.1).abs() < 1e-9, "case {}: te {}", i, te);
+            assert!((ce - expected.2).abs() < 1e-9, "case {}: ce {}", i, ce);
+        }
+    }
+
+    #[test]
+    fn efficiency_skill_levels() {
+        for (level, expected) in [(1, 0.96), (3, 0.88), (5, 0.80)] {
+            let (_, te, _) = apply(&[(&INDUSTRY, level)], Security::High);
+            assert!((te - expected).abs() < 1e-9, "{}", te);
+        }
+    }
+
+    #[test]
+    fn efficiency_unused_bonuses_are_ignored() {
+        // a security multiplier doesn't matter without a bonus
+        let (me, te, ce) = apply(&[(&T1_TE_RIG, 1)], Security::Zero);
+        assert_eq!((me, ce), (1.0, 1.0));
+        assert!((te - 0.58).abs() < 1e-9);
     }
 }
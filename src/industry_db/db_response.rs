@@ -9,6 +9,8 @@ pub struct DatabaseResponse {
     pub installation_minerals: Vec<(Item, i64)>,
     pub efficiencies: HashMap<u32, Efficiency>,
     pub security: f64,
+    pub invention_skills: InventionSkills,
+    pub decryptors: Decryptors,
 }
 
 impl DatabaseResponse {
@@ -18,23 +20,11 @@ impl DatabaseResponse {
         me: &mut f64,
         te: &mut f64,
         ce: &mut f64,
-        probability: &mut f64,
         level: SkillLevel,
         security: Security,
     ) {
         if let Some(eff) = self.efficiencies.get(type_id) {
-            eff.add_efficiencies(me, te, ce, probability, level, security);
+            eff.add_efficiencies(me, te, ce, level, security);
         }
     }
-
-    // pub fn add_probability(
-    //     &self,
-    //     type_id: &u32,
-    //     probability: &mut f64,
-    //     level: SkillLevel,
-    // ) {
-    //     if let Some(eff) = self.efficiencies.get(type_id) {
-    //         eff.add_probability(probability, level);
-    //     }
-    // }
 }
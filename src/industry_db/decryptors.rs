@@ -1,15 +1,17 @@
 use super::*;
 
+// decryptors are (type_id with run / ME / TE modifiers, probability multiplier)
+pub type Decryptors = Vec<(Item, f64)>;
+
 pub fn find_matching_decryptor(
+    decryptors: &[(Item, f64)],
     base: Item,
     product: Item,
     decryptor_match: u32,
 ) -> Option<(Item, f64)> {
-    for (decryptor, p_mult) in DECRYPTORS {
+    for &(decryptor, p_mult) in decryptors {
         if decryptor.type_id == decryptor_match
-            && base.runs + decryptor.runs == product.runs
-            && base.me + decryptor.me == product.me
-            && base.te + decryptor.te == product.te
+            && decryptor_product(base, decryptor) == product
         {
             return Some((decryptor.into_non_blueprint(), p_mult));
         }
@@ -17,10 +19,24 @@ pub fn find_matching_decryptor(
     None
 }
 
-pub fn closest_pmult(base: f64, new: f64) -> (Item, f64) {
+// the invented blueprint when using the decryptor
+pub fn decryptor_product(base: Item, decryptor: Item) -> Item {
+    Item::new_blueprint(
+        base.type_id,
+        base.runs + decryptor.runs,
+        base.me + decryptor.me,
+        base.te + decryptor.te,
+    )
+}
+
+pub fn closest_pmult(
+    decryptors: &[(Item, f64)],
+    base: f64,
+    new: f64,
+) -> (Item, f64) {
     let mut closest_diff = (new - base).abs();
     let mut closest = (Item::null(), 1.0);
-    for decryptor in DECRYPTORS {
+    for &decryptor in decryptors {
         let diff = (base * decryptor.1 - base).abs();
         if diff < closest_diff {
             closest_diff = diff;
@@ -0,0 +1,182 @@
+use super::*;
+
+pub struct StructureInfo {
+    pub rig_size: i64,
+    pub manufacturing: bool,
+    pub science: bool,
+    pub reaction: bool,
+}
+
+pub struct RigInfo {
+    pub rig_size: i64,
+    pub group_id: u32,
+    pub high_sec: bool, // T2 reaction rigs and the like can't be in highsec
+    pub kinds: Vec<ManufacturingKind>, // jobs the rig gives bonuses to
+}
+
+impl StructureInfo {
+    fn allows(&self, kind: ManufacturingKind, high_sec: bool) -> bool {
+        match kind.into() {
+            IndustrySlot::Manufacturing => self.manufacturing,
+            IndustrySlot::Science => self.science,
+            IndustrySlot::Reaction => self.reaction && !high_sec,
+        }
+    }
+}
+
+pub struct FittingResponse {
+    pub structure: Option<StructureInfo>,
+    pub rigs: Vec<(u32, Option<RigInfo>)>,
+    pub security: f64,
+}
+
+impl FittingResponse {
+    // the reason the fitting, or a job kind in it, is impossible
+    pub fn validate(
+        &self,
+        structure_id: u32,
+        kinds: &[ManufacturingKind],
+    ) -> Result<(), String> {
+        let structure = self
+            .structure
+            .as_ref()
+            .ok_or(format!("structure {} is unknown", structure_id))?;
+        let high_sec = matches!(Security::from(self.security), Security::High);
+
+        let mut groups = Vec::new();
+        for (rig_id, rig) in self.rigs.iter() {
+            let rig =
+                rig.as_ref().ok_or(format!("rig {} is unknown", rig_id))?;
+            if rig.rig_size != structure.rig_size {
+                return Err(format!(
+                    "rig {} doesn't fit structure {}",
+                    rig_id, structure_id
+                ));
+            }
+            if high_sec && !rig.high_sec {
+                return Err(format!(
+                    "rig {} can't be fitted in highsec",
+                    rig_id
+                ));
+            }
+            if groups.contains(&rig.group_id) {
+                return Err(format!(
+                    "rig {} is in the same group as another rig",
+                    rig_id
+                ));
+            }
+            groups.push(rig.group_id);
+            if !rig.kinds.iter().any(|&k| structure.allows(k, high_sec)) {
+                return Err(format!(
+                    "rig {} is for jobs structure {} can't run",
+                    rig_id, structure_id
+                ));
+            }
+            if !kinds.is_empty() && !rig.kinds.iter().any(|k| kinds.contains(k))
+            {
+                return Err(format!(
+                    "rig {} doesn't affect any of the jobs run with it",
+                    rig_id
+                ));
+            }
+        }
+
+        for &kind in kinds {
+            if !structure.allows(kind, high_sec) {
+                return Err(format!(
+                    "{:?} jobs can't be run in structure {} at security {:.1}",
+                    kind, structure_id, self.security
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RAITARU_ID: u32 = 35825;
+    const ME_RIG_ID: u32 = 43920;
+    const REACTION_RIG_ID: u32 = 46484;
+    const INVENTION_RIG_ID: u32 = 43891;
+
+    fn raitaru(security: f64, rigs: &[u32]) -> FittingResponse {
+        let rig = |rig_id: u32| {
+            let (group_id, high_sec, kinds) = match rig_id {
+                ME_RIG_ID => (1, true, vec![ManufacturingKind::Manufacturing]),
+                INVENTION_RIG_ID => {
+                    (2, true, vec![ManufacturingKind::Invention])
+                }
+                REACTION_RIG_ID => {
+                    (3, false, vec![ManufacturingKind::Reaction])
+                }
+                _ => return (rig_id, None),
+            };
+            let rig = RigInfo {
+                rig_size: 2,
+                group_id,
+                high_sec,
+                kinds,
+            };
+            (rig_id, Some(rig))
+        };
+        FittingResponse {
+            structure: Some(StructureInfo {
+                rig_size: 2,
+                manufacturing: true,
+                science: true,
+                reaction: false,
+            }),
+            rigs: rigs.iter().map(|&rig_id| rig(rig_id)).collect(),
+            security,
+        }
+    }
+
+    #[test]
+    fn validate_fitting() {
+        let manufacturing = [ManufacturingKind::Manufacturing];
+        let both = [
+            ManufacturingKind::Manufacturing,
+            ManufacturingKind::Invention,
+        ];
+        let fitting = raitaru(0.9, &[ME_RIG_ID]);
+        assert!(fitting.validate(RAITARU_ID, &manufacturing).is_ok());
+        let fitting = raitaru(0.9, &[ME_RIG_ID, INVENTION_RIG_ID]);
+        assert!(fitting.validate(RAITARU_ID, &both).is_ok());
+        // no production lines to check the rigs against
+        assert!(fitting.validate(RAITARU_ID, &[]).is_ok());
+    }
+
+    #[test]
+    fn validate_rig_activity() {
+        let manufacturing = [ManufacturingKind::Manufacturing];
+        // an invention rig does nothing for manufacturing jobs
+        let fitting = raitaru(0.9, &[ME_RIG_ID, INVENTION_RIG_ID]);
+        assert!(fitting.validate(RAITARU_ID, &manufacturing).is_err());
+        // a raitaru can't run reactions, so reaction rigs are useless
+        let fitting = raitaru(-0.5, &[REACTION_RIG_ID]);
+        let reason = fitting
+            .validate(RAITARU_ID, &[ManufacturingKind::Reaction])
+            .unwrap_err();
+        assert!(reason.contains("can't run"), "{}", reason);
+    }
+
+    #[test]
+    fn validate_rigs_and_kinds() {
+        let manufacturing = [ManufacturingKind::Manufacturing];
+        let fitting = raitaru(0.9, &[1]);
+        assert!(fitting.validate(RAITARU_ID, &manufacturing).is_err());
+        let fitting = raitaru(0.9, &[ME_RIG_ID, ME_RIG_ID]);
+        assert!(fitting.validate(RAITARU_ID, &manufacturing).is_err());
+        let fitting = raitaru(0.9, &[]);
+        let reaction = [ManufacturingKind::Reaction];
+        assert!(fitting.validate(RAITARU_ID, &reaction).is_err());
+        let fitting = FittingResponse {
+            structure: None,
+            ..raitaru(0.9, &[])
+        };
+        assert!(fitting.validate(RAITARU_ID, &manufacturing).is_err());
+    }
+}
@@ -0,0 +1,348 @@
+use super::*;
+
+// skills required by an invention blueprint, from the SDE
+#[derive(Debug, Clone, Default)]
+pub struct InventionSkills {
+    pub encryption: Option<u32>,
+    pub datacores: Vec<u32>,
+}
+
+impl InventionSkills {
+    pub fn encryption_level(&self, skills: &HashMap<u32, u8>) -> u8 {
+        self.encryption
+            .and_then(|type_id| skills.get(&type_id).copied())
+            .unwrap_or(0)
+    }
+
+    pub fn datacore_levels(&self, skills: &HashMap<u32, u8>) -> u8 {
+        self.datacores
+            .iter()
+            .map(|type_id| skills.get(type_id).copied().unwrap_or(0))
+            .sum()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelicQuality {
+    Intact,
+    Malfunctioning,
+    Wrecked,
+}
+
+impl RelicQuality {
+    // from the relic's type name, e.g. "Wrecked Hull Section"
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.split_whitespace().next()? {
+            "Intact" => Some(RelicQuality::Intact),
+            "Malfunctioning" => Some(RelicQuality::Malfunctioning),
+            "Wrecked" => Some(RelicQuality::Wrecked),
+            _ => None,
+        }
+    }
+
+    // runs of the invented T3 blueprint copy
+    pub const fn runs(&self) -> i16 {
+        match self {
+            RelicQuality::Intact => 20,
+            RelicQuality::Malfunctioning => 10,
+            RelicQuality::Wrecked => 3,
+        }
+    }
+}
+
+// the invented blueprint before decryptor modifiers
+pub fn invention_base_product(
+    db_product: Item,
+    relic: Option<RelicQuality>,
+) -> Item {
+    match relic {
+        Some(relic) => Item {
+            runs: relic.runs(),
+            ..db_product
+        },
+        None => db_product,
+    }
+}
+
+// base * (1 + encryption / 40 + (datacore_1 + datacore_2) / 30) * decryptor
+pub fn invention_probability(
+    base_probability: f64,
+    encryption_level: u8,
+    datacore_levels: u8,
+    decryptor_multiplier: f64,
+) -> f64 {
+    let skill_multiplier =
+        1.0 + encryption_level as f64 / 40.0 + datacore_levels as f64 / 30.0;
+    (base_probability * skill_multiplier * decryptor_multiplier).min(1.0)
+}
+
+// the binomial distribution of successful inventions from N attempts
+#[derive(Debug, Clone, Copy)]
+pub struct InventionOutcome {
+    pub attempts: i64,
+    pub probability: f64,
+}
+
+impl InventionOutcome {
+    pub fn new(attempts: i64, probability: f64) -> Self {
+        Self {
+            attempts,
+            probability,
+        }
+    }
+
+    pub fn with_attempts(&self, attempts: i64) -> Self {
+        Self::new(attempts, self.probability)
+    }
+
+    pub fn expected(&self) -> f64 {
+        self.attempts as f64 * self.probability
+    }
+
+    pub fn variance(&self) -> f64 {
+        self.expected() * (1.0 - self.probability)
+    }
+
+    // probability of exactly 0..=attempts successes
+    pub fn pmf(&self) -> Vec<f64> {
+        let n = self.attempts.max(0) as usize;
+        let p = self.probability;
+        if p <= 0.0 || p >= 1.0 {
+            let mut pmf = vec![0.0; n + 1];
+            pmf[if p <= 0.0 { 0 } else { n }] = 1.0;
+            return pmf;
+        }
+        // computed in log space, (1 - p)^n underflows for large n
+        let (ln_p, ln_q) = (p.ln(), (1.0 - p).ln());
+        let mut ln_choose = 0.0;
+        let mut pmf = Vec::with_capacity(n + 1);
+        for k in 0..=n {
+            if k > 0 {
+                ln_choose += ((n - k + 1) as f64).ln() - (k as f64).ln();
+            }
+            pmf.push(
+                (ln_choose + k as f64 * ln_p + (n - k) as f64 * ln_q).exp(),
+            );
+        }
+        pmf
+    }
+
+    // smallest number of successes that is reached with probability q
+    pub fn percentile(&self, q: f64) -> i64 {
+        let mut cumulative = 0.0;
+        for (k, p) in self.pmf().into_iter().enumerate() {
+            cumulative += p;
+            if cumulative >= q {
+                return k as i64;
+            }
+        }
+        self.attempts
+    }
+
+    // probability of at least `successes` successes
+    pub fn at_least(&self, successes: i64) -> f64 {
+        self.pmf()
+            .into_iter()
+            .skip(successes.max(0) as usize)
+            .sum::<f64>()
+            .min(1.0)
+    }
+
+    // fewest attempts that reach `successes` with the given confidence
+    pub fn attempts_for(&self, successes: i64, confidence: f64) -> Option<i64> {
+        if successes <= 0 {
+            return Some(0);
+        }
+        if self.probability <= 0.0
+            || (confidence >= 1.0 && self.probability < 1.0)
+        {
+            return None;
+        }
+        // P(X = k) for k < successes, stepped one attempt at a time with
+        // P'(k) = P(k) * (1 - p) + P(k - 1) * p
+        let mut attempts = successes;
+        let mut below = self.with_attempts(attempts).pmf();
+        below.truncate(successes as usize);
+        let (p, q) = (self.probability, 1.0 - self.probability);
+        while 1.0 - below.iter().sum::<f64>() < confidence {
+            for k in (0..below.len()).rev() {
+                below[k] *= q;
+                if k > 0 {
+                    below[k] += below[k - 1] * p;
+                }
+            }
+            attempts += 1;
+        }
+        Some(attempts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-9, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn invention_probability_no_skills() {
+        assert_close(invention_probability(0.34, 0, 0, 1.0), 0.34);
+        assert_close(invention_probability(0.30, 0, 0, 1.0), 0.30);
+    }
+
+    #[test]
+    fn invention_probability_all_v() {
+        // 1 + 5/40 + 10/30 = 1.458333...
+        assert_close(invention_probability(0.34, 5, 10, 1.0), 0.4958333333);
+        assert_close(invention_probability(0.30, 5, 10, 1.0), 0.4375);
+    }
+
+    #[test]
+    fn invention_probability_mixed_skills() {
+        // 1 + 4/40 + 7/30 = 1.333333...
+        assert_close(invention_probability(0.26, 4, 7, 1.0), 0.3466666667);
+    }
+
+    #[test]
+    fn invention_probability_decryptor() {
+        // accelerant (+20%) and attainment (+80%) decryptors
+        assert_close(invention_probability(0.34, 5, 10, 1.2), 0.595);
+        assert_close(invention_probability(0.34, 5, 10, 1.8), 0.8925);
+    }
+
+    #[test]
+    fn invention_probability_capped() {
+        assert_close(invention_probability(0.70, 5, 10, 1.9), 1.0);
+    }
+
+    // 10 attempts at 40%, from the binomial table
+    const PMF_10_40: [f64; 11] = [
+        0.0060466176,
+        0.0403107840,
+        0.1209323520,
+        0.2149908480,
+        0.2508226560,
+        0.2006581248,
+        0.1114767360,
+        0.0424673280,
+        0.0106168320,
+        0.0015728640,
+        0.0001048576,
+    ];
+
+    #[test]
+    fn invention_outcome_pmf() {
+        let pmf = InventionOutcome::new(10, 0.4).pmf();
+        assert_eq!(pmf.len(), PMF_10_40.len());
+        for (p, expected) in pmf.into_iter().zip(PMF_10_40) {
+            assert_close(p, expected);
+        }
+    }
+
+    #[test]
+    fn invention_outcome_pmf_certain() {
+        assert_eq!(InventionOutcome::new(3, 0.0).pmf(), [1.0, 0.0, 0.0, 0.0]);
+        assert_eq!(InventionOutcome::new(3, 1.0).pmf(), [0.0, 0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn invention_outcome_pmf_many_attempts() {
+        // (1 - p)^n underflows, the log space pmf doesn't
+        let pmf = InventionOutcome::new(5000, 0.4).pmf();
+        assert_close(pmf.iter().sum::<f64>(), 1.0);
+        assert!(pmf[2000] > 0.01);
+    }
+
+    #[test]
+    fn invention_outcome_moments() {
+        let outcome = InventionOutcome::new(10, 0.4);
+        assert_close(outcome.expected(), 4.0);
+        assert_close(outcome.variance(), 2.4);
+    }
+
+    #[test]
+    fn invention_outcome_percentile() {
+        let outcome = InventionOutcome::new(10, 0.4);
+        assert_eq!(outcome.percentile(0.1), 2);
+        assert_eq!(outcome.percentile(0.5), 4);
+        assert_eq!(outcome.percentile(0.9), 6);
+    }
+
+    #[test]
+    fn invention_outcome_at_least() {
+        let outcome = InventionOutcome::new(10, 0.4);
+        assert_close(outcome.at_least(0), 1.0);
+        assert_close(outcome.at_least(4), 0.6177193984);
+        assert_close(outcome.at_least(10), 0.0001048576);
+        assert_close(outcome.at_least(11), 0.0);
+    }
+
+    #[test]
+    fn invention_outcome_attempts_for() {
+        let outcome = InventionOutcome::new(10, 0.4);
+        // P(X >= 4) is 0.8757 with 14 attempts and 0.9095 with 15
+        assert_eq!(outcome.attempts_for(4, 0.9), Some(15));
+        assert_eq!(outcome.attempts_for(10, 0.5), Some(24));
+        assert_eq!(outcome.attempts_for(10, 0.95), Some(36));
+        assert_eq!(outcome.attempts_for(1, 0.99), Some(10));
+        assert_eq!(outcome.attempts_for(0, 0.99), Some(0));
+    }
+
+    #[test]
+    fn invention_outcome_attempts_for_unreachable() {
+        assert_eq!(InventionOutcome::new(10, 0.0).attempts_for(1, 0.5), None);
+        assert_eq!(InventionOutcome::new(10, 0.4).attempts_for(1, 1.0), None);
+        assert_eq!(
+            InventionOutcome::new(10, 1.0).attempts_for(7, 1.0),
+            Some(7)
+        );
+    }
+
+    #[test]
+    fn invention_outcome_attempts_for_many_successes() {
+        let outcome = InventionOutcome::new(10, 0.4);
+        let attempts = outcome.attempts_for(2000, 0.9).unwrap();
+        assert!(outcome.with_attempts(attempts).at_least(2000) >= 0.9);
+        assert!(outcome.with_attempts(attempts - 1).at_least(2000) < 0.9);
+    }
+
+    #[test]
+    fn relic_quality_from_name() {
+        for (name, quality, runs) in [
+            ("Intact Hull Section", RelicQuality::Intact, 20),
+            (
+                "Malfunctioning Armor Nanobot",
+                RelicQuality::Malfunctioning,
+                10,
+            ),
+            ("Wrecked Weapon Subroutines", RelicQuality::Wrecked, 3),
+        ] {
+            assert_eq!(RelicQuality::from_name(name), Some(quality));
+            assert_eq!(quality.runs(), runs);
+        }
+        assert_eq!(RelicQuality::from_name("Hull Section"), None);
+        assert_eq!(RelicQuality::from_name(""), None);
+    }
+
+    #[test]
+    fn invention_base_product_relic_runs() {
+        let product = Item::new_blueprint(29986, 1, 2, 4);
+        assert_eq!(invention_base_product(product, None), product);
+        assert_eq!(
+            invention_base_product(product, Some(RelicQuality::Wrecked)),
+            Item::new_blueprint(29986, 3, 2, 4)
+        );
+    }
+
+    #[test]
+    fn invention_skill_levels() {
+        let invention_skills = InventionSkills {
+            encryption: Some(21790),
+            datacores: vec![11433, 11442],
+        };
+        let skills = HashMap::from([(21790, 4), (11433, 5)]);
+        assert_eq!(invention_skills.encryption_level(&skills), 4);
+        assert_eq!(invention_skills.datacore_levels(&skills), 5);
+    }
+}
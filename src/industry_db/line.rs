@@ -1,12 +1,40 @@
 use super::*;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sequencing {
+    Daily,      // restarted once a day, when there's time for another job
+    BackToBack, // restarted as soon as it finishes, one BPC after another
+    Once,       // never restarted
+}
+
+// what a job makes and how, as configured for the line
+#[derive(Debug, Clone, Copy)]
+pub struct JobParams {
+    pub kind: config::ManufacturingKind,
+    pub transput: config::Transput,
+    pub max_duration: Duration,
+    pub decryptor: Option<u32>,
+    pub relic: Option<RelicQuality>,
+}
+
 pub struct Line {
+    pub blueprint: Item, // the blueprint the job is installed with
+    pub product: Item,   // the product, including invented runs / ME / TE
+    pub decryptor: Option<Item>, // decryptor consumed by invention
     pub installation_minerals: Vec<(Item, i64)>, // minerals to be used for computing installation cost for N runs
     pub minerals: Vec<(Item, i64)>,              // minerals needed for N runs
-    pub portion: i64,                            // number produced from N runs
-    pub duration: Duration,                      // time needed for N runs
-    pub runs: i64,                               // number of runs
-    pub cost_multiplier: f64, // job cost (0.0 - 1.0) * job kind multiplier (0.02 or 1.0) + that * tax
+    pub base_minerals: Vec<(Item, i64)>, // minerals ME applies to, for 1 run
+    pub material_efficiency: f64, // bonuses to minerals, without blueprint ME
+    pub portion: i64,             // number produced from N runs
+    pub expected_portion: f64,    // expected number produced from N runs
+    pub invention: Option<InventionOutcome>, // distribution of invented BPCs
+    pub invention_materials: Vec<(Item, i64)>, // consumed per attempt, without BPCs
+    pub duration: Duration,                    // time needed for N runs
+    pub runs: i64,                             // number of runs
+    pub sequencing: Sequencing, // how jobs are repeated within the window
+    pub eiv_multiplier: f64, // job kind multiplier (0.02 or 1.0) applied to EIV
+    pub cost_efficiency: f64, // structure and rig bonuses to system cost (0.0 - 1.0)
+    pub facility_tax: f64,    // facility tax as a portion of job EIV
 }
 
 impl Line {
@@ -16,20 +44,34 @@ impl Line {
             installation_minerals: true,
             efficiencies: true,
             security: true,
+            invention_skills: true,
+            decryptors: true,
         }
     }
 
     pub fn from_rep(
-        db_rep: DatabaseResponse,
+        db_rep: &DatabaseResponse,
         structure_id: u32,
         rigs: [Option<u32>; 3],
         tax: config::ManufacturingValue,
         skills: &HashMap<u32, u8>,
-        kind: config::ManufacturingKind,
-        transput: config::Transput,
-        max_duration: Duration,
-        decryptor: Option<u32>,
+        implants: &[u32],
+        job: JobParams,
     ) -> Result<Line, crate::Error> {
+        let JobParams {
+            kind,
+            transput,
+            max_duration,
+            decryptor,
+            relic,
+        } = job;
+        if relic.is_some() && kind != ManufacturingKind::Invention {
+            return Err(crate::Error::InvalidConfig(format!(
+                "relic {} can only be used for invention",
+                transput.blueprint.type_id
+            )));
+        }
+
         let num_runs = match kind {
             ManufacturingKind::Copy => Some(transput.product.runs.into()),
             ManufacturingKind::Manufacturing => {
@@ -44,12 +86,12 @@ impl Line {
         let mut material_efficiency = 1.0;
         let mut time_efficiency = 1.0;
         let mut cost_efficiency = 1.0;
-        let mut probability = db_rep.probability;
         let security = db_rep.security.into();
 
         for (type_id, slvl) in iter::once(structure_id)
             .map(|id| (id, SkillLevel::One))
             .chain(rigs.into_iter().flatten().map(|id| (id, SkillLevel::One)))
+            .chain(implants.iter().map(|id| (*id, SkillLevel::One)))
             .chain(skills.into_iter().map(|(id, slvl)| (*id, (*slvl).into())))
         {
             db_rep.add_efficiencies(
@@ -57,12 +99,12 @@ impl Line {
                 &mut material_efficiency,
                 &mut time_efficiency,
                 &mut cost_efficiency,
-                &mut probability,
                 slvl,
                 security,
             );
         }
 
+        let facility_material_efficiency = material_efficiency;
         add_blueprint_efficiencies(
             &mut material_efficiency,
             &mut time_efficiency,
@@ -71,51 +113,94 @@ impl Line {
         );
 
         let run_once_duration = db_rep.duration.mul_f64(time_efficiency);
+        if kind.is_research() {
+            return Line::research(
+                db_rep,
+                tax,
+                kind,
+                transput,
+                run_once_duration,
+                cost_efficiency,
+            );
+        }
+
         let mut max_runs_f64 = (max_duration.as_secs_f64()
             / run_once_duration.as_secs_f64())
         .floor();
         let mut max_runs_qnt: i64 = max_runs_f64 as i64;
+        let mut sequencing = Sequencing::Daily;
         if let Some(num_runs) = num_runs {
-            if max_runs_qnt > num_runs {
+            if max_runs_qnt < num_runs && kind == ManufacturingKind::Copy {
+                return Err(crate::Error::InvalidConfig(format!(
+                    "copying {} runs of {} takes longer than the window",
+                    num_runs, transput.blueprint.type_id
+                )));
+            } else if max_runs_qnt < 1 {
+                return Err(crate::Error::InvalidConfig(format!(
+                    "a run of {} takes longer than the window",
+                    transput.blueprint.type_id
+                )));
+            } else if max_runs_qnt < num_runs {
+                // the BPC can't be finished within the window, a single job
+                // runs what fits and the BPC keeps the rest
+                sequencing = Sequencing::Once;
+            } else {
+                // the job shrinks to the runs of the BPC
                 max_runs_qnt = num_runs;
                 max_runs_f64 = num_runs as f64;
-            } else {
-                return Err(crate::Error::Unimplemented);
+                sequencing = Sequencing::BackToBack;
             }
         }
 
         let mut line = Line {
-            installation_minerals: db_rep.installation_minerals,
-            minerals: db_rep.minerals,
+            blueprint: transput.blueprint,
+            product: transput.product,
+            decryptor: None,
+            installation_minerals: db_rep.installation_minerals.clone(),
+            minerals: db_rep.minerals.clone(),
+            base_minerals: db_rep.minerals.clone(),
+            material_efficiency: facility_material_efficiency,
             portion: db_rep.portion * max_runs_qnt,
+            expected_portion: (db_rep.portion * max_runs_qnt) as f64,
+            invention: None,
+            invention_materials: Vec::new(),
             duration: run_once_duration.mul_f64(max_runs_f64),
             runs: max_runs_qnt,
-            cost_multiplier: kind_multiplier(kind) // 1.0 or 0.02
-                * cost_efficiency // 0.0 - 1.0
-                * (1.0 + tax.kind_value(kind)), // 1.0 - 2.0
+            sequencing,
+            eiv_multiplier: kind_multiplier(kind), // 1.0 or 0.02
+            cost_efficiency,                       // 0.0 - 1.0
+            facility_tax: tax.kind_value(kind),
         };
 
+        if kind == ManufacturingKind::Invention {
+            // datacores per attempt, decryptors and relics are added below
+            line.invention_materials = line.minerals.clone();
+        }
         for (_, quantity) in &mut line.minerals {
-            if material_efficiency == 1.0 || quantity == &1 {
-                *quantity *= max_runs_qnt; // 1-ofs are always 1 per run regardless of material efficiency
-            } else {
-                *quantity =
-                    (*quantity as f64 * max_runs_f64 * material_efficiency)
-                        .ceil() as i64;
-            }
+            *quantity = job_material_quantity(
+                *quantity,
+                max_runs_qnt,
+                material_efficiency,
+            );
         }
         for (_, quantity) in &mut line.installation_minerals {
             *quantity *= max_runs_qnt;
         }
         if transput.blueprint.is_bpc() {
             line.minerals.push((transput.blueprint, 1));
+        } else if relic.is_some() {
+            // each invention attempt consumes a relic
+            let relic = transput.blueprint.into_non_blueprint();
+            line.minerals.push((relic, max_runs_qnt));
+            line.invention_materials.push((relic, 1));
         }
 
         match kind {
             ManufacturingKind::Invention => line.set_invention(
-                db_rep.product,
+                db_rep,
+                invention_base_product(db_rep.product, relic),
                 transput.product,
-                probability,
+                skills,
                 max_runs_qnt,
                 decryptor,
             )?,
@@ -126,43 +211,158 @@ impl Line {
         Ok(line)
     }
 
+    // a single job researching every level from the blueprint to the product
+    fn research(
+        db_rep: &DatabaseResponse,
+        tax: config::ManufacturingValue,
+        kind: config::ManufacturingKind,
+        transput: config::Transput,
+        level_one_duration: Duration,
+        cost_efficiency: f64,
+    ) -> Result<Line, crate::Error> {
+        let (blueprint, product) = (transput.blueprint, transput.product);
+        let (from, to) =
+            research_levels(kind, blueprint, product).ok_or_else(|| {
+                crate::Error::InvalidConfig(format!(
+                    "{:?} of {} from ME {} TE {} to ME {} TE {} is invalid",
+                    kind,
+                    blueprint.type_id,
+                    blueprint.me,
+                    blueprint.te,
+                    product.me,
+                    product.te
+                ))
+            })?;
+        let level_multiplier = research_level_multiplier(from, to);
+        Ok(Line {
+            blueprint: transput.blueprint,
+            product: transput.product,
+            decryptor: None,
+            installation_minerals: db_rep.installation_minerals.clone(),
+            minerals: db_rep.minerals.clone(),
+            base_minerals: Vec::new(), // research has no ME
+            material_efficiency: 1.0,
+            portion: 1,
+            expected_portion: 1.0,
+            invention: None,
+            invention_materials: Vec::new(),
+            duration: level_one_duration.mul_f64(level_multiplier),
+            runs: (to - from) as i64,
+            sequencing: Sequencing::Once, // the blueprint has been researched
+            // job cost scales with research time, like the duration
+            eiv_multiplier: kind_multiplier(kind) * level_multiplier,
+            cost_efficiency,
+            facility_tax: tax.kind_value(kind),
+        })
+    }
+
+    // the share of the consumed BPC's runs a job uses, a BPC that doesn't
+    // fit in the window keeps the rest
+    pub fn bpc_runs_used(&self) -> f64 {
+        match self.blueprint.is_bpc() {
+            true => (self.runs as f64 / self.blueprint.runs as f64).min(1.0),
+            false => 1.0,
+        }
+    }
+
+    // minerals needed for N runs if the blueprint had `me` instead
+    pub fn minerals_with_me(
+        &self,
+        me: i8,
+    ) -> impl Iterator<Item = (Item, i64)> + '_ {
+        let material_efficiency =
+            self.material_efficiency * (1.0 - me as f64 / 100.0);
+        self.base_minerals.iter().map(move |(item, quantity)| {
+            (
+                *item,
+                job_material_quantity(
+                    *quantity,
+                    self.runs,
+                    material_efficiency,
+                ),
+            )
+        })
+    }
+
     fn set_invention(
         &mut self,
+        db_rep: &DatabaseResponse,
         db_product: Item,
         line_product: Item,
-        mut probability: f64,
+        skills: &HashMap<u32, u8>,
         max_runs: i64,
         decryptor: Option<u32>,
     ) -> Result<(), crate::Error> {
+        let mut decryptor_multiplier = 1.0;
+        if decryptor.is_none() && db_product != line_product {
+            // e.g. an intact relic's runs for a wrecked relic
+            return Err(crate::Error::InvalidConfig(format!(
+                "invented blueprint {:?} should be {:?}",
+                line_product, db_product
+            )));
+        }
         if let Some(decryptor) = decryptor {
             if db_product == line_product {
-                // configured decryptor is invalid
-                return Err(crate::Error::Unimplemented);
+                // a decryptor always changes the invented blueprint
+                return Err(crate::Error::InvalidConfig(format!(
+                    "decryptor {} doesn't change invented blueprint {:?}",
+                    decryptor, line_product
+                )));
             } else {
                 match find_matching_decryptor(
+                    &db_rep.decryptors,
                     db_product,
                     line_product,
                     decryptor,
                 ) {
                     Some((decryptor, pmult)) => {
                         self.minerals.push((decryptor, max_runs));
-                        probability *= pmult;
+                        self.invention_materials.push((decryptor, 1));
+                        self.decryptor = Some(decryptor);
+                        decryptor_multiplier = pmult;
+                    }
+                    None => {
+                        return Err(crate::Error::InvalidConfig(format!(
+                            "decryptor {} doesn't invent {:?}",
+                            decryptor, line_product
+                        )))
                     }
-                    // configured decryptor is invalid
-                    None => return Err(crate::Error::Unimplemented),
                 }
             }
         }
-        self.portion = (self.portion as f64 * probability).floor() as i64;
+        let probability = invention_probability(
+            db_rep.probability,
+            db_rep.invention_skills.encryption_level(skills),
+            db_rep.invention_skills.datacore_levels(skills),
+            decryptor_multiplier,
+        );
+        let outcome = InventionOutcome::new(self.portion, probability);
+        self.portion = outcome.expected().floor() as i64;
+        self.expected_portion = outcome.expected();
+        self.invention = Some(outcome);
         Ok(())
     }
 
     fn set_copy(&mut self) {
         self.portion = 1;
+        self.expected_portion = 1.0;
         self.runs = 1;
     }
 }
 
+// the quantity of a material needed for a single job, as quoted in game
+// each material is needed at least once per run, regardless of efficiency
+pub fn job_material_quantity(
+    base: i64,
+    runs: i64,
+    material_efficiency: f64,
+) -> i64 {
+    let quantity = base as f64 * runs as f64 * material_efficiency;
+    // rounded to 2 decimals first, so float error doesn't add a unit
+    let quantity = ((quantity * 100.0).round() / 100.0).ceil() as i64;
+    quantity.max(runs)
+}
+
 fn add_blueprint_efficiencies(
     me: &mut f64,
     te: &mut f64,
@@ -174,3 +374,442 @@ fn add_blueprint_efficiencies(
         *te *= 1.0 - blueprint.te as f64 / 100.0;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ME 10 blueprint, Raitaru (1% material bonus), no rigs
+    const ME10_RAITARU: f64 = 0.9 * 0.99;
+    // ME 10 blueprint, Raitaru, T1 ME rig in highsec (2.0%)
+    const ME10_RAITARU_T1: f64 = 0.9 * 0.99 * 0.98;
+
+    const RAITARU_ID: u32 = 35825;
+    const T1_ME_RIG_ID: u32 = 43920;
+    const T1_TE_RIG_ID: u32 = 43919;
+
+    // 1000 tritanium and 1 hour per run, in a raitaru with T1 ME and TE rigs
+    fn database_response(security: f64) -> DatabaseResponse {
+        let rig_security = (1.0, 1.9, 2.1);
+        DatabaseResponse {
+            product: Item::new(1),
+            probability: 1.0,
+            portion: 1,
+            duration: Duration::from_secs(3600),
+            minerals: vec![(Item::new(34), 1000)],
+            installation_minerals: vec![(Item::new(34), 1000)],
+            efficiencies: HashMap::from([
+                (RAITARU_ID, Efficiency::new(0.15, 0.01, 0.03, 1.0, 1.0, 1.0)),
+                (
+                    T1_ME_RIG_ID,
+                    Efficiency::new(
+                        0.0,
+                        0.02,
+                        0.0,
+                        rig_security.0,
+                        rig_security.1,
+                        rig_security.2,
+                    ),
+                ),
+                (
+                    T1_TE_RIG_ID,
+                    Efficiency::new(
+                        0.20,
+                        0.0,
+                        0.0,
+                        rig_security.0,
+                        rig_security.1,
+                        rig_security.2,
+                    ),
+                ),
+            ]),
+            security,
+            invention_skills: InventionSkills::default(),
+            decryptors: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn from_rep_security() {
+        let tax: config::ManufacturingValue = serde_yaml::from_str(
+            "{manufacturing: 0.0, invention: 0.0, reaction: 0.0, copy: 0.0}",
+        )
+        .unwrap();
+        let transput =
+            Transput::new(Item::new_blueprint(2, -1, 10, 20), Item::new(1));
+        // (security, runs in a day, tritanium, seconds per run)
+        for (security, runs, tritanium, run_duration) in [
+            // 0.891 * 0.98, 0.8 * 0.85 * 0.80
+            (0.9, 44, 38_420, 1958.4),
+            // 0.891 * 0.962, 0.8 * 0.85 * 0.62
+            (0.3, 56, 48_000, 1517.76),
+            // 0.891 * 0.958, 0.8 * 0.85 * 0.58
+            (-0.2, 60, 51_215, 1419.84),
+        ] {
+            let line = Line::from_rep(
+                &database_response(security),
+                RAITARU_ID,
+                [Some(T1_ME_RIG_ID), Some(T1_TE_RIG_ID), None],
+                tax,
+                &HashMap::new(),
+                &[],
+                JobParams {
+                    kind: ManufacturingKind::Manufacturing,
+                    transput,
+                    max_duration: Duration::from_secs(86_400),
+                    decryptor: None,
+                    relic: None,
+                },
+            )
+            .unwrap();
+            assert_eq!(line.runs, runs, "{}", security);
+            assert_eq!(line.portion, runs, "{}", security);
+            assert_eq!(line.minerals, vec![(Item::new(34), tritanium)]);
+            // the blueprint's own ME gives back the job's minerals
+            assert_eq!(
+                line.minerals_with_me(10).collect::<Vec<_>>(),
+                line.minerals
+            );
+            assert!(line.minerals_with_me(0).next().unwrap().1 > tritanium);
+            let expected = run_duration * runs as f64;
+            assert!(
+                (line.duration.as_secs_f64() - expected).abs() < 1e-3,
+                "{}: {:?}",
+                security,
+                line.duration
+            );
+            assert!((line.cost_efficiency - 0.97).abs() < 1e-9);
+        }
+    }
+
+    fn bpc_line(runs: i16, copy_runs: i16) -> Result<Line, crate::Error> {
+        let tax: config::ManufacturingValue = serde_yaml::from_str(
+            "{manufacturing: 0.0, invention: 0.0, reaction: 0.0, copy: 0.0}",
+        )
+        .unwrap();
+        let (kind, transput) = match copy_runs {
+            0 => (
+                ManufacturingKind::Manufacturing,
+                Transput::new(
+                    Item::new_blueprint(2, runs, 10, 20),
+                    Item::new(1),
+                ),
+            ),
+            _ => (
+                ManufacturingKind::Copy,
+                Transput::new(
+                    Item::new_blueprint(2, -1, 10, 20),
+                    Item::new_blueprint(2, copy_runs, 10, 20),
+                ),
+            ),
+        };
+        // 0.85 * 0.8 * 3600 s = 2448 s a run, 35 runs in a day
+        Line::from_rep(
+            &database_response(0.9),
+            RAITARU_ID,
+            [None; 3],
+            tax,
+            &HashMap::new(),
+            &[],
+            JobParams {
+                kind,
+                transput,
+                max_duration: Duration::from_secs(86_400),
+                decryptor: None,
+                relic: None,
+            },
+        )
+    }
+
+    #[test]
+    fn from_rep_bpc_back_to_back() {
+        let line = bpc_line(10, 0).unwrap();
+        assert_eq!(line.runs, 10);
+        assert_eq!(line.portion, 10);
+        assert_eq!(line.sequencing, Sequencing::BackToBack);
+        assert!(line
+            .minerals
+            .contains(&(Item::new_blueprint(2, 10, 10, 20), 1)));
+        assert!((line.duration.as_secs_f64() - 24_480.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn from_rep_bpc_capped_to_window() {
+        // a 100 run BPC only gets through the runs that fit in the window
+        let line = bpc_line(100, 0).unwrap();
+        assert_eq!(line.runs, 35);
+        assert_eq!(line.portion, 35);
+        assert_eq!(line.sequencing, Sequencing::Once);
+        assert!(line.duration <= Duration::from_secs(86_400));
+        let bpc = Item::new_blueprint(2, 100, 10, 20);
+        assert!(line.minerals.contains(&(bpc, 1)));
+        // charged for the 35 runs the job uses
+        assert!((line.bpc_runs_used() - 0.35).abs() < 1e-9);
+        assert_eq!(bpc_line(10, 0).unwrap().bpc_runs_used(), 1.0);
+    }
+
+    #[test]
+    fn from_rep_bpc_run_must_fit_window() {
+        // a single 2448 s run doesn't fit in an hour
+        let tax: config::ManufacturingValue = serde_yaml::from_str(
+            "{manufacturing: 0.0, invention: 0.0, reaction: 0.0, copy: 0.0}",
+        )
+        .unwrap();
+        let line = Line::from_rep(
+            &database_response(0.9),
+            RAITARU_ID,
+            [None; 3],
+            tax,
+            &HashMap::new(),
+            &[],
+            JobParams {
+                kind: ManufacturingKind::Manufacturing,
+                transput: Transput::new(
+                    Item::new_blueprint(2, 10, 10, 20),
+                    Item::new(1),
+                ),
+                max_duration: Duration::from_secs(2_000),
+                decryptor: None,
+                relic: None,
+            },
+        );
+        assert!(matches!(line, Err(crate::Error::InvalidConfig(_))));
+    }
+
+    #[test]
+    fn from_rep_copy_must_fit_window() {
+        assert_eq!(bpc_line(0, 10).unwrap().sequencing, Sequencing::BackToBack);
+        // a copy with fewer runs would not be the configured BPC
+        let line = bpc_line(0, 100);
+        assert!(matches!(line, Err(crate::Error::InvalidConfig(_))));
+    }
+
+    const BX_804_ID: u32 = 27_167;
+
+    fn implant_line(implants: &[u32]) -> Line {
+        let tax: config::ManufacturingValue = serde_yaml::from_str(
+            "{manufacturing: 0.0, invention: 0.0, reaction: 0.0, copy: 0.0}",
+        )
+        .unwrap();
+        let mut db_rep = database_response(0.9);
+        // 4% manufacturing time, regardless of security
+        db_rep
+            .efficiencies
+            .insert(BX_804_ID, Efficiency::new(0.04, 0.0, 0.0, 1.0, 1.0, 1.0));
+        Line::from_rep(
+            &db_rep,
+            RAITARU_ID,
+            [None; 3],
+            tax,
+            &HashMap::new(),
+            implants,
+            JobParams {
+                kind: ManufacturingKind::Manufacturing,
+                transput: Transput::new(
+                    Item::new_blueprint(2, -1, 0, 0),
+                    Item::new(1),
+                ),
+                max_duration: Duration::from_secs(86_400),
+                decryptor: None,
+                relic: None,
+            },
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn from_rep_implant_bonus() {
+        // 3600 * 0.85 = 3060 s a run, 28 runs in a day
+        let without = implant_line(&[]);
+        assert_eq!(without.runs, 28);
+        assert!((without.duration.as_secs_f64() - 28.0 * 3060.0).abs() < 1e-3);
+        // 3060 * 0.96 = 2937.6 s a run, 29 runs in a day
+        let with = implant_line(&[BX_804_ID]);
+        assert_eq!(with.runs, 29);
+        assert!((with.duration.as_secs_f64() - 29.0 * 2937.6).abs() < 1e-3);
+        // implants don't change materials
+        assert_eq!(with.material_efficiency, without.material_efficiency);
+    }
+
+    #[test]
+    fn from_rep_unknown_implant_ignored() {
+        let line = implant_line(&[1]);
+        assert_eq!(line.runs, 28);
+    }
+
+    const RELIC_ID: u32 = 30_000;
+    const T3_BLUEPRINT_ID: u32 = 30_001;
+
+    fn relic_line(
+        kind: ManufacturingKind,
+        product: Item,
+        relic: RelicQuality,
+    ) -> Result<Line, crate::Error> {
+        let tax: config::ManufacturingValue = serde_yaml::from_str(
+            "{manufacturing: 0.0, invention: 0.0, reaction: 0.0, copy: 0.0}",
+        )
+        .unwrap();
+        let db_rep = DatabaseResponse {
+            product: Item::new_blueprint(T3_BLUEPRINT_ID, 1, 2, 4),
+            probability: 0.26,
+            ..database_response(1.0)
+        };
+        Line::from_rep(
+            &db_rep,
+            RAITARU_ID,
+            [None; 3],
+            tax,
+            &HashMap::new(),
+            &[],
+            JobParams {
+                kind,
+                transput: Transput::new(Item::new(RELIC_ID), product),
+                max_duration: Duration::from_secs(86_400),
+                decryptor: None,
+                relic: Some(relic),
+            },
+        )
+    }
+
+    #[test]
+    fn from_rep_relic_invention() {
+        let product = Item::new_blueprint(T3_BLUEPRINT_ID, 20, 2, 4);
+        let line = relic_line(
+            ManufacturingKind::Invention,
+            product,
+            RelicQuality::Intact,
+        )
+        .unwrap();
+        assert_eq!(line.product, product);
+        // a relic is consumed by each attempt
+        assert!(line.minerals.contains(&(Item::new(RELIC_ID), line.runs)));
+        assert!(line.invention_materials.contains(&(Item::new(RELIC_ID), 1)));
+    }
+
+    #[test]
+    fn from_rep_relic_runs_match_quality() {
+        // a wrecked relic invents 3 run blueprints, not 20
+        let line = relic_line(
+            ManufacturingKind::Invention,
+            Item::new_blueprint(T3_BLUEPRINT_ID, 20, 2, 4),
+            RelicQuality::Wrecked,
+        );
+        assert!(matches!(line, Err(crate::Error::InvalidConfig(_))));
+    }
+
+    #[test]
+    fn from_rep_relic_only_for_invention() {
+        let line = relic_line(
+            ManufacturingKind::Manufacturing,
+            Item::new(T3_BLUEPRINT_ID),
+            RelicQuality::Intact,
+        );
+        assert!(matches!(line, Err(crate::Error::InvalidConfig(_))));
+    }
+
+    #[test]
+    fn from_rep_invalid_decryptor() {
+        let tax: config::ManufacturingValue = serde_yaml::from_str(
+            "{manufacturing: 0.0, invention: 0.0, reaction: 0.0, copy: 0.0}",
+        )
+        .unwrap();
+        let product = Item::new_blueprint(T3_BLUEPRINT_ID, 1, 2, 4);
+        let db_rep = DatabaseResponse {
+            product,
+            ..database_response(1.0)
+        };
+        // neither changes the invented blueprint nor is known
+        for product in [product, Item::new_blueprint(T3_BLUEPRINT_ID, 2, 2, 4)]
+        {
+            let line = Line::from_rep(
+                &db_rep,
+                RAITARU_ID,
+                [None; 3],
+                tax,
+                &HashMap::new(),
+                &[],
+                JobParams {
+                    kind: ManufacturingKind::Invention,
+                    transput: Transput::new(
+                        Item::new_blueprint(2, -1, 0, 0),
+                        product,
+                    ),
+                    max_duration: Duration::from_secs(86_400),
+                    decryptor: Some(34_201),
+                    relic: None,
+                },
+            );
+            assert!(matches!(line, Err(crate::Error::InvalidConfig(_))));
+        }
+    }
+
+    #[test]
+    fn from_rep_invalid_research() {
+        let tax: config::ManufacturingValue = serde_yaml::from_str(
+            "{manufacturing: 0.0, invention: 0.0, reaction: 0.0, copy: 0.0}",
+        )
+        .unwrap();
+        // ME can't go past 10
+        let line = Line::from_rep(
+            &database_response(0.9),
+            RAITARU_ID,
+            [None; 3],
+            tax,
+            &HashMap::new(),
+            &[],
+            JobParams {
+                kind: ManufacturingKind::ResearchMe,
+                transput: Transput::new(
+                    Item::new_blueprint(2, -1, 0, 0),
+                    Item::new_blueprint(2, -1, 11, 0),
+                ),
+                max_duration: Duration::from_secs(86_400),
+                decryptor: None,
+                relic: None,
+            },
+        );
+        assert!(matches!(line, Err(crate::Error::InvalidConfig(_))));
+    }
+
+    #[test]
+    fn job_material_quantity_no_efficiency() {
+        assert_eq!(job_material_quantity(2_000, 1, 1.0), 2_000);
+        assert_eq!(job_material_quantity(2_000, 10, 1.0), 20_000);
+    }
+
+    #[test]
+    fn job_material_quantity_blueprint_only() {
+        assert_eq!(job_material_quantity(22_222, 1, 0.9), 20_000);
+        assert_eq!(job_material_quantity(3, 1, 0.9), 3);
+        assert_eq!(job_material_quantity(100, 3, 0.9), 270);
+    }
+
+    #[test]
+    fn job_material_quantity_rounds_before_ceil() {
+        // 211 * 0.891 = 188.001, which the industry window shows as 188
+        assert_eq!(job_material_quantity(211, 1, ME10_RAITARU), 188);
+        assert_eq!(job_material_quantity(1_000, 1, ME10_RAITARU), 891);
+        // 74 * 0.9 * 0.976 = 65.0016
+        assert_eq!(job_material_quantity(74, 1, 0.9 * 0.976), 65);
+        // 75 * 0.9 * 0.976 = 65.88
+        assert_eq!(job_material_quantity(75, 1, 0.9 * 0.976), 66);
+    }
+
+    #[test]
+    fn job_material_quantity_at_least_one_per_run() {
+        // 1 * 10 * 0.87318 = 8.73 would be 9, but each run needs 1
+        assert_eq!(job_material_quantity(1, 10, ME10_RAITARU_T1), 10);
+        assert_eq!(job_material_quantity(1, 1, ME10_RAITARU_T1), 1);
+        assert_eq!(job_material_quantity(2, 10, ME10_RAITARU_T1), 18);
+    }
+
+    #[test]
+    fn job_material_quantity_per_job() {
+        // 10 runs per job in two jobs is not the same as 20 runs in one
+        let per_job = job_material_quantity(7, 10, ME10_RAITARU_T1);
+        let batch = job_material_quantity(7, 20, ME10_RAITARU_T1);
+        assert_eq!(per_job, 62);
+        assert_eq!(batch, 123);
+        assert_ne!(per_job * 2, batch);
+    }
+}
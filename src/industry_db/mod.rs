@@ -8,18 +8,28 @@ mod db;
 mod db_efficiency;
 mod db_response;
 mod decryptors;
+mod fitting;
+mod invention;
 mod line;
+mod reprocessing;
+mod research;
 mod sqlite_db;
 mod static_data;
 mod volume;
 
-pub use line::Line;
+pub use invention::{InventionOutcome, RelicQuality};
+pub use line::{JobParams, Line, Sequencing};
+pub use reprocessing::Reprocessing;
 pub use volume::Volume;
 
 use db::{DatabaseParamsInclude, InnerDatabase};
 use db_efficiency::*;
 use db_response::DatabaseResponse;
 use decryptors::*;
+use fitting::*;
+use invention::*;
+use reprocessing::*;
+use research::*;
 use static_data::*;
 
 pub trait IndustryDatabase: Send + Sync {
@@ -32,17 +42,60 @@ pub trait IndustryDatabase: Send + Sync {
         tax: config::ManufacturingValue,
         // config
         skills: &HashMap<u32, u8>,
+        implants: &[u32],
         // production line
         kind: config::ManufacturingKind,
         transput: config::Transput,
         max_duration: Duration,
         decryptor: Option<u32>,
+        relic: bool,
     ) -> Result<Line, crate::Error>;
+    // one invention line per decryptor, including no decryptor
+    async fn compute_decryptor_lines(
+        &self,
+        // location config
+        system_id: u32,
+        structure_id: u32,
+        rigs: [Option<u32>; 3],
+        tax: config::ManufacturingValue,
+        // config
+        skills: &HashMap<u32, u8>,
+        implants: &[u32],
+        // production line
+        transput: config::Transput,
+        max_duration: Duration,
+        relic: bool,
+    ) -> Result<Vec<Line>, crate::Error>;
+    // rejects rigs that can't be fitted, and jobs that can't be run
+    async fn validate_fitting(
+        &self,
+        // location config
+        system_id: u32,
+        structure_id: u32,
+        rigs: [Option<u32>; 3],
+        // production lines
+        kinds: &[config::ManufacturingKind],
+    ) -> Result<(), crate::Error>;
+    // none if the item can't be reprocessed
+    async fn compute_reprocessing(
+        &self,
+        // location config
+        system_id: u32,
+        reprocessing: &config::LocationReprocessing,
+        // config
+        skills: &HashMap<u32, u8>,
+        implant_bonus: f64,
+        item: Item,
+    ) -> Result<Option<Reprocessing>, crate::Error>;
     async fn get_volume(
         &self,
         item: Item,
     ) -> Result<Option<Volume>, crate::Error>;
     async fn get_name(&self, item: Item) -> Result<String, crate::Error>;
+    async fn get_market_groups(
+        &self,
+        type_id: u32,
+    ) -> Result<Vec<u32>, crate::Error>;
 }
 
 pub async fn new_industry_database(
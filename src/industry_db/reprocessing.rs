@@ -0,0 +1,189 @@
+use super::*;
+
+pub struct ReprocessingResponse {
+    pub portion: i64,                // units reprocessed at once
+    pub skill: Option<u32>,          // ore processing skill, none for scrap
+    pub materials: Vec<(Item, i64)>, // materials from one portion, before yield
+    pub security: f64,
+}
+
+pub struct Reprocessing {
+    pub item: Item,
+    pub portion: i64,                // units reprocessed at once
+    pub materials: Vec<(Item, i64)>, // materials from one portion, after yield
+    pub tax: f64,  // portion of the materials' estimated value
+    pub ore: bool, // ore, ice or moon ore, rather than scrap
+}
+
+impl Reprocessing {
+    pub fn from_rep(
+        rep: &ReprocessingResponse,
+        item: Item,
+        cfg: &config::LocationReprocessing,
+        skills: &HashMap<u32, u8>,
+        implant_bonus: f64,
+    ) -> Reprocessing {
+        let level = |type_id: u32| skills.get(&type_id).copied().unwrap_or(0);
+        let efficiency = match rep.skill {
+            Some(skill) => ore_reprocessing_efficiency(
+                cfg,
+                rep.security.into(),
+                level(REPROCESSING_SKILL),
+                level(REPROCESSING_EFFICIENCY_SKILL),
+                level(skill),
+                implant_bonus,
+            ),
+            None => scrap_reprocessing_efficiency(level(
+                SCRAPMETAL_PROCESSING_SKILL,
+            )),
+        };
+        Reprocessing {
+            item,
+            portion: rep.portion,
+            materials: rep
+                .materials
+                .iter()
+                // partial units are lost
+                .map(|&(m, q)| (m, (q as f64 * efficiency).floor() as i64))
+                .filter(|&(_, q)| q > 0)
+                .collect(),
+            tax: cfg.tax,
+            ore: rep.skill.is_some(),
+        }
+    }
+}
+
+// (50 + rig) * (1 + sec) * (1 + structure) * skills * (1 + implant)
+pub fn ore_reprocessing_efficiency(
+    cfg: &config::LocationReprocessing,
+    security: Security,
+    reprocessing_level: u8,
+    efficiency_level: u8,
+    ore_level: u8,
+    implant_bonus: f64, // of the reprocessing character
+) -> f64 {
+    // rigs yield more outside of highsec
+    let security_multiplier = match (cfg.rig_yield > 0.0, security) {
+        (false, _) | (true, Security::High) => 1.0,
+        (true, Security::Low) => 1.06,
+        (true, Security::Zero) => 1.12,
+    };
+    (50.0 + cfg.rig_yield) / 100.0
+        * security_multiplier
+        * (1.0 + cfg.structure_bonus)
+        * (1.0 + 0.03 * reprocessing_level as f64)
+        * (1.0 + 0.02 * efficiency_level as f64)
+        * (1.0 + 0.02 * ore_level as f64)
+        * (1.0 + implant_bonus)
+}
+
+// scrap ignores structure, rigs and implants
+pub fn scrap_reprocessing_efficiency(scrapmetal_level: u8) -> f64 {
+    0.5 * (1.0 + 0.02 * scrapmetal_level as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VELDSPAR: u32 = 1230;
+    const VELDSPAR_PROCESSING: u32 = 60377;
+    const TRITANIUM: u32 = 34;
+
+    fn cfg(
+        structure_bonus: f64,
+        rig_yield: f64,
+    ) -> config::LocationReprocessing {
+        config::LocationReprocessing {
+            tax: 0.0,
+            structure_bonus,
+            rig_yield,
+            character: None,
+        }
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-4,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn ore_efficiency_in_game_yields() {
+        // station, every skill at 5: 69.58% as shown in the reprocessing window
+        let yield_ = ore_reprocessing_efficiency(
+            &cfg(0.0, 0.0),
+            Security::High,
+            5,
+            5,
+            5,
+            0.0,
+        );
+        assert_close(yield_, 0.69575);
+        // tatara with a T1 rig in highsec: 74.87%
+        let yield_ = ore_reprocessing_efficiency(
+            &cfg(0.055, 1.0),
+            Security::High,
+            5,
+            5,
+            5,
+            0.0,
+        );
+        assert_close(yield_, 0.748697);
+        // tatara with a T2 rig in nullsec and RX-804: 90.63%
+        let yield_ = ore_reprocessing_efficiency(
+            &cfg(0.055, 3.0),
+            Security::Zero,
+            5,
+            5,
+            5,
+            0.04,
+        );
+        assert_close(yield_, 0.906281);
+        // untrained, 50% before any bonus
+        let yield_ = ore_reprocessing_efficiency(
+            &cfg(0.0, 0.0),
+            Security::Zero,
+            0,
+            0,
+            0,
+            0.0,
+        );
+        assert_close(yield_, 0.5);
+    }
+
+    #[test]
+    fn scrap_efficiency_in_game_yields() {
+        assert_close(scrap_reprocessing_efficiency(0), 0.5);
+        assert_close(scrap_reprocessing_efficiency(5), 0.55);
+    }
+
+    #[test]
+    fn from_rep_implant_is_per_character() {
+        // 100 veldspar is 400 tritanium before yield
+        let rep = ReprocessingResponse {
+            portion: 100,
+            skill: Some(VELDSPAR_PROCESSING),
+            materials: vec![(Item::new(TRITANIUM), 400)],
+            security: 1.0,
+        };
+        let skills = HashMap::from([
+            (REPROCESSING_SKILL, 5),
+            (REPROCESSING_EFFICIENCY_SKILL, 5),
+            (VELDSPAR_PROCESSING, 5),
+        ]);
+        let item = Item::new(VELDSPAR);
+        // 400 * 0.69575 = 278.3
+        let without =
+            Reprocessing::from_rep(&rep, item, &cfg(0.0, 0.0), &skills, 0.0);
+        assert_eq!(without.materials, vec![(Item::new(TRITANIUM), 278)]);
+        assert!(without.ore);
+        // 400 * 0.69575 * 1.04 = 289.43
+        let with =
+            Reprocessing::from_rep(&rep, item, &cfg(0.0, 0.0), &skills, 0.04);
+        assert_eq!(with.materials, vec![(Item::new(TRITANIUM), 289)]);
+    }
+}
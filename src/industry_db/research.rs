@@ -0,0 +1,102 @@
+use super::*;
+
+// levels researched by a research line, 1 level is 1% ME or 2% TE
+pub fn research_levels(
+    kind: ManufacturingKind,
+    blueprint: Item,
+    product: Item,
+) -> Option<(i8, i8)> {
+    let (from, to) = match kind {
+        ManufacturingKind::ResearchMe
+            if blueprint.te == product.te && product.me <= 10 =>
+        {
+            (blueprint.me, product.me)
+        }
+        ManufacturingKind::ResearchTe
+            if blueprint.me == product.me
+                && product.te <= 20
+                && blueprint.te % 2 == 0
+                && product.te % 2 == 0 =>
+        {
+            (blueprint.te / 2, product.te / 2)
+        }
+        _ => return None,
+    };
+    match blueprint.type_id == product.type_id && 0 <= from && from < to {
+        true => Some((from, to)),
+        false => None,
+    }
+}
+
+// time for researching levels (from, to], relative to level 1
+pub fn research_level_multiplier(from: i8, to: i8) -> f64 {
+    RESEARCH_LEVEL_MULTIPLIERS[from as usize..to as usize]
+        .iter()
+        .sum::<f64>()
+        / RESEARCH_LEVEL_MULTIPLIERS[0]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bpo(me: i8, te: i8) -> Item {
+        Item::new_blueprint(1101, -1, me, te)
+    }
+
+    #[test]
+    fn research_levels_me() {
+        let kind = ManufacturingKind::ResearchMe;
+        assert_eq!(research_levels(kind, bpo(0, 0), bpo(10, 0)), Some((0, 10)));
+        assert_eq!(research_levels(kind, bpo(4, 8), bpo(6, 8)), Some((4, 6)));
+        // TE can't change, ME can't go past 10 or backwards
+        assert_eq!(research_levels(kind, bpo(0, 0), bpo(10, 2)), None);
+        assert_eq!(research_levels(kind, bpo(0, 0), bpo(11, 0)), None);
+        assert_eq!(research_levels(kind, bpo(5, 0), bpo(5, 0)), None);
+        assert_eq!(research_levels(kind, bpo(5, 0), bpo(3, 0)), None);
+        let other = Item::new_blueprint(1102, -1, 10, 0);
+        assert_eq!(research_levels(kind, bpo(0, 0), other), None);
+    }
+
+    #[test]
+    fn research_levels_te() {
+        let kind = ManufacturingKind::ResearchTe;
+        // a level is 2% TE
+        assert_eq!(research_levels(kind, bpo(0, 0), bpo(0, 20)), Some((0, 10)));
+        assert_eq!(
+            research_levels(kind, bpo(10, 4), bpo(10, 10)),
+            Some((2, 5))
+        );
+        assert_eq!(research_levels(kind, bpo(0, 0), bpo(0, 5)), None);
+        assert_eq!(research_levels(kind, bpo(0, 0), bpo(0, 22)), None);
+        assert_eq!(research_levels(kind, bpo(0, 0), bpo(2, 20)), None);
+        let manufacturing = ManufacturingKind::Manufacturing;
+        assert_eq!(research_levels(manufacturing, bpo(0, 0), bpo(0, 20)), None);
+    }
+
+    #[test]
+    fn research_level_multiplier_rank_times() {
+        // a rank 1 blueprint takes 105 s for level 1, 5,724 s up to level 5
+        // and 441,679 s (5d 2h 41m 19s) up to level 10
+        assert_eq!(research_level_multiplier(0, 1) * 105.0, 105.0);
+        assert!(
+            (research_level_multiplier(0, 5) * 105.0 - 5_724.0).abs() < 1e-6
+        );
+        assert!(
+            (research_level_multiplier(0, 10) * 105.0 - 441_679.0).abs() < 1e-6
+        );
+        // level 9 to 10 alone is 256,000 s, x10 for a rank 10 blueprint
+        assert!(
+            (research_level_multiplier(9, 10) * 1_050.0 - 2_560_000.0).abs()
+                < 1e-6
+        );
+        // levels add up
+        assert!(
+            (research_level_multiplier(0, 4)
+                + research_level_multiplier(4, 10)
+                - research_level_multiplier(0, 10))
+            .abs()
+                < 1e-9
+        );
+    }
+}
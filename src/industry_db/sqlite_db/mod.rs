@@ -24,6 +24,7 @@ impl InnerDatabase for SqliteDb {
         include: DatabaseParamsInclude,
     ) -> Result<DatabaseResponse, Self::Error> {
         let mut conn = self.inner.acquire().await?;
+        let blueprint_id = blueprint.type_id;
         let (blueprint, identifiers) =
             select_blueprint(&mut conn, product_id, blueprint, kind).await?;
         let minerals = match include.minerals {
@@ -61,6 +62,17 @@ impl InnerDatabase for SqliteDb {
             true => select_security(&mut conn, system_id).await?,
             false => 0.0,
         };
+        let invention_skills = match include.invention_skills
+            && kind == ManufacturingKind::Invention
+        {
+            true => select_invention_skills(&mut conn, blueprint_id).await?,
+            false => InventionSkills::default(),
+        };
+        let decryptors =
+            match include.decryptors && kind == ManufacturingKind::Invention {
+                true => select_decryptors(&mut conn).await?,
+                false => Vec::new(),
+            };
         Ok(DatabaseResponse {
             product: blueprint.product,
             probability: blueprint.probability,
@@ -70,8 +82,56 @@ impl InnerDatabase for SqliteDb {
             installation_minerals: installation_minerals,
             efficiencies: efficiencies,
             security: security,
+            invention_skills: invention_skills,
+            decryptors: decryptors,
+        })
+    }
+    async fn get_fitting(
+        &self,
+        structure_id: u32,
+        rigs: [Option<u32>; 3],
+        system_id: u32,
+    ) -> Result<FittingResponse, Self::Error> {
+        let mut conn = self.inner.acquire().await?;
+        let structure = match select_structure(&mut conn, structure_id).await {
+            Ok(s) => Some(s),
+            Err(sqlx::Error::RowNotFound) => None,
+            Err(e) => return Err(e),
+        };
+        let mut fitted_rigs = Vec::new();
+        for rig_id in rigs.into_iter().flatten() {
+            let rig = match select_rig(&mut conn, rig_id).await {
+                Ok(r) => Some(r),
+                Err(sqlx::Error::RowNotFound) => None,
+                Err(e) => return Err(e),
+            };
+            fitted_rigs.push((rig_id, rig));
+        }
+        Ok(FittingResponse {
+            structure: structure,
+            rigs: fitted_rigs,
+            security: select_security(&mut conn, system_id).await?,
         })
     }
+    async fn get_reprocessing(
+        &self,
+        item: u32,
+        system_id: u32,
+    ) -> Result<Option<ReprocessingResponse>, Self::Error> {
+        let mut conn = self.inner.acquire().await?;
+        let reprocessing = match select_reprocessing(&mut conn, item).await {
+            Ok(r) => r,
+            Err(sqlx::Error::RowNotFound) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        Ok(Some(ReprocessingResponse {
+            portion: reprocessing.portion,
+            skill: reprocessing.skill_type_id.map(|id| id as u32),
+            materials: select_minerals(&mut conn, reprocessing.minerals)
+                .await?,
+            security: select_security(&mut conn, system_id).await?,
+        }))
+    }
     async fn get_volume(
         &self,
         item: u32,
@@ -87,6 +147,13 @@ impl InnerDatabase for SqliteDb {
         let mut conn = self.inner.acquire().await?;
         select_type_name(&mut conn, item).await
     }
+    async fn get_market_groups(
+        &self,
+        item: u32,
+    ) -> Result<Vec<u32>, Self::Error> {
+        let mut conn = self.inner.acquire().await?;
+        select_market_groups(&mut conn, item).await
+    }
 }
 
 impl SqliteDb {
@@ -155,6 +222,14 @@ async fn select_blueprint(
                         DEFAULT_INVENTION_ME,
                         DEFAULT_INVENTION_TE,
                     ),
+                    // the researched blueprint, levels are up to the line
+                    ManufacturingKind::ResearchMe
+                    | ManufacturingKind::ResearchTe => Item::new_blueprint(
+                        product_id,
+                        blueprint.runs,
+                        blueprint.me,
+                        blueprint.te,
+                    ),
                     _manufacturing_or_reaction => Item::new(product_id),
                 },
                 portion: match kind.is_science() {
@@ -198,12 +273,27 @@ struct DbEfficiency {
     time_efficiency: f64,
     material_efficiency: f64,
     cost_efficiency: f64,
-    probability_multiplier: f64,
     high_sec_multiplier: f64,
     low_sec_multiplier: f64,
     zero_sec_multiplier: f64,
 }
 
+impl DbEfficiency {
+    fn into_efficiency(self) -> (u32, Efficiency) {
+        (
+            self.type_id as u32,
+            Efficiency::new(
+                self.time_efficiency,
+                self.material_efficiency,
+                self.cost_efficiency,
+                self.high_sec_multiplier,
+                self.low_sec_multiplier,
+                self.zero_sec_multiplier,
+            ),
+        )
+    }
+}
+
 async fn select_rigs_skills_structures(
     conn: &mut SqlitePoolConnection,
     kind: ManufacturingKind,
@@ -217,24 +307,141 @@ async fn select_rigs_skills_structures(
         id,
     )
     .fetch(&mut **conn)
-    .map_ok(|e| {
-        (
-            e.type_id as u32,
-            Efficiency::new(
-                e.time_efficiency,
-                e.material_efficiency,
-                e.cost_efficiency,
-                e.probability_multiplier,
-                e.low_sec_multiplier,
-                e.high_sec_multiplier,
-                e.zero_sec_multiplier,
-            ),
-        )
-    })
+    .map_ok(|e| e.into_efficiency())
     .try_collect()
     .await
 }
 
+struct DbInventionSkill {
+    skill_type_id: i64,
+    encryption: i64,
+}
+
+async fn select_invention_skills(
+    conn: &mut SqlitePoolConnection,
+    blueprint_id: u32,
+) -> sqlx::Result<InventionSkills> {
+    sqlx::query_file_as!(
+        DbInventionSkill,
+        "sqlite_build_data/select_invention_skills.sql",
+        blueprint_id,
+    )
+    .fetch(&mut **conn)
+    .try_fold(InventionSkills::default(), |mut skills, s| async move {
+        match s.encryption != 0 {
+            true => skills.encryption = Some(s.skill_type_id as u32),
+            false => skills.datacores.push(s.skill_type_id as u32),
+        }
+        Ok(skills)
+    })
+    .await
+}
+
+struct DbDecryptor {
+    type_id: i64,
+    probability_multiplier: f64,
+    runs: i64,
+    me: i64,
+    te: i64,
+}
+
+async fn select_decryptors(
+    conn: &mut SqlitePoolConnection,
+) -> sqlx::Result<Decryptors> {
+    sqlx::query_file_as!(DbDecryptor, "sqlite_build_data/select_decryptors.sql")
+        .fetch(&mut **conn)
+        .map_ok(|d| {
+            (
+                Item::new_blueprint(
+                    d.type_id as u32,
+                    d.runs as i16,
+                    d.me as i8,
+                    d.te as i8,
+                ),
+                d.probability_multiplier,
+            )
+        })
+        .try_collect()
+        .await
+}
+
+struct DbStructure {
+    rig_size: i64,
+    manufacturing: i64,
+    science: i64,
+    reaction: i64,
+}
+
+async fn select_structure(
+    conn: &mut SqlitePoolConnection,
+    type_id: u32,
+) -> sqlx::Result<StructureInfo> {
+    sqlx::query_file_as!(
+        DbStructure,
+        "sqlite_build_data/select_structure.sql",
+        type_id,
+    )
+    .fetch_one(&mut **conn)
+    .await
+    .map(|s| StructureInfo {
+        rig_size: s.rig_size,
+        manufacturing: s.manufacturing != 0,
+        science: s.science != 0,
+        reaction: s.reaction != 0,
+    })
+}
+
+struct DbRig {
+    rig_size: i64,
+    group_id: i64,
+    high_sec: i64,
+}
+
+async fn select_rig(
+    conn: &mut SqlitePoolConnection,
+    type_id: u32,
+) -> sqlx::Result<RigInfo> {
+    let rig = sqlx::query_file_as!(
+        DbRig,
+        "sqlite_build_data/select_rig.sql",
+        type_id
+    )
+    .fetch_one(&mut **conn)
+    .await?;
+    let kinds =
+        sqlx::query_file!("sqlite_build_data/select_rig_kinds.sql", type_id)
+            .fetch_all(&mut **conn)
+            .await?
+            .into_iter()
+            .map(|r| from_database_kind(r.kind))
+            .collect();
+    Ok(RigInfo {
+        rig_size: rig.rig_size,
+        group_id: rig.group_id as u32,
+        high_sec: rig.high_sec != 0,
+        kinds,
+    })
+}
+
+struct DbReprocessing {
+    portion: i64,
+    skill_type_id: Option<i64>,
+    minerals: SqliteID,
+}
+
+async fn select_reprocessing(
+    conn: &mut SqlitePoolConnection,
+    type_id: u32,
+) -> sqlx::Result<DbReprocessing> {
+    sqlx::query_file_as!(
+        DbReprocessing,
+        "sqlite_build_data/select_reprocessing.sql",
+        type_id,
+    )
+    .fetch_one(&mut **conn)
+    .await
+}
+
 struct DbSecurity {
     security: f64,
 }
@@ -288,3 +495,58 @@ async fn select_type_name(
     .await
     .map(|n| n.name)
 }
+
+struct DbMarketGroup {
+    market_group_id: i64,
+}
+
+async fn select_market_groups(
+    conn: &mut SqlitePoolConnection,
+    type_id: u32,
+) -> sqlx::Result<Vec<u32>> {
+    sqlx::query_file_as!(
+        DbMarketGroup,
+        "sqlite_build_data/select_market_groups.sql",
+        type_id,
+    )
+    .fetch_all(&mut **conn)
+    .await
+    .map(|groups| {
+        groups
+            .into_iter()
+            .map(|g| g.market_group_id as u32)
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn efficiency_security_multipliers_not_swapped() {
+        // T1 ME rig, 2.0% in highsec, 3.8% in lowsec, 4.2% in nullsec
+        let (type_id, efficiency) = DbEfficiency {
+            type_id: 43920,
+            time_efficiency: 0.0,
+            material_efficiency: 0.02,
+            cost_efficiency: 0.0,
+            high_sec_multiplier: 1.0,
+            low_sec_multiplier: 1.9,
+            zero_sec_multiplier: 2.1,
+        }
+        .into_efficiency();
+        assert_eq!(type_id, 43920);
+        for (security, expected) in [(1.0, 0.98), (0.3, 0.962), (-0.5, 0.958)] {
+            let (mut me, mut te, mut ce) = (1.0, 1.0, 1.0);
+            efficiency.add_efficiencies(
+                &mut me,
+                &mut te,
+                &mut ce,
+                SkillLevel::One,
+                security.into(),
+            );
+            assert!((me - expected).abs() < 1e-9, "{}: {}", security, me);
+        }
+    }
+}
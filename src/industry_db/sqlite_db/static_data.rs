@@ -7,6 +7,8 @@ pub const fn into_database_kind(kind: ManufacturingKind) -> SqliteKind {
         ManufacturingKind::Invention => 2,
         ManufacturingKind::Copy => 3,
         ManufacturingKind::Reaction => 4,
+        ManufacturingKind::ResearchMe => 5,
+        ManufacturingKind::ResearchTe => 6,
     }
 }
 
@@ -16,6 +18,8 @@ pub const fn from_database_kind(kind: SqliteKind) -> ManufacturingKind {
         2 => ManufacturingKind::Invention,
         3 => ManufacturingKind::Copy,
         4 => ManufacturingKind::Reaction,
+        5 => ManufacturingKind::ResearchMe,
+        6 => ManufacturingKind::ResearchTe,
         _ => unreachable!(),
     }
 }
@@ -1,14 +1,25 @@
 mod api_data;
 mod composite;
 mod config;
+mod history;
 mod industry_db;
 mod runtime;
 
 mod error;
-use std::io::{self, Write};
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+};
 
 use error::Error;
 
+const USAGE: &str = "\
+usage: eve_industry_program [command]
+
+  (none)     plan the builds, write the output and record the plan
+  scenarios  compare the scenarios in scenarios.yaml against the config
+  compare    compare the latest recorded plan against what happened";
+
 #[tokio::main]
 async fn main() {
     let mut stdout = io::stdout();
@@ -16,58 +27,163 @@ async fn main() {
     print!("Reading config... ");
     stdout.flush().unwrap();
     let cfg = config::Config::read().unwrap();
-    print!("Done\n");
+    println!("Done");
 
     print!("Reading database... ");
     stdout.flush().unwrap();
     let db = industry_db::new_industry_database().await.unwrap();
+    println!("Done");
+
+    print!("Reading API data... ");
+    stdout.flush().unwrap();
+    let api = api_data::Api::read().unwrap();
+    println!("Done");
+
+    match std::env::args().nth(1).as_deref() {
+        // compares what-if scenarios against the config as it is
+        Some("scenarios") => {
+            let scenarios = config::Scenario::read_all().unwrap();
+            let market_groups = match scenarios
+                .iter()
+                .any(|scenario| scenario.uses_market_groups())
+            {
+                true => composite::get_db_market_groups(
+                    api.market_orders
+                        .values()
+                        .flat_map(|orders| orders.keys().copied()),
+                    &db,
+                )
+                .await
+                .unwrap(),
+                false => HashMap::new(),
+            };
+            println!("\nScenario 'baseline'");
+            let baseline = plan(&cfg, &api, &db, false).await;
+            let mut summaries = Vec::new();
+            for scenario in scenarios.iter() {
+                println!("\nScenario '{}'", scenario.name);
+                let summary = plan(
+                    &scenario.apply(&cfg),
+                    &api.with_scenario(scenario, &market_groups),
+                    &db,
+                    false,
+                )
+                .await;
+                summaries.push((scenario.name.clone(), summary));
+            }
+            let report = runtime::OutputScenarios::new(baseline, summaries);
+            println!();
+            report.print();
+            report.write().unwrap();
+        }
+        // the latest recorded plan against what actually happened since
+        Some("compare") => {
+            print!("Ingesting jobs and transactions... ");
+            stdout.flush().unwrap();
+            let history = history::History::connect().await.unwrap();
+            history
+                .ingest(
+                    &api_data::read_industry_jobs().unwrap(),
+                    &api_data::read_wallet_transactions().unwrap(),
+                )
+                .await
+                .unwrap();
+            println!("Done");
+            match history.compare_latest().await.unwrap() {
+                Some(comparison) => {
+                    println!();
+                    comparison.print();
+                    comparison.write().unwrap();
+                }
+                None => println!("No plans recorded yet"),
+            }
+        }
+        Some(command) => {
+            eprintln!("unknown command '{}'\n\n{}", command, USAGE);
+            std::process::exit(2);
+        }
+        None => {
+            plan(&cfg, &api, &db, true).await;
+        }
+    }
+}
+
+// plans the builds, writing the output and recording it if asked to
+async fn plan(
+    cfg: &config::Config,
+    api: &api_data::Api,
+    db: &impl industry_db::IndustryDatabase,
+    write: bool,
+) -> runtime::OutputSummary {
+    let mut stdout = io::stdout();
+
+    print!("Reading lines... ");
+    stdout.flush().unwrap();
+    composite::validate_locations(cfg.locations.iter(), &cfg.characters, db)
+        .await
+        .unwrap();
     let db_lines = composite::get_db_lines(
         cfg.locations.iter(),
         &cfg.skills,
+        &cfg.characters,
         cfg.max_time,
-        &db,
+        db,
+    )
+    .await
+    .unwrap();
+    let db_reprocessing = composite::get_db_reprocessing(
+        cfg.locations.iter(),
+        &cfg.skills,
+        &cfg.characters,
+        &cfg.reprocessing_ores,
+        db,
     )
     .await
     .unwrap();
     let (type_volumes, type_names) = composite::get_db_volumes_and_names(
         cfg.locations.iter(),
-        db_lines.values(),
-        &db,
+        db_lines.values().flatten(),
+        db_reprocessing.values().flat_map(|r| r.values()),
+        db,
     )
     .await
     .unwrap();
-    print!("Done\n");
-
-    print!("Reading API data... ");
-    stdout.flush().unwrap();
-    let api = api_data::Api::read().unwrap();
-    print!("Done\n");
+    println!("Done");
 
     print!("Building runtime... ");
     stdout.flush().unwrap();
     let mut runtime = runtime::RuntimeData::new(
-        &cfg.locations,
-        &cfg.slots,
-        cfg.max_time,
-        cfg.daily_flex_time,
-        cfg.min_profit,
-        cfg.min_margin,
+        cfg,
         &db_lines,
+        &db_reprocessing,
         &type_volumes,
-        &api.adjusted_prices,
-        &api.cost_indices,
-        &api.market_orders,
-        &api.assets,
+        api,
     );
-    print!("Done\n");
+    println!("Done");
 
     print!("Calculating... ");
     stdout.flush().unwrap();
     runtime.build();
-    print!("Done\n");
+    println!("Done");
 
-    print!("Writing output... ");
-    stdout.flush().unwrap();
-    runtime.write(&type_names, &type_volumes).unwrap();
-    print!("Done\n");
+    if write {
+        print!("Writing output... ");
+        stdout.flush().unwrap();
+        runtime.write(&type_names, &type_volumes).unwrap();
+        println!("Done");
+
+        print!("Recording plan... ");
+        stdout.flush().unwrap();
+        let history = history::History::connect().await.unwrap();
+        let plan_id = history
+            .record_plan(
+                &history::inputs_hash().unwrap(),
+                &runtime.record(&type_names, &type_volumes).unwrap(),
+            )
+            .await
+            .unwrap();
+        println!("Done, plan {}", plan_id);
+    }
+
+    runtime.summary(&type_names, &type_volumes)
 }
@@ -12,9 +12,19 @@ impl<'db> DbLineTransformed<'db> {
         max_time: Duration,
         daily_flex_time: Duration,
     ) -> Self {
-        let (extra_duration, num_sequences) = if (inner.duration * 2) > max_time
+        let (extra_duration, num_sequences) = if inner.sequencing
+            == industry_db::Sequencing::Once
+            || (inner.duration * 2) > max_time
         {
             (Duration::new(0, 0), 1)
+        } else if inner.sequencing == industry_db::Sequencing::BackToBack {
+            // as many BPCs as fit in the window, with time to start each job
+            let time_per_sequence = inner.duration + daily_flex_time;
+            let multiplier = (max_time.as_secs_f64()
+                / time_per_sequence.as_secs_f64())
+            .floor()
+            .max(1.0) as i64;
+            (daily_flex_time, multiplier)
         } else {
             let flexed_time_per_sequence = inner.duration + daily_flex_time;
             // final_time_per_sequence = the smallest multiple of 24 hours
@@ -54,8 +64,47 @@ impl<'db> DbLineTransformed<'db> {
         })
     }
 
-    pub fn cost_multiplier(&self) -> f64 {
-        self.inner.cost_multiplier
+    pub fn minerals_with_me(
+        &self,
+        me: i8,
+    ) -> impl Iterator<Item = (Item, i64)> + '_ {
+        self.inner
+            .minerals_with_me(me)
+            .map(move |(item, quantity)| (item, quantity * self.num_sequences))
+    }
+
+    pub fn product(&self) -> Item {
+        self.inner.product
+    }
+
+    pub fn blueprint(&self) -> Item {
+        self.inner.blueprint
+    }
+
+    pub fn decryptor(&self) -> Option<Item> {
+        self.inner.decryptor
+    }
+
+    pub fn eiv_multiplier(&self) -> f64 {
+        self.inner.eiv_multiplier
+    }
+
+    pub fn cost_efficiency(&self) -> f64 {
+        self.inner.cost_efficiency
+    }
+
+    pub fn facility_tax(&self) -> f64 {
+        self.inner.facility_tax
+    }
+
+    // time needed for a single sequence
+    pub fn duration(&self) -> Duration {
+        self.inner.duration
+    }
+
+    // time between the end of a sequence and the start of the next
+    pub fn extra_duration(&self) -> Duration {
+        self.extra_duration
     }
 
     pub fn runs(&self) -> i64 {
@@ -66,10 +115,41 @@ impl<'db> DbLineTransformed<'db> {
         self.inner.portion * self.num_sequences
     }
 
+    pub fn expected_portion(&self) -> f64 {
+        self.inner.expected_portion * self.num_sequences as f64
+    }
+
+    // the outcome of every invention attempt over all sequences
+    pub fn invention(&self) -> Option<industry_db::InventionOutcome> {
+        self.inner.invention.map(|outcome| {
+            outcome.with_attempts(outcome.attempts * self.num_sequences)
+        })
+    }
+
+    pub fn invention_materials(
+        &self,
+    ) -> impl Iterator<Item = (Item, i64)> + '_ {
+        self.inner.invention_materials.iter().copied()
+    }
+
     pub fn num_sequences(&self) -> i64 {
         self.num_sequences
     }
 
+    // BPCs consumed per slot over the window, one per job
+    pub fn num_bpcs_consumed(&self) -> i64 {
+        self.inner
+            .minerals
+            .iter()
+            .filter(|(item, _)| item.is_bpc())
+            .map(|(_, quantity)| *quantity * self.num_sequences)
+            .sum()
+    }
+
+    pub fn bpc_runs_used(&self) -> f64 {
+        self.inner.bpc_runs_used()
+    }
+
     pub fn runs_per_sequence(&self) -> i64 {
         self.inner.runs
     }
@@ -1,22 +1,22 @@
 use super::*;
 use crate::config::{self, Item};
 use std::{
-    cell::{Ref, RefCell},
-    collections::HashMap,
+    collections::BTreeMap,
     iter,
-    rc::Rc,
+    sync::{Arc, RwLock, RwLockReadGuard},
+    time::Duration,
 };
 
 pub struct DeliveryPipe<'cfg, 'db, 'api> {
-    pub routes: Vec<Rc<DeliveryRoute<'cfg, 'db, 'api>>>,
-    pub deliveries: RefCell<HashMap<Item, i64>>,
+    pub routes: Vec<Arc<DeliveryRoute<'cfg, 'db, 'api>>>,
+    pub deliveries: RwLock<BTreeMap<Item, i64>>,
 }
 
 impl<'cfg, 'db, 'api> DeliveryPipe<'cfg, 'db, 'api> {
-    pub fn new(routes: Vec<Rc<DeliveryRoute<'cfg, 'db, 'api>>>) -> Self {
+    pub fn new(routes: Vec<Arc<DeliveryRoute<'cfg, 'db, 'api>>>) -> Self {
         Self {
             routes,
-            deliveries: RefCell::new(HashMap::new()),
+            deliveries: RwLock::new(BTreeMap::new()),
         }
     }
 
@@ -31,12 +31,18 @@ impl<'cfg, 'db, 'api> DeliveryPipe<'cfg, 'db, 'api> {
     pub fn delivery_rate(&self) -> config::DeliveryRate {
         let mut rate = config::DeliveryRate::new();
         for route in self.routes.iter() {
-            rate.collateral_rate += route.delivery_rate().collateral_rate;
-            rate.m3_rate += route.delivery_rate().m3_rate;
+            let route_rate = route.delivery_rate();
+            rate.collateral_rate += route_rate.collateral_rate;
+            rate.m3_rate += route_rate.m3_rate;
+            rate.capital_rate += route_rate.capital_rate;
         }
         rate
     }
 
+    pub fn transit_time(&self) -> Duration {
+        self.routes.iter().map(|route| route.transit_time()).sum()
+    }
+
     pub fn locations(
         &self,
     ) -> impl Iterator<Item = &Location<'cfg, 'db, 'api>> {
@@ -53,18 +59,18 @@ impl<'cfg, 'db, 'api> DeliveryPipe<'cfg, 'db, 'api> {
     }
 
     pub fn deliver(&self, item: Item, volume: i64) {
-        *self.deliveries.borrow_mut().entry(item).or_insert(0) += volume;
+        *self.deliveries.write().unwrap().entry(item).or_insert(0) += volume;
     }
 
     pub fn deliveries(&self) -> DeliveryPipeDeliveries {
         DeliveryPipeDeliveries {
-            inner: self.deliveries.borrow(),
+            inner: self.deliveries.read().unwrap(),
         }
     }
 }
 
 pub struct DeliveryPipeDeliveries<'dp> {
-    inner: Ref<'dp, HashMap<Item, i64>>,
+    inner: RwLockReadGuard<'dp, BTreeMap<Item, i64>>,
 }
 
 impl<'dp> DeliveryPipeDeliveries<'dp> {
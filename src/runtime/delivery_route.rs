@@ -1,38 +1,49 @@
 use super::*;
 use crate::config;
 use std::{
-    cell::{Ref, RefCell},
-    rc::Rc,
+    sync::{Arc, RwLock, RwLockReadGuard},
+    time::Duration,
 };
 
 pub struct DeliveryRoute<'cfg, 'db, 'api> {
     pub inner: &'cfg config::DeliveryRoute,
-    pub src: Rc<Location<'cfg, 'db, 'api>>,
-    pub dst: Rc<Location<'cfg, 'db, 'api>>,
-    pub pipes: RefCell<Vec<Rc<DeliveryPipe<'cfg, 'db, 'api>>>>,
+    pub src: Arc<Location<'cfg, 'db, 'api>>,
+    pub dst: Arc<Location<'cfg, 'db, 'api>>,
+    pub pipes: RwLock<Vec<Arc<DeliveryPipe<'cfg, 'db, 'api>>>>,
+    capital_cost: f64, // daily, as a portion of the value in transit
 }
 
 impl<'cfg, 'db, 'api> DeliveryRoute<'cfg, 'db, 'api> {
     pub fn new(
         inner: &'cfg config::DeliveryRoute,
-        src: Rc<Location<'cfg, 'db, 'api>>,
-        dst: Rc<Location<'cfg, 'db, 'api>>,
+        src: Arc<Location<'cfg, 'db, 'api>>,
+        dst: Arc<Location<'cfg, 'db, 'api>>,
+        capital_cost: f64,
     ) -> Self {
         Self {
             inner,
             src,
             dst,
-            pipes: RefCell::new(Vec::new()),
+            pipes: RwLock::new(Vec::new()),
+            capital_cost,
         }
     }
 
-    pub fn delivery_rate(&self) -> &config::DeliveryRate {
-        &self.inner.rate
+    pub fn delivery_rate(&self) -> config::DeliveryRate {
+        let transit_days = self.transit_time().as_secs_f64() / (24.0 * 3600.0);
+        config::DeliveryRate {
+            capital_rate: self.capital_cost * transit_days,
+            ..self.inner.rate.clone()
+        }
+    }
+
+    pub fn transit_time(&self) -> Duration {
+        self.inner.transit_time
     }
 
     pub fn pipes(&self) -> DeliveryRoutePipes<'_, 'cfg, 'db, 'api> {
         DeliveryRoutePipes {
-            inner: self.pipes.borrow(),
+            inner: self.pipes.read().unwrap(),
         }
     }
 
@@ -42,13 +53,13 @@ impl<'cfg, 'db, 'api> DeliveryRoute<'cfg, 'db, 'api> {
 }
 
 pub struct DeliveryRoutePipes<'dr, 'cfg, 'db, 'api> {
-    inner: Ref<'dr, Vec<Rc<DeliveryPipe<'cfg, 'db, 'api>>>>,
+    inner: RwLockReadGuard<'dr, Vec<Arc<DeliveryPipe<'cfg, 'db, 'api>>>>,
 }
 
 impl<'dr, 'cfg, 'db, 'api> DeliveryRoutePipes<'dr, 'cfg, 'db, 'api> {
     pub fn iter(
         &self,
-    ) -> impl Iterator<Item = &Rc<DeliveryPipe<'cfg, 'db, 'api>>> {
+    ) -> impl Iterator<Item = &Arc<DeliveryPipe<'cfg, 'db, 'api>>> {
         self.inner.iter()
     }
 }
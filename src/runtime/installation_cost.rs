@@ -0,0 +1,120 @@
+use std::ops::Mul;
+
+// alpha clones pay an extra surcharge on top of the SCC surcharge
+pub const ALPHA_CLONE_SURCHARGE: f64 = 0.0025;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct InstallationCost {
+    pub system_cost: f64, // job EIV * system cost index * structure bonuses
+    pub facility_tax: f64, // job EIV * facility tax
+    pub scc_surcharge: f64, // job EIV * SCC surcharge rate
+    pub alpha_surcharge: f64, // job EIV * alpha clone surcharge rate
+}
+
+impl InstallationCost {
+    pub fn new(
+        eiv: f64,
+        eiv_multiplier: f64,
+        cost_index: f64,
+        cost_efficiency: f64,
+        facility_tax: f64,
+        scc_surcharge: f64,
+        alpha_clone: bool,
+    ) -> Self {
+        // research, copying and invention use a portion of the product EIV
+        let job_eiv = eiv * eiv_multiplier;
+        Self {
+            system_cost: job_eiv * cost_index * cost_efficiency,
+            facility_tax: job_eiv * facility_tax,
+            scc_surcharge: job_eiv * scc_surcharge,
+            alpha_surcharge: match alpha_clone {
+                true => job_eiv * ALPHA_CLONE_SURCHARGE,
+                false => 0.0,
+            },
+        }
+    }
+
+    pub fn total(&self) -> f64 {
+        self.system_cost
+            + self.facility_tax
+            + self.scc_surcharge
+            + self.alpha_surcharge
+    }
+}
+
+impl Mul<f64> for InstallationCost {
+    type Output = Self;
+
+    fn mul(self, rhs: f64) -> Self {
+        Self {
+            system_cost: self.system_cost * rhs,
+            facility_tax: self.facility_tax * rhs,
+            scc_surcharge: self.scc_surcharge * rhs,
+            alpha_surcharge: self.alpha_surcharge * rhs,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-6, "{} != {}", a, b);
+    }
+
+    // manufacturing job with an EIV of 10,000,000 in a 5.04% system, built
+    // in a raitaru (3% cost bonus) with a 1% facility tax
+    fn raitaru_job(alpha_clone: bool) -> InstallationCost {
+        InstallationCost::new(
+            10_000_000.0,
+            1.0,
+            0.0504,
+            0.97,
+            0.01,
+            0.04,
+            alpha_clone,
+        )
+    }
+
+    #[test]
+    fn installation_cost_components() {
+        // as the industry window breaks it down
+        let cost = raitaru_job(false);
+        assert_close(cost.system_cost, 488_880.0);
+        assert_close(cost.facility_tax, 100_000.0);
+        assert_close(cost.scc_surcharge, 400_000.0);
+        assert_close(cost.alpha_surcharge, 0.0);
+        assert_close(cost.total(), 988_880.0);
+    }
+
+    #[test]
+    fn installation_cost_alpha_clone() {
+        let cost = raitaru_job(true);
+        assert_close(cost.alpha_surcharge, 25_000.0);
+        assert_close(cost.total(), 1_013_880.0);
+    }
+
+    #[test]
+    fn installation_cost_science_job() {
+        // invention and copying are charged on 2% of the product's EIV
+        let cost = InstallationCost::new(
+            10_000_000.0,
+            0.02,
+            0.0504,
+            1.0,
+            0.0,
+            0.04,
+            false,
+        );
+        assert_close(cost.system_cost, 10_080.0);
+        assert_close(cost.scc_surcharge, 8_000.0);
+        assert_close(cost.total(), 18_080.0);
+    }
+
+    #[test]
+    fn installation_cost_per_build() {
+        let cost = raitaru_job(false) * 3.0;
+        assert_close(cost.total(), 2_966_640.0);
+    }
+}
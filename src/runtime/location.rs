@@ -5,10 +5,15 @@ use crate::config::IndustrySlots;
 use crate::config::Item;
 use crate::config::ProductionLineExportKind;
 use crate::industry_db;
-use std::cell::Ref;
 use std::io;
 use std::io::Write;
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use std::sync::mpsc;
+use std::sync::RwLockReadGuard;
+use std::thread;
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex, RwLock},
+};
 
 fn deduplicate_locations<'iter, 'cfg, 'db, 'api>(
     locations: impl Iterator<Item = &'iter Location<'cfg, 'db, 'api>>,
@@ -28,11 +33,16 @@ fn deduplicate_locations<'iter, 'cfg, 'db, 'api>(
 }
 
 fn assets_target<'cfg, 'db, 'api>(
-    locations: &[Rc<Location<'cfg, 'db, 'api>>],
-) -> HashMap<u64, HashMap<Item, i64>> {
-    let mut assets_target = HashMap::<u64, HashMap<Item, i64>>::new();
+    locations: &[Arc<Location<'cfg, 'db, 'api>>],
+) -> HashMap<u64, BTreeMap<Item, i64>> {
+    let mut assets_target = HashMap::<u64, BTreeMap<Item, i64>>::new();
     for location in locations {
         for production_line in location.production_lines().iter_all() {
+            // research lines are only recommended, never built
+            if production_line.job_kind().is_research() {
+                continue;
+            }
+
             // add product to locations along export pipe
             // only do this for products, as intermediates will be added by the import
             if production_line.export_kind()
@@ -43,7 +53,7 @@ fn assets_target<'cfg, 'db, 'api>(
                 for location in production_line.export_pipe().locations() {
                     *assets_target
                         .entry(location.id())
-                        .or_insert_with(HashMap::new)
+                        .or_insert_with(BTreeMap::new)
                         .entry(item)
                         .or_insert(0) += num_buildable;
                 }
@@ -60,15 +70,13 @@ fn assets_target<'cfg, 'db, 'api>(
 
             for (item, quantity) in production_line.minerals_i64() {
                 // check if it's coming from market or from intermediate line
-                match production_line
-                    .import_src_intermediate_pipe(&item.type_id)
-                {
+                match production_line.import_src_intermediate_pipe(&item) {
                     // add item to every location along the pipe, including source
                     Some(pipe) => {
                         for location in pipe.locations() {
                             *assets_target
                                 .entry(location.id())
-                                .or_insert_with(HashMap::new)
+                                .or_insert_with(BTreeMap::new)
                                 .entry(item)
                                 .or_insert(0) += quantity * max_num_builds;
                         }
@@ -81,7 +89,7 @@ fn assets_target<'cfg, 'db, 'api>(
                         for location in market_locations.iter() {
                             *assets_target
                                 .entry(location.id())
-                                .or_insert_with(HashMap::new)
+                                .or_insert_with(BTreeMap::new)
                                 .entry(item)
                                 .or_insert(0) += quantity * max_num_builds;
                         }
@@ -94,105 +102,231 @@ fn assets_target<'cfg, 'db, 'api>(
 }
 
 pub fn build_in_locations<'cfg, 'db, 'api>(
-    locations: &[Rc<Location<'cfg, 'db, 'api>>],
+    locations: &[Arc<Location<'cfg, 'db, 'api>>],
     slots: &mut IndustrySlots,
     min_profit: f64,
     min_margin: f64,
+    objective: config::Objective,
     type_volumes: &HashMap<Item, f64>,
 ) {
     let mut stdout = io::stdout();
-    let mut i = 0;
-    print!("\n");
-    loop {
-        i += 1;
-        print!("  Iteration {}... ", i);
-        stdout.flush().unwrap();
-        let mut best = None;
-        for location in locations.iter() {
-            for production_line in
-                location.production_lines().iter_export_product()
-            {
-                if production_line.can_build(&slots) {
-                    if let Some(profit) =
-                        production_line.profit(None, None, type_volumes)
+    thread::scope(|scope| {
+        let pricing = PricingPool::new(scope, type_volumes);
+        let mut i = 0;
+        println!();
+        loop {
+            i += 1;
+            print!("  Iteration {}... ", i);
+            stdout.flush().unwrap();
+            let mut candidates = Vec::new();
+            for location in locations.iter() {
+                for production_line in
+                    location.production_lines().iter_export_product()
+                {
+                    if production_line.job_kind().is_research() {
+                        continue;
+                    }
+                    if production_line.can_build(&slots) {
+                        candidates.push(production_line.clone());
+                    }
+                }
+            }
+            let profits = pricing.profits(&candidates);
+            let mut best = None;
+            for (production_line, profit) in candidates.iter().zip(profits) {
+                if let Some(profit) = profit {
+                    let score = match profit
+                        .score(objective, production_line.slot_hours())
                     {
-                        if profit.profit() > min_profit
-                            && profit.profit()
-                                > best.as_ref().map(|(_, p)| *p).unwrap_or(0.0)
-                            && profit.margin() > min_margin
-                        {
-                            best = Some((
-                                production_line.clone(),
-                                profit.profit(),
-                            ));
-                        }
+                        Some(score) => score,
+                        None => continue,
+                    };
+                    if profit.profit() > min_profit
+                        && score
+                            > best
+                                .as_ref()
+                                .map(|(_, s, _)| *s)
+                                .unwrap_or(f64::MIN)
+                        && profit.margin() > min_margin
+                    {
+                        best = Some((production_line.clone(), score, profit));
                     }
                 }
             }
-        }
-        match best {
-            Some((production_line, _)) => {
-                production_line.build(slots, type_volumes);
-                print!(
-                    "Built '{}' at '{}'\n",
-                    production_line.product().type_id,
-                    production_line.location().name()
-                );
+            match best {
+                Some((production_line, score, profit)) => {
+                    production_line.build(slots, type_volumes);
+                    production_line.scores.write().unwrap().push(score);
+                    production_line.profits.write().unwrap().push(profit);
+                    println!(
+                        "Built '{}' at '{}'",
+                        production_line.product().type_id,
+                        production_line.location().name()
+                    );
+                }
+                None => {
+                    println!("Built nothing, breaking");
+                    break;
+                }
             }
-            None => {
-                print!("Built nothing, breaking\n");
-                break;
+        }
+    })
+}
+
+// prices build candidates on worker threads kept for the whole build loop
+// each candidate is priced on its own reservation overlay, and cached
+// profits are only read and written between rounds, so nothing shared is
+// written while the workers run
+struct PricingPool<'cfg, 'db, 'api> {
+    jobs: mpsc::Sender<(usize, Arc<ProductionLine<'cfg, 'db, 'api>>)>,
+    results: mpsc::Receiver<(usize, Option<Profit>)>,
+}
+
+impl<'cfg, 'db, 'api> PricingPool<'cfg, 'db, 'api> {
+    fn new<'scope, 'env>(
+        scope: &'scope thread::Scope<'scope, 'env>,
+        type_volumes: &'env HashMap<Item, f64>,
+    ) -> Self
+    where
+        'cfg: 'env,
+        'db: 'env,
+        'api: 'env,
+    {
+        let threads = thread::available_parallelism()
+            .map(|threads| threads.get())
+            .unwrap_or(1);
+        let (jobs, job_receiver) =
+            mpsc::channel::<(usize, Arc<ProductionLine<'cfg, 'db, 'api>>)>();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+        let (result_sender, results) = mpsc::channel();
+        for _ in 0..threads {
+            let job_receiver = job_receiver.clone();
+            let result_sender = result_sender.clone();
+            // workers stop once the pool, and with it the sender, is dropped
+            scope.spawn(move || loop {
+                let job = job_receiver.lock().unwrap().recv();
+                let Ok((i, production_line)) = job else {
+                    break;
+                };
+                let profit = production_line.profit(None, None, type_volumes);
+                if result_sender.send((i, profit)).is_err() {
+                    break;
+                }
+            });
+        }
+        Self { jobs, results }
+    }
+
+    // profits of the candidates' next builds, only repriced once a
+    // committed reservation was made on an order book that they buy from
+    fn profits(
+        &self,
+        candidates: &[Arc<ProductionLine<'cfg, 'db, 'api>>],
+    ) -> Vec<Option<Profit>> {
+        let mut profits = vec![None; candidates.len()];
+        let mut pending = Vec::new();
+        for (i, production_line) in candidates.iter().enumerate() {
+            let version = production_line.market_version();
+            match production_line.cached_profit(version) {
+                Some(profit) => profits[i] = profit,
+                None => {
+                    self.jobs.send((i, production_line.clone())).unwrap();
+                    pending.push((i, version));
+                }
             }
         }
+        for _ in 0..pending.len() {
+            let (i, profit) = self.results.recv().unwrap();
+            profits[i] = profit;
+        }
+        for (i, version) in pending {
+            candidates[i].cache_profit(version, profits[i].clone());
+        }
+        profits
+    }
+}
+
+// upstream lines first, so each line is scored against the variants
+// feeding it, lines making another line's blueprint follow that line
+fn select_variants<'cfg, 'db, 'api>(
+    production_lines: &[Arc<ProductionLine<'cfg, 'db, 'api>>],
+    objective: config::Objective,
+    type_volumes: &HashMap<Item, f64>,
+) {
+    // a line has more lines upstream than any line upstream of it
+    let mut order = production_lines.to_vec();
+    order.sort_by_key(|pl| pl.upstream_ids().len());
+    for production_line in order.iter() {
+        if production_lines
+            .iter()
+            .any(|consumer| production_line.is_blueprint_source(consumer))
+        {
+            continue;
+        }
+        let consumers = production_lines
+            .iter()
+            .filter(|consumer| {
+                consumer.export_kind()
+                    == config::ProductionLineExportKind::Product
+                    && consumer.upstream_ids().contains(&production_line.id())
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        production_line.select_variant(&consumers, objective, type_volumes);
     }
 }
 
 pub fn new_locations<'cfg, 'db, 'api>(
     cfg_locations: &'cfg [config::Location],
-    db_lines: &'db HashMap<u32, industry_db::Line>,
-    adjusted_prices: &'api HashMap<u32, f64>,
-    cost_indices: &'api HashMap<u32, config::ManufacturingValue>,
-    market_orders: &'api HashMap<u64, HashMap<u32, api_data::TypeMarketOrders>>,
-    assets: &'api HashMap<u64, HashMap<Item, i64>>,
-    max_time: Duration,
-    daily_flex_time: Duration,
-) -> Vec<Rc<Location<'cfg, 'db, 'api>>> {
+    cfg_characters: &'cfg HashMap<String, config::Character>,
+    db_lines: &'db HashMap<u32, Vec<industry_db::Line>>,
+    db_reprocessing: &'db HashMap<
+        u64,
+        HashMap<Item, industry_db::Reprocessing>,
+    >,
+    type_volumes: &HashMap<Item, f64>,
+    api: &'api api_data::Api,
+    settings: config::Settings,
+) -> Vec<Arc<Location<'cfg, 'db, 'api>>> {
     let locations = cfg_locations
         .iter()
         .map(|l| {
             (
                 l.id,
-                Rc::new(Location::new(
+                Arc::new(Location::new(
                     l,
-                    market_orders.get(&l.id),
-                    assets.get(&l.id),
+                    api.market_orders.get(&l.id),
+                    api.assets.get(&l.id),
+                    db_reprocessing.get(&l.id),
+                    &api.adjusted_prices,
                 )),
             )
         })
         .collect::<HashMap<_, _>>();
 
-    let mut routes = HashMap::<u32, Rc<DeliveryRoute<'cfg, 'db, 'api>>>::new();
+    let mut routes = HashMap::<u32, Arc<DeliveryRoute<'cfg, 'db, 'api>>>::new();
 
     for cfg_location in cfg_locations.iter() {
         let location = &locations[&cfg_location.id];
         for (dst_location_id, cfg_route) in cfg_location.routes.iter() {
             let dst_location = locations[dst_location_id].clone();
-            let route = Rc::new(DeliveryRoute::new(
+            let route = Arc::new(DeliveryRoute::new(
                 cfg_route,
                 location.clone(),
                 dst_location,
+                settings.capital_cost,
             ));
             routes.insert(cfg_route.id, route.clone());
-            location.routes.borrow_mut().push(route);
+            location.routes.write().unwrap().push(route);
         }
     }
 
-    let mut pipes = HashMap::<u32, Rc<DeliveryPipe<'cfg, 'db, 'api>>>::new();
+    let mut pipes = HashMap::<u32, Arc<DeliveryPipe<'cfg, 'db, 'api>>>::new();
 
     for cfg_location in cfg_locations.iter() {
         let location = &locations[&cfg_location.id];
         for (cfg_pipe_id, cfg_pipe) in cfg_location.pipes.iter() {
-            let pipe = Rc::new(DeliveryPipe::new(
+            let pipe = Arc::new(DeliveryPipe::new(
                 cfg_pipe
                     .iter()
                     .map(|cfg_route_id| routes[cfg_route_id].clone())
@@ -200,9 +334,9 @@ pub fn new_locations<'cfg, 'db, 'api>(
             ));
             pipes.insert(*cfg_pipe_id, pipe.clone());
             if pipe.src().id() == location.id() {
-                location.export_pipes.borrow_mut().push(pipe.clone());
+                location.export_pipes.write().unwrap().push(pipe.clone());
             } else if pipe.dst().id() == location.id() {
-                location.import_pipes.borrow_mut().push(pipe.clone());
+                location.import_pipes.write().unwrap().push(pipe.clone());
             } else {
                 panic!("pipe not connected to location");
             }
@@ -210,16 +344,26 @@ pub fn new_locations<'cfg, 'db, 'api>(
         for (cfg_pipe_id, _) in cfg_location.pipes.iter() {
             let pipe = &pipes[cfg_pipe_id];
             for route in pipe.routes.iter() {
-                route.pipes.borrow_mut().push(pipe.clone());
+                route.pipes.write().unwrap().push(pipe.clone());
             }
         }
     }
 
+    // characters with limited slots share them between their lines
+    let character_slots = cfg_characters
+        .iter()
+        .filter_map(|(name, character)| {
+            character
+                .slots
+                .clone()
+                .map(|slots| (name.as_str(), Arc::new(RwLock::new(slots))))
+        })
+        .collect::<HashMap<_, _>>();
+
     let mut production_lines =
-        HashMap::<u32, Rc<ProductionLine<'cfg, 'db, 'api>>>::new();
+        HashMap::<u32, Arc<ProductionLine<'cfg, 'db, 'api>>>::new();
 
     for cfg_location in cfg_locations.iter() {
-        let location = &locations[&cfg_location.id];
         let cfg_location_production = match &cfg_location.production {
             Some(p) => p,
             None => continue,
@@ -234,27 +378,29 @@ pub fn new_locations<'cfg, 'db, 'api>(
                 .iter()
                 .map(|id| pipes[id].clone())
                 .collect();
-            let production_line = Rc::new(ProductionLine::new(
+            let production_line = Arc::new(ProductionLine::new(
                 cfg_production_line,
                 export_pipe,
                 import_src_market_pipes,
                 &db_lines[&cfg_production_line.id],
-                adjusted_prices,
-                cost_indices,
-                max_time,
-                daily_flex_time,
+                api,
+                config::Settings {
+                    alpha_clone: cfg_production_line
+                        .character
+                        .as_ref()
+                        .and_then(|name| cfg_characters.get(name))
+                        .and_then(|character| character.alpha_clone)
+                        .unwrap_or(settings.alpha_clone),
+                    ..settings
+                },
+                cfg_production_line
+                    .character
+                    .as_ref()
+                    .and_then(|name| character_slots.get(name.as_str()))
+                    .cloned(),
             ));
             production_lines
                 .insert(cfg_production_line.id, production_line.clone());
-            location
-                .production
-                .as_ref()
-                .unwrap()
-                .production_lines
-                .borrow_mut()
-                .entry(production_line.product())
-                .or_insert_with(Vec::new)
-                .push(production_line);
         }
     }
 
@@ -275,20 +421,71 @@ pub fn new_locations<'cfg, 'db, 'api>(
                     production_lines[cfg_import_production_line_id].clone();
                 production_line
                     .import_src_intermediate_production_lines
-                    .borrow_mut()
+                    .write()
+                    .unwrap()
                     .insert(type_id, import_production_line);
             }
+            for cfg_import_production_line_id in cfg_production_line
+                .import_src_blueprint_production_line_ids
+                .iter()
+            {
+                let import_production_line =
+                    production_lines[cfg_import_production_line_id].clone();
+                if !matches!(
+                    import_production_line.job_kind(),
+                    config::ManufacturingKind::Copy
+                        | config::ManufacturingKind::Invention
+                ) {
+                    panic!("blueprint import not from copy or invention line");
+                }
+                production_line
+                    .import_src_blueprint_production_lines
+                    .write()
+                    .unwrap()
+                    .push(import_production_line);
+            }
+        }
+    }
+
+    // the product isn't known until a variant is selected
+    let mut sorted_production_lines =
+        production_lines.values().cloned().collect::<Vec<_>>();
+    sorted_production_lines.sort_by_key(|pl| pl.id());
+    select_variants(&sorted_production_lines, settings.objective, type_volumes);
+
+    for cfg_location in cfg_locations.iter() {
+        let location = &locations[&cfg_location.id];
+        let cfg_location_production = match &cfg_location.production {
+            Some(p) => p,
+            None => continue,
+        };
+        for cfg_production_line in
+            cfg_location_production.production_lines.iter()
+        {
+            let production_line =
+                production_lines[&cfg_production_line.id].clone();
+            location
+                .production
+                .as_ref()
+                .unwrap()
+                .production_lines
+                .write()
+                .unwrap()
+                .entry(production_line.product())
+                .or_insert_with(Vec::new)
+                .push(production_line);
         }
     }
 
-    let locations = locations.into_values().collect::<Vec<_>>();
+    let mut locations = locations.into_values().collect::<Vec<_>>();
+    locations.sort_by_key(|location| location.id());
 
     let mut assets_target = assets_target(&locations);
 
     for location in locations.iter() {
-        *location.assets_target.borrow_mut() = assets_target
+        *location.assets_target.write().unwrap() = assets_target
             .remove(&location.id())
-            .unwrap_or(HashMap::new());
+            .unwrap_or(BTreeMap::new());
     }
 
     locations
@@ -296,14 +493,15 @@ pub fn new_locations<'cfg, 'db, 'api>(
 
 pub struct Location<'cfg, 'db, 'api> {
     pub inner: &'cfg config::Location,
-    pub routes: RefCell<Vec<Rc<DeliveryRoute<'cfg, 'db, 'api>>>>,
-    pub import_pipes: RefCell<Vec<Rc<DeliveryPipe<'cfg, 'db, 'api>>>>,
-    pub export_pipes: RefCell<Vec<Rc<DeliveryPipe<'cfg, 'db, 'api>>>>,
+    pub routes: RwLock<Vec<Arc<DeliveryRoute<'cfg, 'db, 'api>>>>,
+    pub import_pipes: RwLock<Vec<Arc<DeliveryPipe<'cfg, 'db, 'api>>>>,
+    pub export_pipes: RwLock<Vec<Arc<DeliveryPipe<'cfg, 'db, 'api>>>>,
     pub production: Option<LocationProduction<'cfg, 'db, 'api>>,
     pub market: Option<LocationMarket<'cfg, 'api>>,
     pub assets: Option<&'api HashMap<Item, i64>>,
-    pub assets_target: RefCell<HashMap<Item, i64>>,
-    pub assets_consumed: RefCell<HashMap<Item, i64>>,
+    pub assets_target: RwLock<BTreeMap<Item, i64>>,
+    pub assets_consumed: RwLock<BTreeMap<Item, i64>>,
+    pub reprocessing: BTreeMap<Item, ReprocessingYield<'db>>,
 }
 
 impl<'cfg, 'db, 'api> Location<'cfg, 'db, 'api> {
@@ -311,12 +509,14 @@ impl<'cfg, 'db, 'api> Location<'cfg, 'db, 'api> {
         inner: &'cfg config::Location,
         orders: Option<&'api HashMap<u32, api_data::TypeMarketOrders>>,
         assets: Option<&'api HashMap<Item, i64>>,
+        reprocessing: Option<&'db HashMap<Item, industry_db::Reprocessing>>,
+        adjusted_prices: &HashMap<u32, f64>,
     ) -> Self {
         Self {
             inner,
-            routes: RefCell::new(Vec::new()),
-            import_pipes: RefCell::new(Vec::new()),
-            export_pipes: RefCell::new(Vec::new()),
+            routes: RwLock::new(Vec::new()),
+            import_pipes: RwLock::new(Vec::new()),
+            export_pipes: RwLock::new(Vec::new()),
             production: inner
                 .production
                 .as_ref()
@@ -324,13 +524,32 @@ impl<'cfg, 'db, 'api> Location<'cfg, 'db, 'api> {
             market: inner
                 .market
                 .as_ref()
-                .map(|m| LocationMarket::new(m, orders)),
+                .map(|m| LocationMarket::new(m, inner.id, orders)),
             assets,
-            assets_target: RefCell::new(HashMap::new()),
-            assets_consumed: RefCell::new(HashMap::new()),
+            assets_target: RwLock::new(BTreeMap::new()),
+            assets_consumed: RwLock::new(BTreeMap::new()),
+            reprocessing: reprocessing
+                .into_iter()
+                .flat_map(|r| r.iter())
+                .map(|(item, r)| {
+                    (*item, ReprocessingYield::new(r, adjusted_prices))
+                })
+                .collect(),
         }
     }
 
+    // ores that yield the material, and how much of it per unit of ore
+    pub fn reprocessing_sources<'this>(
+        &'this self,
+        material: &'this Item,
+    ) -> impl Iterator<Item = (&'this ReprocessingYield<'db>, f64)> + 'this
+    {
+        self.reprocessing
+            .values()
+            .filter(|r| r.is_ore())
+            .filter_map(move |r| r.material_per_unit(material).map(|q| (r, q)))
+    }
+
     pub fn name(&self) -> &'cfg str {
         self.inner.name.as_str()
     }
@@ -352,16 +571,27 @@ impl<'cfg, 'db, 'api> Location<'cfg, 'db, 'api> {
     }
 
     pub fn num_target(&self, item: Item) -> i64 {
-        self.assets_target.borrow().get(&item).copied().unwrap_or(0)
+        self.assets_target
+            .read()
+            .unwrap()
+            .get(&item)
+            .copied()
+            .unwrap_or(0)
     }
 
     pub fn consume_for_build(&self, item: Item, quantity: i64) {
-        *self.assets_consumed.borrow_mut().entry(item).or_insert(0) += quantity;
+        *self
+            .assets_consumed
+            .write()
+            .unwrap()
+            .entry(item)
+            .or_insert(0) += quantity;
     }
 
     pub fn num_consumed_for_build(&self, item: Item) -> i64 {
         self.assets_consumed
-            .borrow()
+            .read()
+            .unwrap()
             .get(&item)
             .copied()
             .unwrap_or(0)
@@ -376,7 +606,20 @@ impl<'cfg, 'db, 'api> Location<'cfg, 'db, 'api> {
         }
     }
 
-    pub fn num_available(&self, context: Option<u64>, item: Item) -> i64 {
+    // number of jobs using the BPO at this location
+    pub fn num_bpo_in_use(&self, blueprint: Item) -> i64 {
+        self.production_lines()
+            .iter_all()
+            .filter(|pl| pl.uses_bpo() && pl.blueprint() == blueprint)
+            .map(|pl| pl.num_builds())
+            .sum()
+    }
+
+    pub fn num_available(
+        &self,
+        overlay: Option<&ReservationOverlay>,
+        item: Item,
+    ) -> i64 {
         // add number of item present in assets
         let mut available = self
             .assets
@@ -389,7 +632,7 @@ impl<'cfg, 'db, 'api> Location<'cfg, 'db, 'api> {
             available += self
                 .market
                 .as_ref()
-                .map(|m| m.orders.num_purchased(context, &item.type_id))
+                .map(|m| m.orders.num_purchased(overlay, &item.type_id))
                 .unwrap_or(0);
         }
 
@@ -398,7 +641,7 @@ impl<'cfg, 'db, 'api> Location<'cfg, 'db, 'api> {
             .production
             .as_ref()
             .map(|p| {
-                p.production_lines.borrow().get(&item).map(|pls| {
+                p.production_lines.read().unwrap().get(&item).map(|pls| {
                     pls.iter().map(|pl| pl.num_building()).sum::<i64>()
                 })
             })
@@ -408,20 +651,32 @@ impl<'cfg, 'db, 'api> Location<'cfg, 'db, 'api> {
         // add number of item imported via delivery pipes
         available += self
             .import_pipes
-            .borrow()
+            .read()
+            .unwrap()
             .iter()
             .map(|pipe| {
-                pipe.deliveries.borrow().get(&item).copied().unwrap_or(0)
+                pipe.deliveries
+                    .read()
+                    .unwrap()
+                    .get(&item)
+                    .copied()
+                    .unwrap_or(0)
             })
             .sum::<i64>();
 
         // subtract number of item exported via delivery pipes
         available -= self
             .export_pipes
-            .borrow()
+            .read()
+            .unwrap()
             .iter()
             .map(|pipe| {
-                pipe.deliveries.borrow().get(&item).copied().unwrap_or(0)
+                pipe.deliveries
+                    .read()
+                    .unwrap()
+                    .get(&item)
+                    .copied()
+                    .unwrap_or(0)
             })
             .sum::<i64>();
 
@@ -433,13 +688,13 @@ impl<'cfg, 'db, 'api> Location<'cfg, 'db, 'api> {
 
     pub fn routes(&self) -> LocationRoutes<'_, 'cfg, 'db, 'api> {
         LocationRoutes {
-            inner: self.routes.borrow(),
+            inner: self.routes.read().unwrap(),
         }
     }
 
     pub fn assets_target(&self) -> LocationAssetTargets<'_> {
         LocationAssetTargets {
-            inner: self.assets_target.borrow(),
+            inner: self.assets_target.read().unwrap(),
         }
     }
 
@@ -452,13 +707,13 @@ impl<'cfg, 'db, 'api> Location<'cfg, 'db, 'api> {
 }
 
 pub struct LocationRoutes<'l, 'cfg, 'db, 'api> {
-    inner: Ref<'l, Vec<Rc<DeliveryRoute<'cfg, 'db, 'api>>>>,
+    inner: RwLockReadGuard<'l, Vec<Arc<DeliveryRoute<'cfg, 'db, 'api>>>>,
 }
 
 impl<'l, 'cfg, 'db, 'api> LocationRoutes<'l, 'cfg, 'db, 'api> {
     pub fn iter(
         &self,
-    ) -> impl Iterator<Item = &Rc<DeliveryRoute<'cfg, 'db, 'api>>> {
+    ) -> impl Iterator<Item = &Arc<DeliveryRoute<'cfg, 'db, 'api>>> {
         self.inner.iter()
     }
 
@@ -466,7 +721,7 @@ impl<'l, 'cfg, 'db, 'api> LocationRoutes<'l, 'cfg, 'db, 'api> {
     pub fn iter_transit<'ll>(
         &self,
         location_id: u64,
-    ) -> impl Iterator<Item = &Rc<DeliveryRoute<'cfg, 'db, 'api>>> + '_ {
+    ) -> impl Iterator<Item = &Arc<DeliveryRoute<'cfg, 'db, 'api>>> + '_ {
         self.inner
             .iter()
             .filter(move |route| route.dst.id() != location_id)
@@ -474,7 +729,7 @@ impl<'l, 'cfg, 'db, 'api> LocationRoutes<'l, 'cfg, 'db, 'api> {
 }
 
 pub struct LocationAssetTargets<'l> {
-    inner: Ref<'l, HashMap<Item, i64>>,
+    inner: RwLockReadGuard<'l, BTreeMap<Item, i64>>,
 }
 
 impl<'l> LocationAssetTargets<'l> {
@@ -486,14 +741,19 @@ impl<'l> LocationAssetTargets<'l> {
 pub struct LocationProduction<'cfg, 'db, 'api> {
     pub inner: &'cfg config::LocationProduction,
     pub production_lines:
-        RefCell<HashMap<Item, Vec<Rc<ProductionLine<'cfg, 'db, 'api>>>>>,
+        RwLock<HashMap<Item, Vec<Arc<ProductionLine<'cfg, 'db, 'api>>>>>,
+    pub slots: Option<Arc<RwLock<IndustrySlots>>>, // capped in this structure
 }
 
 impl<'cfg, 'db, 'api> LocationProduction<'cfg, 'db, 'api> {
     pub fn new(inner: &'cfg config::LocationProduction) -> Self {
         Self {
             inner,
-            production_lines: RefCell::new(HashMap::new()),
+            production_lines: RwLock::new(HashMap::new()),
+            slots: inner
+                .slots
+                .clone()
+                .map(|slots| Arc::new(RwLock::new(slots))),
         }
     }
 
@@ -501,39 +761,44 @@ impl<'cfg, 'db, 'api> LocationProduction<'cfg, 'db, 'api> {
         &self,
     ) -> LocationProductionLines<'_, 'cfg, 'db, 'api> {
         LocationProductionLines {
-            inner: Some(self.production_lines.borrow()),
+            inner: Some(self.production_lines.read().unwrap()),
         }
     }
 }
 
 pub struct LocationProductionLines<'lp, 'cfg, 'db, 'api> {
     inner: Option<
-        Ref<'lp, HashMap<Item, Vec<Rc<ProductionLine<'cfg, 'db, 'api>>>>>,
+        RwLockReadGuard<
+            'lp,
+            HashMap<Item, Vec<Arc<ProductionLine<'cfg, 'db, 'api>>>>,
+        >,
     >,
 }
 
 impl<'lp, 'cfg, 'db, 'api> LocationProductionLines<'lp, 'cfg, 'db, 'api> {
+    // ordered by config id, so ties are broken the same way every run
     pub fn iter_all(
         &self,
-    ) -> impl Iterator<Item = &Rc<ProductionLine<'cfg, 'db, 'api>>> {
-        self.inner
+    ) -> impl Iterator<Item = &Arc<ProductionLine<'cfg, 'db, 'api>>> {
+        let mut production_lines = self
+            .inner
             .as_ref()
-            .map(|pls_map| pls_map.values().map(|pls| pls.iter()))
-            .into_iter()
-            .flatten()
-            .flatten()
+            .map(|pls_map| pls_map.values().flatten().collect::<Vec<_>>())
+            .unwrap_or_default();
+        production_lines.sort_by_key(|pl| pl.id());
+        production_lines.into_iter()
     }
 
     pub fn iter_export_product(
         &self,
-    ) -> impl Iterator<Item = &Rc<ProductionLine<'cfg, 'db, 'api>>> {
+    ) -> impl Iterator<Item = &Arc<ProductionLine<'cfg, 'db, 'api>>> {
         self.iter_all()
             .filter(|pl| pl.export_kind() == ProductionLineExportKind::Product)
     }
 
     pub fn iter_export_intermediate(
         &self,
-    ) -> impl Iterator<Item = &Rc<ProductionLine<'cfg, 'db, 'api>>> {
+    ) -> impl Iterator<Item = &Arc<ProductionLine<'cfg, 'db, 'api>>> {
         self.iter_all().filter(|pl| {
             pl.export_kind() == ProductionLineExportKind::Intermediate
         })
@@ -548,11 +813,12 @@ pub struct LocationMarket<'cfg, 'api> {
 impl<'cfg, 'api> LocationMarket<'cfg, 'api> {
     pub fn new(
         inner: &'cfg config::LocationMarket,
+        location_id: u64,
         orders: Option<&'api HashMap<u32, api_data::TypeMarketOrders>>,
     ) -> Self {
         Self {
             inner,
-            orders: LocationMarketOrders::new(orders),
+            orders: LocationMarketOrders::new(location_id, orders),
         }
     }
 
@@ -564,3 +830,173 @@ impl<'cfg, 'api> LocationMarket<'cfg, 'api> {
         self.inner.brokers_fee
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::tests::{line, Snapshot, TRITANIUM};
+    use industry_db::{InventionOutcome, Line};
+
+    const DATACORE: u32 = 20410;
+    const DECRYPTOR: u32 = 34201;
+    const T2_PRODUCT: u32 = 202;
+
+    // an invention line picking its decryptor, feeding a T2 product line
+    const T2_CONFIG: &str = "
+locations:
+  - id: 1
+    name: hub
+    system_id: 10
+    market: {sales_tax: 0.036, brokers_fee: 0.015}
+    routes:
+      2: {id: 1, service_name: courier, m3_rate: 100.0, collateral_rate: 0.01}
+  - id: 2
+    name: factory
+    system_id: 20
+    production:
+      tax: {manufacturing: 0.01, invention: 0.01, reaction: 0.01, copy: 0.01}
+      rigs: [null, null, null]
+      structure_type_id: 35825
+      production_lines:
+        - {id: 1, blueprint: {type_id: 1201, runs: 10},
+           product: {type_id: 1202, runs: 10, me: 2, te: 4},
+           kind: Invention, export_kind: Intermediate, export_pipe_id: 3,
+           import_src_market_pipe_ids: [1], auto_decryptor: true,
+           parallel: 1}
+        - {id: 2, blueprint: {type_id: 1202, runs: 10, me: 2, te: 4},
+           product: {type_id: 202}, kind: Manufacturing,
+           export_kind: Product, export_pipe_id: 2,
+           import_src_market_pipe_ids: [1],
+           import_src_blueprint_production_line_ids: [1], parallel: 1}
+    routes:
+      1: {id: 2, service_name: courier, m3_rate: 100.0, collateral_rate: 0.01}
+      2: {id: 3, service_name: hangar, m3_rate: 0.0, collateral_rate: 0.0}
+    pipes:
+      1: [1]
+      2: [2]
+      3: [3]
+skills: {}
+slots: {manufacturing: 1, reaction: 0, science: 1}
+max_time: {secs: 604800, nanos: 0}
+daily_flex_time: {secs: 3600, nanos: 0}
+min_profit: 0.0
+min_margin: 1.0
+";
+
+    fn t2_blueprint(me: i8) -> Item {
+        Item::new_blueprint(1202, 10, me, 4)
+    }
+
+    // 10 attempts at 40%, the decryptor adds 2 ME and costs 5,000 an attempt
+    fn invention_line(decryptor: bool) -> Line {
+        let mut minerals = vec![(Item::new(DATACORE), 20)];
+        if decryptor {
+            minerals.push((Item::new(DECRYPTOR), 10));
+        }
+        Line {
+            blueprint: Item::new_blueprint(1201, 10, 0, 0),
+            product: t2_blueprint(if decryptor { 4 } else { 2 }),
+            decryptor: decryptor.then(|| Item::new(DECRYPTOR)),
+            installation_minerals: vec![(Item::new(TRITANIUM), 100)],
+            minerals,
+            portion: 4,
+            expected_portion: 4.0,
+            invention: Some(InventionOutcome::new(10, 0.4)),
+            ..line(0)
+        }
+    }
+
+    fn manufacturing_line(me: i8, tritanium: i64) -> Line {
+        Line {
+            blueprint: t2_blueprint(me),
+            minerals: vec![
+                (Item::new(TRITANIUM), tritanium),
+                (t2_blueprint(me), 1),
+            ],
+            ..line(T2_PRODUCT)
+        }
+    }
+
+    // with the ME 4 blueprint the T2 line needs `me4_tritanium`
+    fn t2_snapshot(me4_tritanium: i64) -> Snapshot {
+        let db_lines = HashMap::from([
+            (1, vec![invention_line(false), invention_line(true)]),
+            (
+                2,
+                vec![
+                    manufacturing_line(2, 100_000),
+                    manufacturing_line(4, me4_tritanium),
+                ],
+            ),
+        ]);
+        Snapshot::new(T2_CONFIG, db_lines)
+            .orders(1, TRITANIUM, 5.0, 10_000_000.0)
+            .orders(1, DATACORE, 1_000.0, 100_000.0)
+            .orders(1, DECRYPTOR, 5_000.0, 100_000.0)
+            .orders(1, T2_PRODUCT, 100_000.0, 1_000.0)
+    }
+
+    #[test]
+    fn decryptor_picked_by_consumer_profit() {
+        // the decryptor costs more per invented run, but saves 200,000 ISK
+        // of tritanium on every T2 job
+        let snapshot = t2_snapshot(60_000);
+        let runtime = snapshot.runtime();
+        assert_eq!(runtime.production_line(1).product(), t2_blueprint(4));
+        let decryptor = runtime.production_line(1).decryptor();
+        assert_eq!(decryptor, Some(Item::new(DECRYPTOR)));
+        assert_eq!(runtime.production_line(2).blueprint(), t2_blueprint(4));
+    }
+
+    #[test]
+    fn decryptor_skipped_when_savings_are_small() {
+        // 5,000 ISK saved per T2 job, less than the decryptors cost
+        let snapshot = t2_snapshot(99_000);
+        let runtime = snapshot.runtime();
+        assert_eq!(runtime.production_line(1).product(), t2_blueprint(2));
+        assert_eq!(runtime.production_line(1).decryptor(), None);
+        assert_eq!(runtime.production_line(2).blueprint(), t2_blueprint(2));
+    }
+
+    // invention without a decryptor, expecting `expected_portion` BPCs
+    fn expected_bpcs_snapshot(expected_portion: f64) -> Snapshot {
+        let db_lines = HashMap::from([
+            (
+                1,
+                vec![Line {
+                    expected_portion,
+                    ..invention_line(false)
+                }],
+            ),
+            (2, vec![manufacturing_line(2, 100_000)]),
+        ]);
+        Snapshot::new(T2_CONFIG, db_lines)
+            .orders(1, TRITANIUM, 5.0, 10_000_000.0)
+            .orders(1, DATACORE, 1_000.0, 100_000.0)
+            .orders(1, T2_PRODUCT, 100_000.0, 1_000.0)
+    }
+
+    #[test]
+    fn builds_for_expected_portion() {
+        let snapshot = expected_bpcs_snapshot(4.0);
+        let runtime = snapshot.runtime();
+        let invention = runtime.production_line(1);
+        assert_eq!(invention.builds_for(1), Some(1));
+        assert_eq!(invention.builds_for(4), Some(1));
+        assert_eq!(invention.builds_for(5), Some(2));
+        // 0.4 BPCs a job takes 3 jobs for one BPC
+        let snapshot = expected_bpcs_snapshot(0.4);
+        let runtime = snapshot.runtime();
+        assert_eq!(runtime.production_line(1).builds_for(1), Some(3));
+    }
+
+    #[test]
+    fn bpc_input_expected_to_deliver_nothing() {
+        let snapshot = expected_bpcs_snapshot(0.0);
+        let mut runtime = snapshot.runtime();
+        assert_eq!(runtime.production_line(1).builds_for(1), None);
+        runtime.build();
+        assert_eq!(runtime.production_line(2).num_builds(), 0);
+        assert_eq!(runtime.production_line(1).num_builds(), 0);
+    }
+}
@@ -2,68 +2,85 @@ use super::*;
 use crate::api_data;
 use crate::config;
 use crate::industry_db;
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    sync::RwLock,
+};
+
+// reservations of a single profit check, on top of the committed ones
+// every check has its own, so checks can run side by side on threads
+#[derive(Default)]
+pub struct ReservationOverlay {
+    reserved: RefCell<HashMap<(u64, u32), f64>>, // by location and type id
+}
+
+impl ReservationOverlay {
+    fn reserved(&self, orders: &TypeMarketOrders) -> f64 {
+        self.reserved
+            .borrow()
+            .get(&orders.key())
+            .copied()
+            .unwrap_or(0.0)
+    }
+
+    fn reserve(&self, orders: &TypeMarketOrders, volume: f64) {
+        *self
+            .reserved
+            .borrow_mut()
+            .entry(orders.key())
+            .or_insert(0.0) += volume;
+    }
+}
 
 pub struct TypeMarketOrders<'api> {
+    pub location_id: u64,
+    pub type_id: u32,
     pub inner: &'api api_data::TypeMarketOrders,
-    pub reserved: RefCell<f64>, // committed reservations
-    reserved_current: RefCell<f64>, // current profit check reservations
-    reserved_current_context: RefCell<u64>, // current profit check context
+    pub reserved: RwLock<f64>, // committed reservations
+    version: RwLock<u64>,      // bumped by every committed reservation
 }
 
 impl<'api> TypeMarketOrders<'api> {
-    pub fn new(inner: &'api api_data::TypeMarketOrders) -> Self {
+    pub fn new(
+        location_id: u64,
+        type_id: u32,
+        inner: &'api api_data::TypeMarketOrders,
+    ) -> Self {
         Self {
+            location_id,
+            type_id,
             inner,
-            reserved: RefCell::new(0.0),
-            reserved_current: RefCell::new(0.0),
-            reserved_current_context: RefCell::new(0),
+            reserved: RwLock::new(0.0),
+            version: RwLock::new(0),
         }
     }
 
-    fn reserved_current(&self, context: Option<u64>) -> f64 {
-        match context {
-            Some(context)
-                if *self.reserved_current_context.borrow() == context =>
-            {
-                *self.reserved_current.borrow()
-            }
-            Some(context) => {
-                *self.reserved_current_context.borrow_mut() = context;
-                *self.reserved_current.borrow_mut() = 0.0;
-                0.0
-            }
-            None => 0.0,
-        }
+    fn key(&self) -> (u64, u32) {
+        (self.location_id, self.type_id)
     }
 
-    fn reserved(&self, context: Option<u64>) -> f64 {
-        *self.reserved.borrow() + self.reserved_current(context)
+    fn reserved(&self, overlay: Option<&ReservationOverlay>) -> f64 {
+        *self.reserved.read().unwrap()
+            + overlay.map(|o| o.reserved(self)).unwrap_or(0.0)
     }
 
-    pub fn reserve(&self, context: Option<u64>, volume: f64) {
-        match context {
-            Some(context)
-                if *self.reserved_current_context.borrow() == context =>
-            {
-                *self.reserved_current.borrow_mut() += volume;
-            }
-            Some(context) => {
-                *self.reserved_current_context.borrow_mut() = context;
-                *self.reserved_current.borrow_mut() = volume;
-            }
+    pub fn reserve(&self, overlay: Option<&ReservationOverlay>, volume: f64) {
+        match overlay {
+            Some(overlay) => overlay.reserve(self, volume),
             None => {
-                *self.reserved.borrow_mut() += volume;
+                *self.reserved.write().unwrap() += volume;
+                *self.version.write().unwrap() += 1;
             }
         }
     }
 
     pub fn next_available(
         &self,
-        context: Option<u64>,
+        overlay: Option<&ReservationOverlay>,
     ) -> Option<api_data::MarketOrder> {
         let mut current = 0.0;
-        let reserved = self.reserved(context);
+        let reserved = self.reserved(overlay);
         if reserved >= self.inner.total {
             return None;
         }
@@ -79,16 +96,47 @@ impl<'api> TypeMarketOrders<'api> {
         None
     }
 
-    pub fn num_purchased(&self, context: Option<u64>) -> i64 {
-        (*self.reserved.borrow() + self.reserved_current(context)) as i64
+    // price of the next unit bought, or of the last order once all are taken
+    pub fn marginal_price(&self) -> Option<f64> {
+        self.next_available(None)
+            .or_else(|| self.inner.orders.last().cloned())
+            .map(|order| order.price)
+    }
+
+    // unreserved orders, cheapest first, until the volume is covered
+    pub fn available(
+        &self,
+        overlay: Option<&ReservationOverlay>,
+        volume: f64,
+    ) -> Vec<api_data::MarketOrder> {
+        let mut current = 0.0;
+        let mut available = Vec::new();
+        let mut remaining = volume;
+        let reserved = self.reserved(overlay);
+        for order in &self.inner.orders {
+            current += order.volume;
+            if current > reserved && remaining > 0.0 {
+                let order_volume = (current - reserved).min(order.volume);
+                remaining -= order_volume;
+                available.push(api_data::MarketOrder {
+                    price: order.price,
+                    volume: order_volume,
+                });
+            }
+        }
+        available
+    }
+
+    pub fn num_purchased(&self, overlay: Option<&ReservationOverlay>) -> i64 {
+        self.reserved(overlay) as i64
     }
 
     pub fn num_purchased_with_stats(
         &self,
-        context: Option<u64>,
+        overlay: Option<&ReservationOverlay>,
         cost: &mut f64,
     ) -> (i64, PurchaseStats) {
-        let reserved = self.reserved(context);
+        let reserved = self.reserved(overlay);
 
         if reserved == 0.0 {
             return (
@@ -125,6 +173,10 @@ impl<'api> TypeMarketOrders<'api> {
         self.inner.orders.first().map(|order| order.price)
     }
 
+    pub fn version(&self) -> u64 {
+        *self.version.read().unwrap()
+    }
+
     pub fn total_volume(&self) -> f64 {
         self.inner.total
     }
@@ -136,13 +188,13 @@ pub struct PurchaseStats {
 }
 
 pub struct LocationMarketOrders<'api> {
-    pub inner: HashMap<u32, TypeMarketOrders<'api>>,
+    pub inner: BTreeMap<u32, TypeMarketOrders<'api>>, // purchases by type id
 }
 
 impl<'api> LocationMarketOrders<'api> {
     pub fn iter_purchases<'this, 'c>(
         &'this self,
-        context: Option<u64>,
+        overlay: Option<&'this ReservationOverlay>,
         cost: &'c mut f64,
     ) -> impl Iterator<Item = (u32, i64, PurchaseStats)> + 'this + 'c
     where
@@ -153,7 +205,7 @@ impl<'api> LocationMarketOrders<'api> {
         self.inner
             .iter()
             .map(move |(&type_id, orders)| {
-                (type_id, orders.num_purchased_with_stats(context, cost))
+                (type_id, orders.num_purchased_with_stats(overlay, cost))
             })
             .filter(|(_, (num_purchased, _))| *num_purchased > 0)
             .map(move |(type_id, (num_purchased, stats))| {
@@ -161,6 +213,7 @@ impl<'api> LocationMarketOrders<'api> {
             })
     }
     pub fn new(
+        location_id: u64,
         inner: Option<&'api HashMap<u32, api_data::TypeMarketOrders>>,
     ) -> Self {
         Self {
@@ -169,11 +222,18 @@ impl<'api> LocationMarketOrders<'api> {
                     inner
                         .iter()
                         .map(|(&type_id, orders)| {
-                            (type_id, TypeMarketOrders::new(orders))
+                            (
+                                type_id,
+                                TypeMarketOrders::new(
+                                    location_id,
+                                    type_id,
+                                    orders,
+                                ),
+                            )
                         })
                         .collect()
                 })
-                .unwrap_or(HashMap::new()),
+                .unwrap_or(BTreeMap::new()),
         }
     }
 
@@ -184,30 +244,53 @@ impl<'api> LocationMarketOrders<'api> {
             .unwrap_or(0.0)
     }
 
-    pub fn reserve(&self, context: Option<u64>, type_id: &u32, volume: f64) {
-        self.inner.get(type_id).unwrap().reserve(context, volume);
+    pub fn reserve(
+        &self,
+        overlay: Option<&ReservationOverlay>,
+        type_id: &u32,
+        volume: f64,
+    ) {
+        self.inner.get(type_id).unwrap().reserve(overlay, volume);
     }
 
     pub fn reserve_i64(
         &self,
-        context: Option<u64>,
+        overlay: Option<&ReservationOverlay>,
         type_id: &u32,
         volume: i64,
     ) {
         self.inner
             .get(type_id)
             .unwrap()
-            .reserve(context, volume as f64);
+            .reserve(overlay, volume as f64);
     }
 
     pub fn next_available(
         &self,
-        context: Option<u64>,
+        overlay: Option<&ReservationOverlay>,
         type_id: &u32,
     ) -> Option<api_data::MarketOrder> {
         self.inner
             .get(&type_id)
-            .and_then(|orders| orders.next_available(context))
+            .and_then(|orders| orders.next_available(overlay))
+    }
+
+    pub fn available(
+        &self,
+        overlay: Option<&ReservationOverlay>,
+        type_id: &u32,
+        volume: f64,
+    ) -> Vec<api_data::MarketOrder> {
+        self.inner
+            .get(type_id)
+            .map(|orders| orders.available(overlay, volume))
+            .unwrap_or_default()
+    }
+
+    pub fn marginal_price(&self, type_id: &u32) -> Option<f64> {
+        self.inner
+            .get(type_id)
+            .and_then(|orders| orders.marginal_price())
     }
 
     pub fn min_sell(&self, type_id: &u32) -> Option<f64> {
@@ -217,10 +300,58 @@ impl<'api> LocationMarketOrders<'api> {
             .flatten()
     }
 
-    pub fn num_purchased(&self, context: Option<u64>, type_id: &u32) -> i64 {
+    pub fn version(&self, type_id: &u32) -> u64 {
+        self.inner
+            .get(type_id)
+            .map(|orders| orders.version())
+            .unwrap_or(0)
+    }
+
+    // units and cost of the committed purchases
+    pub fn purchased(&self, type_id: &u32) -> (i64, f64) {
+        let mut cost = 0.0;
+        let num_purchased = self
+            .inner
+            .get(type_id)
+            .map(|orders| orders.num_purchased_with_stats(None, &mut cost).0)
+            .unwrap_or(0);
+        (num_purchased, cost)
+    }
+
+    pub fn num_purchased(
+        &self,
+        overlay: Option<&ReservationOverlay>,
+        type_id: &u32,
+    ) -> i64 {
         self.inner
             .get(type_id)
-            .map(|orders| orders.num_purchased(context))
+            .map(|orders| orders.num_purchased(overlay))
             .unwrap_or(0)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overlay_keyed_by_location_and_type() {
+        let type_orders = api_data::TypeMarketOrders {
+            orders: vec![api_data::MarketOrder {
+                price: 5.0,
+                volume: 100.0,
+            }],
+            total: 100.0,
+        };
+        let api_orders = HashMap::from([(34, type_orders)]);
+        let hub = LocationMarketOrders::new(1, Some(&api_orders));
+        let other_hub = LocationMarketOrders::new(2, Some(&api_orders));
+        let overlay = ReservationOverlay::default();
+        hub.reserve(Some(&overlay), &34, 40.0);
+        assert_eq!(hub.num_purchased(Some(&overlay), &34), 40);
+        // the same books elsewhere, and nothing committed
+        assert_eq!(other_hub.num_purchased(Some(&overlay), &34), 0);
+        assert_eq!(hub.num_purchased(None, &34), 0);
+        assert_eq!(hub.version(&34), 0);
+    }
+}
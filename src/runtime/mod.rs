@@ -3,7 +3,7 @@ use crate::{
     config::{self, IndustrySlots, Item},
     industry_db,
 };
-use std::{collections::HashMap, rc::Rc, time::Duration};
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
 mod delivery_route;
 
@@ -27,50 +27,74 @@ use db_line_transformed::*;
 mod profit;
 use profit::*;
 
+mod installation_cost;
+use installation_cost::*;
+
+mod research;
+use research::*;
+
+mod reprocessing;
+use reprocessing::*;
+
+mod simplex;
+
+mod ore_mix;
+use ore_mix::*;
+
+mod schedule;
+use schedule::*;
+
+mod sensitivity;
+use sensitivity::*;
+
 mod output;
 use output::*;
+pub use output::{OutputScenarios, OutputSummary};
+
+mod record;
+pub use record::PlanRecord;
 
 pub struct RuntimeData<'cfg, 'db, 'api> {
-    pub locations: Vec<Rc<Location<'cfg, 'db, 'api>>>,
+    pub locations: Vec<Arc<Location<'cfg, 'db, 'api>>>,
     pub type_volumes: &'db HashMap<Item, f64>,
     pub slots: IndustrySlots,
+    pub cfg_slots: &'cfg IndustrySlots,
+    pub cfg_characters: &'cfg HashMap<String, config::Character>,
+    pub max_time: Duration,
     pub min_profit: f64,
     pub min_margin: f64,
+    pub objective: config::Objective,
 }
 
 impl<'cfg, 'db, 'api> RuntimeData<'cfg, 'db, 'api> {
     pub fn new(
-        cfg_locations: &'cfg [config::Location],
-        cfg_slots: &'cfg IndustrySlots,
-        max_time: Duration,
-        daily_flex_time: Duration,
-        min_profit: f64,
-        min_margin: f64,
-        db_lines: &'db HashMap<u32, industry_db::Line>,
-        type_volumes: &'db HashMap<Item, f64>,
-        adjusted_prices: &'api HashMap<u32, f64>,
-        cost_indices: &'api HashMap<u32, config::ManufacturingValue>,
-        market_orders: &'api HashMap<
+        cfg: &'cfg config::Config,
+        db_lines: &'db HashMap<u32, Vec<industry_db::Line>>,
+        db_reprocessing: &'db HashMap<
             u64,
-            HashMap<u32, api_data::TypeMarketOrders>,
+            HashMap<Item, industry_db::Reprocessing>,
         >,
-        assets: &'api HashMap<u64, HashMap<Item, i64>>,
+        type_volumes: &'db HashMap<Item, f64>,
+        api: &'api api_data::Api,
     ) -> Self {
         Self {
             locations: new_locations(
-                cfg_locations,
+                &cfg.locations,
+                &cfg.characters,
                 db_lines,
-                adjusted_prices,
-                cost_indices,
-                market_orders,
-                assets,
-                max_time,
-                daily_flex_time,
+                db_reprocessing,
+                type_volumes,
+                api,
+                cfg.settings(),
             ),
             type_volumes,
-            slots: cfg_slots.clone(),
-            min_profit,
-            min_margin,
+            slots: cfg.slots.clone(),
+            cfg_slots: &cfg.slots,
+            cfg_characters: &cfg.characters,
+            max_time: cfg.max_time,
+            min_profit: cfg.min_profit,
+            min_margin: cfg.min_margin,
+            objective: cfg.objective,
         }
     }
 
@@ -80,6 +104,7 @@ impl<'cfg, 'db, 'api> RuntimeData<'cfg, 'db, 'api> {
             &mut self.slots,
             self.min_profit,
             self.min_margin,
+            self.objective,
             self.type_volumes,
         );
     }
@@ -89,8 +114,295 @@ impl<'cfg, 'db, 'api> RuntimeData<'cfg, 'db, 'api> {
         type_names: &'db HashMap<Item, String>,
         type_volumes: &'db HashMap<Item, f64>,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let output =
-            OutputLocations::new(&self.locations, type_names, type_volumes);
-        output.write()
+        self.output(type_names, type_volumes).write()
+    }
+
+    pub fn summary(
+        &self,
+        type_names: &'db HashMap<Item, String>,
+        type_volumes: &'db HashMap<Item, f64>,
+    ) -> OutputSummary {
+        let production_lines = self
+            .locations
+            .iter()
+            .flat_map(|location| {
+                location
+                    .production_lines()
+                    .iter_all()
+                    .cloned()
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        OutputSummary {
+            profit: production_lines
+                .iter()
+                .flat_map(|pl| pl.profits.read().unwrap().clone())
+                .map(|profit| profit.profit())
+                .sum(),
+            cost: self.output(type_names, type_volumes).cost(),
+            builds: production_lines.iter().map(|pl| pl.num_builds()).sum(),
+        }
+    }
+
+    // what the plan expects, for the history database
+    pub fn record(
+        &self,
+        type_names: &'db HashMap<Item, String>,
+        type_volumes: &'db HashMap<Item, f64>,
+    ) -> serde_json::Result<PlanRecord> {
+        PlanRecord::new(
+            &self.locations,
+            &self.output(type_names, type_volumes),
+            type_names,
+            type_volumes,
+        )
+    }
+
+    fn output(
+        &self,
+        type_names: &'db HashMap<Item, String>,
+        type_volumes: &'db HashMap<Item, f64>,
+    ) -> OutputLocations<'cfg, 'db> {
+        let schedule = schedule_builds(
+            &self.locations,
+            self.cfg_slots,
+            self.cfg_characters,
+            self.max_time,
+        );
+        OutputLocations::new(
+            &self.locations,
+            self.objective,
+            &schedule,
+            type_names,
+            type_volumes,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use industry_db::{Line, Sequencing};
+
+    pub const TRITANIUM: u32 = 34;
+    const PRODUCTS: [u32; 3] = [101, 102, 103];
+
+    // a market hub and a factory building three equally profitable products
+    const CONFIG: &str = "
+locations:
+  - id: 1
+    name: hub
+    system_id: 10
+    market: {sales_tax: 0.036, brokers_fee: 0.015}
+    routes:
+      2: {id: 1, service_name: courier, m3_rate: 100.0, collateral_rate: 0.01}
+  - id: 2
+    name: factory
+    system_id: 20
+    production:
+      tax: {manufacturing: 0.01, invention: 0.01, reaction: 0.01, copy: 0.01}
+      rigs: [null, null, null]
+      structure_type_id: 35825
+      production_lines:
+        - {id: 1, blueprint: {type_id: 1101, runs: 10}, product: {type_id: 101},
+           kind: Manufacturing, export_kind: Product, export_pipe_id: 2,
+           import_src_market_pipe_ids: [1], parallel: 2}
+        - {id: 2, blueprint: {type_id: 1102, runs: 10}, product: {type_id: 102},
+           kind: Manufacturing, export_kind: Product, export_pipe_id: 2,
+           import_src_market_pipe_ids: [1], parallel: 2}
+        - {id: 3, blueprint: {type_id: 1103, runs: 10}, product: {type_id: 103},
+           kind: Manufacturing, export_kind: Product, export_pipe_id: 2,
+           import_src_market_pipe_ids: [1], parallel: 2}
+    routes:
+      1: {id: 2, service_name: courier, m3_rate: 100.0, collateral_rate: 0.01}
+    pipes:
+      1: [1]
+      2: [2]
+skills: {}
+slots: {manufacturing: 3, reaction: 0, science: 0}
+max_time: {secs: 604800, nanos: 0}
+daily_flex_time: {secs: 3600, nanos: 0}
+min_profit: 0.0
+min_margin: 1.0
+";
+
+    // 10 runs making 10 products from 10,000 tritanium, in 10 hours
+    pub fn line(product: u32) -> Line {
+        Line {
+            blueprint: Item::new_blueprint(product + 1000, 10, 0, 0),
+            product: Item::new(product),
+            decryptor: None,
+            installation_minerals: vec![(Item::new(TRITANIUM), 10_000)],
+            minerals: vec![(Item::new(TRITANIUM), 10_000)],
+            base_minerals: vec![(Item::new(TRITANIUM), 1_000)],
+            material_efficiency: 1.0,
+            portion: 10,
+            expected_portion: 10.0,
+            invention: None,
+            invention_materials: Vec::new(),
+            duration: Duration::from_secs(36_000),
+            runs: 10,
+            sequencing: Sequencing::Once,
+            eiv_multiplier: 1.0,
+            cost_efficiency: 1.0,
+            facility_tax: 0.01,
+        }
+    }
+
+    // the config, database and API data a runtime is built from
+    pub struct Snapshot {
+        pub cfg: config::Config,
+        pub db_lines: HashMap<u32, Vec<Line>>,
+        pub db_reprocessing:
+            HashMap<u64, HashMap<Item, industry_db::Reprocessing>>,
+        pub type_names: HashMap<Item, String>,
+        pub type_volumes: HashMap<Item, f64>,
+        pub api: api_data::Api,
+    }
+
+    impl Snapshot {
+        // every item is named and has a volume, products 1 m3, others 0.01
+        // every system has 5% cost indices, every material an EIV of 5
+        pub fn new(cfg: &str, db_lines: HashMap<u32, Vec<Line>>) -> Self {
+            let cfg: config::Config = serde_yaml::from_str(cfg).unwrap();
+            let cost_index: config::ManufacturingValue = serde_yaml::from_str(
+                "{manufacturing: 0.05, invention: 0.05, reaction: 0.05, \
+                     copy: 0.05, research_me: 0.05, research_te: 0.05}",
+            )
+            .unwrap();
+            let cost_indices = cfg
+                .locations
+                .iter()
+                .map(|location| (location.system_id, cost_index))
+                .collect();
+            let mut snapshot = Self {
+                cfg,
+                db_lines: HashMap::new(),
+                db_reprocessing: HashMap::new(),
+                type_names: HashMap::new(),
+                type_volumes: HashMap::new(),
+                api: api_data::Api {
+                    adjusted_prices: HashMap::new(),
+                    cost_indices,
+                    market_orders: HashMap::new(),
+                    assets: HashMap::new(),
+                },
+            };
+            for line in db_lines.values().flatten() {
+                snapshot.add_type(line.product, 1.0);
+                snapshot.add_type(line.blueprint, 0.01);
+                for (item, _) in line
+                    .minerals
+                    .iter()
+                    .chain(line.installation_minerals.iter())
+                    .chain(line.invention_materials.iter())
+                {
+                    snapshot.add_type(*item, 0.01);
+                    snapshot.api.adjusted_prices.insert(item.type_id, 5.0);
+                }
+            }
+            snapshot.db_lines = db_lines;
+            snapshot
+        }
+
+        fn add_type(&mut self, item: Item, volume: f64) {
+            self.type_names
+                .entry(item)
+                .or_insert_with(|| format!("Type {:?}", item));
+            self.type_volumes.entry(item).or_insert(volume);
+        }
+
+        pub fn orders(
+            mut self,
+            location_id: u64,
+            type_id: u32,
+            price: f64,
+            volume: f64,
+        ) -> Self {
+            self.add_type(Item::new(type_id), 0.01);
+            self.api
+                .market_orders
+                .entry(location_id)
+                .or_default()
+                .insert(
+                    type_id,
+                    api_data::TypeMarketOrders {
+                        orders: vec![api_data::MarketOrder { price, volume }],
+                        total: volume,
+                    },
+                );
+            self
+        }
+
+        pub fn assets(
+            mut self,
+            location_id: u64,
+            item: Item,
+            quantity: i64,
+        ) -> Self {
+            self.api
+                .assets
+                .entry(location_id)
+                .or_default()
+                .insert(item, quantity);
+            self
+        }
+
+        pub fn runtime(&self) -> RuntimeData<'_, '_, '_> {
+            RuntimeData::new(
+                &self.cfg,
+                &self.db_lines,
+                &self.db_reprocessing,
+                &self.type_volumes,
+                &self.api,
+            )
+        }
+    }
+
+    impl<'cfg, 'db, 'api> RuntimeData<'cfg, 'db, 'api> {
+        pub fn production_line(
+            &self,
+            id: u32,
+        ) -> Arc<ProductionLine<'cfg, 'db, 'api>> {
+            self.locations
+                .iter()
+                .flat_map(|location| {
+                    location
+                        .production_lines()
+                        .iter_all()
+                        .cloned()
+                        .collect::<Vec<_>>()
+                })
+                .find(|pl| pl.id() == id)
+                .unwrap()
+        }
+    }
+
+    fn run_once() -> String {
+        let db_lines = (1..=3)
+            .zip(PRODUCTS)
+            .map(|(id, product)| (id, vec![line(product)]))
+            .collect::<HashMap<_, _>>();
+        // tritanium runs out before every line can run in parallel
+        let mut snapshot =
+            Snapshot::new(CONFIG, db_lines).orders(1, TRITANIUM, 5.0, 50_000.0);
+        for product in PRODUCTS {
+            snapshot = snapshot.orders(1, product, 20_000.0, 100.0);
+        }
+        let mut runtime = snapshot.runtime();
+        runtime.build();
+        runtime
+            .output(&snapshot.type_names, &snapshot.type_volumes)
+            .to_json()
+            .unwrap()
+    }
+
+    #[test]
+    fn same_snapshot_same_output() {
+        let first = run_once();
+        assert!(first.contains("\"builds\": 2"), "{}", first);
+        for _ in 0..5 {
+            assert_eq!(run_once(), first);
+        }
     }
 }
@@ -0,0 +1,82 @@
+use super::*;
+
+// order levels of a type beyond these are priced as one, at the highest
+const MAX_ORE_MIX_LEVELS: usize = 8;
+
+// a market order that may be part of the mix, as is or as ore to reprocess
+pub struct OreMixOrder<'a, 'cfg, 'db, 'api> {
+    pub pipe: &'a DeliveryPipe<'cfg, 'db, 'api>,
+    pub orders: &'a LocationMarketOrders<'api>,
+    pub bought: Item,
+    pub portion: i64,     // units bought at once
+    pub price: f64,       // per unit bought, with delivery and tax
+    pub units: f64,       // units available at this price
+    pub yields: Vec<f64>, // per unit bought, for each mineral in the list
+    pub fees: f64,        // per unit bought, delivery by volume and tax
+    pub value_rate: f64,  // delivery by value, per ISK of the order
+}
+
+impl<'a, 'cfg, 'db, 'api> OreMixOrder<'a, 'cfg, 'db, 'api> {
+    // the orders of the type, cheapest first, with the costliest merged
+    pub fn levels(
+        orders: &LocationMarketOrders<'api>,
+        overlay: Option<&ReservationOverlay>,
+        bought: &Item,
+        max_units: f64,
+    ) -> Vec<api_data::MarketOrder> {
+        let mut levels = orders.available(overlay, &bought.type_id, max_units);
+        if levels.len() > MAX_ORE_MIX_LEVELS {
+            let merged = levels.split_off(MAX_ORE_MIX_LEVELS - 1);
+            levels.push(api_data::MarketOrder {
+                price: merged.last().unwrap().price,
+                volume: merged.iter().map(|order| order.volume).sum(),
+            });
+        }
+        levels
+    }
+
+    pub fn unit_price(&self, order_price: f64) -> f64 {
+        order_price * (1.0 + self.value_rate) + self.fees
+    }
+
+    // cost of the next units of the type, None if there aren't enough
+    pub fn purchase_cost(
+        &self,
+        overlay: Option<&ReservationOverlay>,
+        units: f64,
+    ) -> Option<f64> {
+        let mut remaining = units;
+        let mut cost = 0.0;
+        for order in self.orders.available(overlay, &self.bought.type_id, units)
+        {
+            let bought = order.volume.min(remaining);
+            cost += self.unit_price(order.price) * bought;
+            remaining -= bought;
+        }
+        match remaining > 1e-9 {
+            true => None,
+            false => Some(cost),
+        }
+    }
+}
+
+// the cheapest units to buy from each order, None if they can't be covered
+// byproducts count towards the other minerals in the list
+pub fn cheapest_ore_mix<'a, 'cfg, 'db, 'api>(
+    needs: &[f64],
+    orders: Vec<OreMixOrder<'a, 'cfg, 'db, 'api>>,
+) -> Option<Vec<(OreMixOrder<'a, 'cfg, 'db, 'api>, f64)>> {
+    let c = orders.iter().map(|o| o.price).collect::<Vec<_>>();
+    let a = (0..needs.len())
+        .map(|i| orders.iter().map(|o| o.yields[i]).collect())
+        .collect::<Vec<_>>();
+    let upper = orders.iter().map(|o| o.units).collect::<Vec<_>>();
+    let units = simplex::minimize(&c, &a, needs, &upper)?;
+    Some(
+        orders
+            .into_iter()
+            .zip(units)
+            .filter(|(_, units)| *units > 0.0)
+            .collect(),
+    )
+}
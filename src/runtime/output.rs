@@ -1,20 +1,41 @@
 use super::*;
 use crate::config::Item;
 use serde::Serialize;
+use std::collections::BTreeMap;
 
 #[derive(Serialize)]
 pub struct OutputLocations<'cfg, 'db> {
     // DOES NOT include delivery collateral rate costs
     cost: f64,
+    objective: String, // what the builds' scores are
     locations: Vec<OutputLocation<'cfg, 'db>>,
+    research: Vec<OutputResearch<'cfg, 'db>>,
+    schedule: Vec<OutputJob<'cfg, 'db>>,
 }
 
 impl<'cfg, 'db> OutputLocations<'cfg, 'db> {
     pub fn new(
-        locations: &[Rc<Location<'cfg, '_, '_>>],
+        locations: &[Arc<Location<'cfg, '_, '_>>],
+        objective: config::Objective,
+        schedule: &[ScheduledJob<'cfg, '_, '_>],
         type_names: &'db HashMap<Item, String>,
         type_volumes: &'db HashMap<Item, f64>,
     ) -> Self {
+        let schedule = schedule
+            .iter()
+            .map(|job| OutputJob::new(job, type_names))
+            .collect();
+        let research = locations
+            .iter()
+            .flat_map(|location| {
+                OutputResearch::new_in_location(
+                    location,
+                    locations,
+                    type_names,
+                    type_volumes,
+                )
+            })
+            .collect();
         let mut cost = 0.0;
         let locations = locations
             .iter()
@@ -27,14 +48,27 @@ impl<'cfg, 'db> OutputLocations<'cfg, 'db> {
                 )
             })
             .collect();
-        Self { cost, locations }
+        Self {
+            cost,
+            objective: format!("{:?}", objective),
+            locations,
+            research,
+            schedule,
+        }
+    }
+
+    pub fn cost(&self) -> f64 {
+        self.cost
     }
 
     pub fn write(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let file = std::fs::File::create("output.json")?;
-        serde_json::to_writer_pretty(file, self)?;
+        std::fs::write("output.json", self.to_json()?)?;
         Ok(())
     }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
 }
 
 #[derive(Serialize)]
@@ -56,7 +90,7 @@ impl<'cfg, 'db> OutputLocation<'cfg, 'db> {
         Self {
             location: location.name(),
             purchases: Self::purchases(location, type_names, cost),
-            builds: Self::builds(location, type_names, cost),
+            builds: Self::builds(location, type_names, type_volumes, cost),
             deliveries: Self::deliveries(
                 location,
                 type_names,
@@ -93,6 +127,7 @@ impl<'cfg, 'db> OutputLocation<'cfg, 'db> {
     fn builds(
         location: &Location<'cfg, '_, '_>,
         type_names: &'db HashMap<Item, String>,
+        type_volumes: &HashMap<Item, f64>,
         cost: &mut f64,
     ) -> Option<Vec<OutputBuild<'db>>> {
         let mut builds = None;
@@ -101,7 +136,9 @@ impl<'cfg, 'db> OutputLocation<'cfg, 'db> {
             if num_builds == 0 {
                 continue;
             }
-            *cost += num_builds as f64 * production_line.installation_cost();
+            let installation_cost =
+                production_line.installation_cost() * num_builds as f64;
+            *cost += installation_cost.total();
             builds.get_or_insert_with(Vec::new).push(OutputBuild {
                 product: &type_names[&production_line.product()],
                 blueprint: &type_names[&production_line.blueprint()],
@@ -110,7 +147,32 @@ impl<'cfg, 'db> OutputLocation<'cfg, 'db> {
                     .map(|item| type_names[&item].as_str()),
                 runs: production_line.runs_per_sequence(),
                 sequences: production_line.num_sequences(),
+                bpcs: production_line.num_bpcs_consumed() * num_builds,
                 builds: num_builds,
+                installation_cost: OutputInstallationCost::new(
+                    installation_cost,
+                ),
+                invention: OutputInvention::new(
+                    production_line,
+                    num_builds,
+                    type_names,
+                ),
+                reprocessing: production_line.reprocessing_value().map(
+                    |(reprocessed, sold)| OutputReprocessing {
+                        reprocessed,
+                        sold,
+                        reprocess: reprocessed > sold,
+                    },
+                ),
+                chain_hours: production_line.chain_duration().as_secs_f64()
+                    / 3600.0,
+                exceeds_window: production_line.exceeds_window(),
+                scores: production_line.scores.read().unwrap().clone(),
+                sensitivity: OutputSensitivity::new(
+                    production_line,
+                    type_names,
+                    type_volumes,
+                ),
             });
         }
         builds
@@ -122,7 +184,7 @@ impl<'cfg, 'db> OutputLocation<'cfg, 'db> {
         type_volumes: &'db HashMap<Item, f64>,
         cost: &mut f64,
     ) -> Option<Vec<OutputDeliveries<'cfg, 'db>>> {
-        let mut deliveries_map = HashMap::new();
+        let mut deliveries_map = BTreeMap::new();
         for (route, delivery_pipes) in location
             .routes()
             .iter_transit(location.id())
@@ -136,7 +198,7 @@ impl<'cfg, 'db> OutputLocation<'cfg, 'db> {
                         * quantity as f64;
                     *deliveries_map
                         .entry((route.dst.name(), route.service_name()))
-                        .or_insert(HashMap::new())
+                        .or_insert(BTreeMap::new())
                         .entry(item)
                         .or_insert(0) += quantity;
                 }
@@ -184,6 +246,100 @@ impl<'cfg, 'db> OutputLocation<'cfg, 'db> {
     }
 }
 
+#[derive(Serialize)]
+pub struct OutputResearch<'cfg, 'db> {
+    pub location: &'cfg str,
+    pub blueprint: &'db str,
+    pub product: &'db str,
+    pub duration_hours: f64,
+    pub installation_cost: f64,
+    pub planned_runs: i64,
+    pub material_savings: f64,
+    pub time_savings_hours: f64,
+    pub time_savings_value: f64,
+    pub pays_off: Option<bool>,
+}
+
+impl<'cfg, 'db> OutputResearch<'cfg, 'db> {
+    pub fn new_in_location<'ldb, 'api>(
+        location: &Location<'cfg, 'ldb, 'api>,
+        locations: &[Arc<Location<'cfg, 'ldb, 'api>>],
+        type_names: &'db HashMap<Item, String>,
+        type_volumes: &HashMap<Item, f64>,
+    ) -> Vec<Self> {
+        location
+            .production_lines()
+            .iter_all()
+            .filter(|pl| pl.job_kind().is_research())
+            .map(|pl| {
+                let recommendation =
+                    ResearchRecommendation::new(pl, locations, type_volumes);
+                OutputResearch {
+                    location: location.name(),
+                    blueprint: &type_names[&pl.blueprint()],
+                    product: &type_names[&pl.product()],
+                    duration_hours: pl.duration().as_secs_f64() / 3600.0,
+                    installation_cost: recommendation.installation_cost(),
+                    planned_runs: recommendation.planned_runs,
+                    material_savings: recommendation.material_savings,
+                    time_savings_hours: recommendation
+                        .time_savings
+                        .as_secs_f64()
+                        / 3600.0,
+                    time_savings_value: recommendation.time_savings_value,
+                    pays_off: recommendation.pays_off(),
+                }
+            })
+            .collect()
+    }
+}
+
+#[derive(Serialize)]
+pub struct OutputJob<'cfg, 'db> {
+    pub location: &'cfg str,
+    pub product: &'db str,
+    pub kind: String,
+    pub character: Option<&'cfg str>,
+    pub location_slot: Option<usize>, // within the location's share of slots
+    pub character_slot: Option<usize>, // within the character's slots
+    pub build: i64,
+    pub sequence: i64,
+    pub start_day: u64, // 1 is the first day of the window
+    pub start_hours: f64,
+    pub end_hours: f64,
+    pub late: bool,           // ends after the planning window
+    pub after: Vec<&'db str>, // intermediates that have to arrive first
+}
+
+impl<'cfg, 'db> OutputJob<'cfg, 'db> {
+    pub fn new(
+        job: &ScheduledJob<'cfg, '_, '_>,
+        type_names: &'db HashMap<Item, String>,
+    ) -> Self {
+        let production_line = &job.production_line;
+        OutputJob {
+            location: production_line.location().name(),
+            product: &type_names[&production_line.product()],
+            kind: format!("{:?}", production_line.job_kind()),
+            character: production_line.character(),
+            location_slot: job.location_slot,
+            character_slot: job.character_slot,
+            build: job.build,
+            sequence: job.sequence,
+            start_day: job.start.as_secs() / (24 * 60 * 60) + 1,
+            start_hours: job.start.as_secs_f64() / 3600.0,
+            end_hours: job.end.as_secs_f64() / 3600.0,
+            late: job.late,
+            after: production_line
+                .intermediate_production_lines()
+                .iter()
+                .filter(|pl| pl.num_builds() > 0)
+                .map(|pl| type_names[&pl.product()].as_str())
+                .collect(),
+        }
+    }
+}
+
 #[derive(Serialize)]
 pub struct OutputPurchase<'db> {
     pub item: &'db str,
@@ -199,7 +355,176 @@ pub struct OutputBuild<'db> {
     pub decryptor: Option<&'db str>,
     pub runs: i64,
     pub sequences: i64,
+    pub bpcs: i64, // BPCs consumed over all builds
     pub builds: i64,
+    pub installation_cost: OutputInstallationCost,
+    pub invention: Option<OutputInvention<'db>>,
+    pub reprocessing: Option<OutputReprocessing>,
+    pub chain_hours: f64, // upstream builds and transit, one build, to market
+    pub exceeds_window: bool,
+    pub scores: Vec<f64>, // objective of each build when it was picked
+    pub sensitivity: Option<OutputSensitivity<'db>>,
+}
+
+// per build, for products that were picked
+#[derive(Serialize)]
+pub struct OutputSensitivity<'db> {
+    pub profit: f64,
+    pub sell_price: f64,
+    pub break_even_sell_price: f64,
+    pub break_even_material_change: Option<f64>, // portion, e.g. 0.05
+    pub swung_profit: f64, // sell prices down and material prices up by 3%
+    pub negative_on_swing: bool,
+    pub materials: Vec<OutputMaterialSensitivity<'db>>,
+}
+
+#[derive(Serialize)]
+pub struct OutputMaterialSensitivity<'db> {
+    pub item: &'db str,
+    pub quantity: f64,
+    pub price: f64, // paid on average by the plan, delivered
+    pub profit_per_percent: f64, // profit change when the price rises by 1%
+}
+
+impl<'db> OutputSensitivity<'db> {
+    pub fn new(
+        production_line: &ProductionLine,
+        type_names: &'db HashMap<Item, String>,
+        type_volumes: &HashMap<Item, f64>,
+    ) -> Option<Self> {
+        let sensitivity = PriceSensitivity::new(production_line, type_volumes)?;
+        Some(Self {
+            profit: sensitivity.profit,
+            sell_price: sensitivity.sell_price,
+            break_even_sell_price: sensitivity.break_even_sell_price(),
+            break_even_material_change: sensitivity
+                .break_even_material_change(),
+            swung_profit: sensitivity.swung_profit(),
+            negative_on_swing: sensitivity.swung_profit() < 0.0,
+            materials: sensitivity
+                .materials
+                .iter()
+                .map(|(item, quantity, price)| OutputMaterialSensitivity {
+                    item: &type_names[item],
+                    quantity: *quantity,
+                    price: *price,
+                    profit_per_percent: -quantity * price / 100.0,
+                })
+                .collect(),
+        })
+    }
+}
+
+// per unit, for products that could be reprocessed instead of sold
+#[derive(Serialize)]
+pub struct OutputReprocessing {
+    pub reprocessed: f64,
+    pub sold: f64,
+    pub reprocess: bool,
+}
+
+#[derive(Serialize)]
+pub struct OutputInvention<'db> {
+    pub attempts: i64,
+    pub probability: f64,
+    pub expected: f64,
+    pub variance: f64,
+    pub p10: i64,
+    pub p90: i64,
+    pub target: Option<OutputInventionTarget<'db>>,
+}
+
+#[derive(Serialize)]
+pub struct OutputInventionTarget<'db> {
+    pub bpcs: i64,
+    pub confidence: f64,
+    pub planned_confidence: f64, // of reaching the target with the plan
+    pub attempts: Option<i64>,   // None if the target can't be reached
+    pub extra_attempts: Option<i64>,
+    pub extra_materials: Vec<OutputDelivery<'db>>,
+}
+
+impl<'db> OutputInvention<'db> {
+    pub fn new(
+        production_line: &ProductionLine,
+        num_builds: i64,
+        type_names: &'db HashMap<Item, String>,
+    ) -> Option<Self> {
+        let outcome = production_line.invention()?;
+        let outcome = outcome.with_attempts(outcome.attempts * num_builds);
+        let target = production_line.invention_target().map(|target| {
+            let attempts = outcome.attempts_for(target.bpcs, target.confidence);
+            let extra_attempts =
+                attempts.map(|attempts| (attempts - outcome.attempts).max(0));
+            OutputInventionTarget {
+                bpcs: target.bpcs,
+                confidence: target.confidence,
+                planned_confidence: outcome.at_least(target.bpcs),
+                attempts,
+                extra_attempts,
+                extra_materials: Self::extra_materials(
+                    production_line,
+                    extra_attempts.unwrap_or(0),
+                    type_names,
+                ),
+            }
+        });
+        Some(Self {
+            attempts: outcome.attempts,
+            probability: outcome.probability,
+            expected: outcome.expected(),
+            variance: outcome.variance(),
+            p10: outcome.percentile(0.1),
+            p90: outcome.percentile(0.9),
+            target,
+        })
+    }
+
+    // datacores, decryptors and relics per attempt, BPCs per extra job
+    fn extra_materials(
+        production_line: &ProductionLine,
+        extra_attempts: i64,
+        type_names: &'db HashMap<Item, String>,
+    ) -> Vec<OutputDelivery<'db>> {
+        let mut extra_materials = production_line
+            .invention_materials()
+            .map(|(item, quantity)| OutputDelivery {
+                item: &type_names[&item],
+                quantity: quantity * extra_attempts,
+            })
+            .collect::<Vec<_>>();
+        let blueprint = production_line.blueprint();
+        if blueprint.is_bpc() && extra_attempts > 0 {
+            let attempts_per_job = production_line.runs_per_sequence();
+            extra_materials.push(OutputDelivery {
+                item: &type_names[&blueprint],
+                quantity: (extra_attempts + attempts_per_job - 1)
+                    / attempts_per_job,
+            });
+        }
+        extra_materials
+    }
+}
+
+#[derive(Serialize)]
+pub struct OutputInstallationCost {
+    pub system_cost: f64,
+    pub facility_tax: f64,
+    pub scc_surcharge: f64,
+    pub alpha_surcharge: f64,
+    pub total: f64,
+}
+
+impl OutputInstallationCost {
+    pub fn new(installation_cost: InstallationCost) -> Self {
+        Self {
+            system_cost: installation_cost.system_cost,
+            facility_tax: installation_cost.facility_tax,
+            scc_surcharge: installation_cost.scc_surcharge,
+            alpha_surcharge: installation_cost.alpha_surcharge,
+            total: installation_cost.total(),
+        }
+    }
 }
 
 #[derive(Serialize)]
@@ -221,3 +546,74 @@ pub struct OutputAssetTarget<'db> {
     pub target: i64,
     pub current: i64,
 }
+
+// totals of a plan, for comparing scenarios
+#[derive(Clone, Serialize)]
+pub struct OutputSummary {
+    pub profit: f64, // expected, as priced when each build was picked
+    pub cost: f64,   // ISK spent, as in the plan's output
+    pub builds: i64,
+}
+
+#[derive(Serialize)]
+pub struct OutputScenario {
+    pub name: String,
+    #[serde(flatten)]
+    pub summary: OutputSummary,
+    // against the baseline, the config and API data as they are
+    pub profit_change: f64,
+    pub cost_change: f64,
+    pub builds_change: i64,
+}
+
+#[derive(Serialize)]
+pub struct OutputScenarios {
+    baseline: OutputSummary,
+    scenarios: Vec<OutputScenario>,
+}
+
+impl OutputScenarios {
+    pub fn new(
+        baseline: OutputSummary,
+        scenarios: Vec<(String, OutputSummary)>,
+    ) -> Self {
+        let scenarios = scenarios
+            .into_iter()
+            .map(|(name, summary)| OutputScenario {
+                name,
+                profit_change: summary.profit - baseline.profit,
+                cost_change: summary.cost - baseline.cost,
+                builds_change: summary.builds - baseline.builds,
+                summary,
+            })
+            .collect();
+        Self {
+            baseline,
+            scenarios,
+        }
+    }
+
+    pub fn print(&self) {
+        println!(
+            "{:<24} {:>18} {:>18} {:>8}",
+            "scenario", "profit", "spent", "builds"
+        );
+        let rows = std::iter::once(("baseline", &self.baseline)).chain(
+            self.scenarios
+                .iter()
+                .map(|scenario| (scenario.name.as_str(), &scenario.summary)),
+        );
+        for (name, summary) in rows {
+            println!(
+                "{:<24} {:>18.2} {:>18.2} {:>8}",
+                name, summary.profit, summary.cost, summary.builds
+            );
+        }
+    }
+
+    pub fn write(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let file = std::fs::File::create("scenarios.json")?;
+        serde_json::to_writer_pretty(file, self)?;
+        Ok(())
+    }
+}
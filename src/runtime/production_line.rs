@@ -2,75 +2,375 @@ use super::*;
 use crate::config::{self, IndustrySlots, Item, ProductionLineExportKind};
 use crate::industry_db;
 use core::f64;
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use std::{
+    collections::HashMap,
+    iter,
+    sync::{Arc, RwLock},
+};
 
 pub struct ProductionLine<'cfg, 'db, 'api> {
     pub inner: &'cfg config::ProductionLine,
-    pub export_pipe: Rc<DeliveryPipe<'cfg, 'db, 'api>>,
-    pub import_src_market_pipes: Vec<Rc<DeliveryPipe<'cfg, 'db, 'api>>>,
+    pub export_pipe: Arc<DeliveryPipe<'cfg, 'db, 'api>>,
+    pub import_src_market_pipes: Vec<Arc<DeliveryPipe<'cfg, 'db, 'api>>>,
     pub import_src_intermediate_production_lines:
-        RefCell<HashMap<u32, Rc<ProductionLine<'cfg, 'db, 'api>>>>,
+        RwLock<HashMap<u32, Arc<ProductionLine<'cfg, 'db, 'api>>>>,
+    // matched to blueprint inputs by their product
+    pub import_src_blueprint_production_lines:
+        RwLock<Vec<Arc<ProductionLine<'cfg, 'db, 'api>>>>,
+    variants: Vec<ProductionLineVariant<'db>>, // one per decryptor if auto
+    variant: RwLock<usize>,                    // the selected variant
+    character_slots: Option<Arc<RwLock<IndustrySlots>>>,
+    max_time: Duration,
+    pub builds: RwLock<i64>,
+    pub scores: RwLock<Vec<f64>>, // objective of each build when picked
+    pub profits: RwLock<Vec<Profit>>, // of each build when picked
+    profit_cache: RwLock<Option<(u64, Option<Profit>)>>, // by market version
+}
+
+type MarketMinerals = Vec<(Item, f64)>;
+type SlotPools = Vec<(Arc<RwLock<IndustrySlots>>, IndustrySlots)>;
+
+// a market order that a material can be bought from
+struct MaterialSource<'a, 'cfg, 'db, 'api> {
+    pipe: &'a DeliveryPipe<'cfg, 'db, 'api>,
+    orders: &'a LocationMarketOrders<'api>,
+    bought: Item,    // the material itself, or ore to reprocess
+    per_unit: f64,   // material from each unit bought
+    portion: i64,    // units bought at once
+    price: f64,      // per unit of material, with delivery and tax
+    units: f64,      // units available at this price
+    fees: f64,       // per unit bought, delivery by volume and tax
+    value_rate: f64, // delivery by value, per ISK of the order
+}
+
+impl MaterialSource<'_, '_, '_, '_> {
+    // units to buy for the material still needed, ore in whole portions
+    // that may take later orders too
+    fn units_for(&self, material: f64) -> f64 {
+        let needed = material / self.per_unit;
+        match self.portion {
+            1 => needed.min(self.units),
+            portion => {
+                let portion = portion as f64;
+                (needed / portion - 1e-9).ceil() * portion
+            }
+        }
+    }
+
+    // the next units bought order by order and what they cost delivered,
+    // fewer units if the market runs out
+    fn purchase(
+        &self,
+        overlay: Option<&ReservationOverlay>,
+        units: f64,
+    ) -> (f64, f64) {
+        let mut bought = 0.0;
+        let mut cost = 0.0;
+        for order in self.orders.available(overlay, &self.bought.type_id, units)
+        {
+            let volume = order.volume.min(units - bought);
+            cost +=
+                (order.price * (1.0 + self.value_rate) + self.fees) * volume;
+            bought += volume;
+        }
+        (bought, cost)
+    }
+}
+
+pub struct ProductionLineVariant<'db> {
     pub db_line: DbLineTransformed<'db>,
-    installation_cost: f64, // installation cost for N runs
-    pub builds: RefCell<i64>,
+    installation_cost: InstallationCost, // installation cost for N runs
 }
 
 impl<'cfg, 'db, 'api> ProductionLine<'cfg, 'db, 'api> {
     pub fn new(
         inner: &'cfg config::ProductionLine,
-        export_pipe: Rc<DeliveryPipe<'cfg, 'db, 'api>>,
-        import_src_market_pipes: Vec<Rc<DeliveryPipe<'cfg, 'db, 'api>>>,
-        db_line: &'db industry_db::Line,
-        adjusted_prices: &'api HashMap<u32, f64>,
-        cost_indices: &'api HashMap<u32, config::ManufacturingValue>,
-        max_time: Duration,
-        daily_flex_time: Duration,
+        export_pipe: Arc<DeliveryPipe<'cfg, 'db, 'api>>,
+        import_src_market_pipes: Vec<Arc<DeliveryPipe<'cfg, 'db, 'api>>>,
+        db_lines: &'db [industry_db::Line],
+        api: &'api api_data::Api,
+        settings: config::Settings,
+        character_slots: Option<Arc<RwLock<IndustrySlots>>>,
     ) -> Self {
-        let db_line =
-            DbLineTransformed::new(db_line, max_time, daily_flex_time);
-        let installation_cost = {
-            let eiv = db_line
-                .installation_minerals()
-                .map(|(item, quantity)| {
-                    let price = adjusted_prices.get(&item.type_id).unwrap();
-                    *price * quantity as f64
-                })
-                .sum::<f64>();
-            let index_cost = cost_indices[&export_pipe.src().system_id()]
-                .kind_value(inner.kind);
-            eiv * index_cost * db_line.cost_multiplier()
-        };
+        let index_cost = api.cost_indices[&export_pipe.src().system_id()]
+            .kind_value(inner.kind);
+        let variants = db_lines
+            .iter()
+            .map(|db_line| {
+                let db_line = DbLineTransformed::new(
+                    db_line,
+                    settings.max_time,
+                    settings.daily_flex_time,
+                );
+                let eiv = db_line
+                    .installation_minerals()
+                    .map(|(item, quantity)| {
+                        let price =
+                            api.adjusted_prices.get(&item.type_id).unwrap();
+                        *price * quantity as f64
+                    })
+                    .sum::<f64>();
+                let installation_cost = InstallationCost::new(
+                    eiv,
+                    db_line.eiv_multiplier(),
+                    index_cost,
+                    db_line.cost_efficiency(),
+                    db_line.facility_tax(),
+                    settings.scc_surcharge,
+                    settings.alpha_clone,
+                );
+                ProductionLineVariant {
+                    db_line,
+                    installation_cost,
+                }
+            })
+            .collect();
         Self {
             inner,
-            installation_cost,
             export_pipe,
             import_src_market_pipes,
-            import_src_intermediate_production_lines: RefCell::new(
+            import_src_intermediate_production_lines: RwLock::new(
                 HashMap::new(),
             ),
-            db_line,
-            builds: RefCell::new(0),
+            import_src_blueprint_production_lines: RwLock::new(Vec::new()),
+            variants,
+            character_slots,
+            max_time: settings.max_time,
+            variant: RwLock::new(0),
+            builds: RwLock::new(0),
+            scores: RwLock::new(Vec::new()),
+            profits: RwLock::new(Vec::new()),
+            profit_cache: RwLock::new(None),
+        }
+    }
+
+    fn selected(&self) -> &ProductionLineVariant<'db> {
+        &self.variants[*self.variant.read().unwrap()]
+    }
+
+    fn db_line(&self) -> &DbLineTransformed<'db> {
+        &self.selected().db_line
+    }
+
+    pub fn installation_cost(&self) -> InstallationCost {
+        self.selected().installation_cost
+    }
+
+    // picks the best scoring variant, must be called before building
+    // lines making this line's blueprint follow its variant, and
+    // intermediates are scored by the product lines they feed
+    pub fn select_variant(
+        &self,
+        consumers: &[Arc<ProductionLine<'cfg, 'db, 'api>>],
+        objective: config::Objective,
+        type_volumes: &HashMap<Item, f64>,
+    ) {
+        if self.variants.len() < 2 {
+            self.align_blueprint_sources();
+            return;
+        }
+        let mut best = None;
+        for i in 0..self.variants.len() {
+            *self.variant.write().unwrap() = i;
+            self.align_blueprint_sources();
+            let value = match self.export_kind() {
+                ProductionLineExportKind::Product => {
+                    self.profit(None, None, type_volumes).and_then(|profit| {
+                        profit.score(objective, self.slot_hours())
+                    })
+                }
+                ProductionLineExportKind::Intermediate
+                    if !consumers.is_empty() =>
+                {
+                    consumers
+                        .iter()
+                        .filter_map(|consumer| {
+                            consumer.profit(None, None, type_volumes).and_then(
+                                |profit| {
+                                    profit
+                                        .score(objective, consumer.slot_hours())
+                                },
+                            )
+                        })
+                        .reduce(|a, b| a + b)
+                }
+                // nothing downstream to score by, the cheapest per run
+                ProductionLineExportKind::Intermediate => {
+                    self.profit(None, None, type_volumes).map(|profit| {
+                        -profit.cost
+                            / (self.expected_portion()
+                                * self.product().runs.max(1) as f64)
+                    })
+                }
+            };
+            let value = match value {
+                Some(value) => value,
+                None => continue,
+            };
+            if best
+                .map(|(_, best_value)| value > best_value)
+                .unwrap_or(true)
+            {
+                best = Some((i, value));
+            }
+        }
+        *self.variant.write().unwrap() = best.map(|(i, _)| i).unwrap_or(0);
+        self.align_blueprint_sources();
+    }
+
+    // points the lines making this line's blueprint at the variant that
+    // makes it, the blueprint is bought if none of them can
+    fn align_blueprint_sources(&self) {
+        let blueprint = self.blueprint();
+        for source in self
+            .import_src_blueprint_production_lines
+            .read()
+            .unwrap()
+            .iter()
+        {
+            if let Some(i) = source
+                .variants
+                .iter()
+                .position(|variant| variant.db_line.product() == blueprint)
+            {
+                *source.variant.write().unwrap() = i;
+                source.align_blueprint_sources();
+            }
         }
     }
 
-    pub fn installation_cost(&self) -> f64 {
-        self.installation_cost
+    // the configured lines upstream of this one, whatever their variants
+    pub fn upstream_ids(&self) -> Vec<u32> {
+        let mut upstream = self
+            .import_src_intermediate_production_lines
+            .read()
+            .unwrap()
+            .values()
+            .chain(
+                self.import_src_blueprint_production_lines
+                    .read()
+                    .unwrap()
+                    .iter(),
+            )
+            .flat_map(|pl| iter::once(pl.id()).chain(pl.upstream_ids()))
+            .collect::<Vec<_>>();
+        upstream.sort();
+        upstream.dedup();
+        upstream
+    }
+
+    pub fn is_blueprint_source(&self, line: &ProductionLine) -> bool {
+        line.import_src_blueprint_production_lines
+            .read()
+            .unwrap()
+            .iter()
+            .any(|pl| pl.id() == self.id())
     }
 
     pub fn runs(&self) -> i64 {
-        self.db_line.runs()
+        self.db_line().runs()
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.db_line().duration()
     }
 
     pub fn runs_per_sequence(&self) -> i64 {
-        self.db_line.runs_per_sequence()
+        self.db_line().runs_per_sequence()
     }
 
     pub fn num_sequences(&self) -> i64 {
-        self.db_line.num_sequences()
+        self.db_line().num_sequences()
+    }
+
+    pub fn extra_duration(&self) -> Duration {
+        self.db_line().extra_duration()
+    }
+
+    // every sequence of a build, back to back in a single slot
+    pub fn build_duration(&self) -> Duration {
+        match self.num_sequences() {
+            // not a single daily sequence fits in the window
+            0 => Duration::ZERO,
+            num_sequences => {
+                (self.duration() + self.extra_duration()) * num_sequences as u32
+                    - self.extra_duration()
+            }
+        }
+    }
+
+    // the slowest market delivery, materials are bought before the first job
+    pub fn import_market_transit_time(&self) -> Duration {
+        self.import_src_market_pipes
+            .iter()
+            .map(|pipe| pipe.transit_time())
+            .max()
+            .unwrap_or(Duration::ZERO)
+    }
+
+    // the longest path of builds and deliveries ending with one build here,
+    // and for products, its delivery to market
+    pub fn chain_duration(&self) -> Duration {
+        match self.export_kind() {
+            ProductionLineExportKind::Product => {
+                self.build_chain_duration() + self.export_pipe().transit_time()
+            }
+            ProductionLineExportKind::Intermediate => {
+                self.build_chain_duration()
+            }
+        }
+    }
+
+    fn build_chain_duration(&self) -> Duration {
+        self.intermediate_production_lines()
+            .into_iter()
+            .map(|pl| {
+                pl.build_chain_duration() + pl.export_pipe().transit_time()
+            })
+            .max()
+            .unwrap_or(Duration::ZERO)
+            .max(self.import_market_transit_time())
+            + self.build_duration()
+    }
+
+    // the chain can't deliver within the planning window
+    pub fn exceeds_window(&self) -> bool {
+        self.chain_duration() > self.max_time
+    }
+
+    pub fn id(&self) -> u32 {
+        self.inner.id
+    }
+
+    pub fn character(&self) -> Option<&'cfg str> {
+        self.inner.character.as_deref()
+    }
+
+    pub fn num_bpcs_consumed(&self) -> i64 {
+        self.db_line().num_bpcs_consumed()
     }
 
     pub fn portion(&self) -> i64 {
-        self.db_line.portion()
+        self.db_line().portion()
+    }
+
+    // for invention, the expected value of the outcome distribution
+    pub fn expected_portion(&self) -> f64 {
+        self.db_line().expected_portion()
+    }
+
+    pub fn invention(&self) -> Option<industry_db::InventionOutcome> {
+        self.db_line().invention()
+    }
+
+    pub fn invention_materials(
+        &self,
+    ) -> impl Iterator<Item = (config::Item, i64)> + '_ {
+        self.db_line().invention_materials()
+    }
+
+    pub fn invention_target(&self) -> Option<config::InventionTarget> {
+        self.inner.invention_target
     }
 
     pub fn runs_per(&self) -> f64 {
@@ -82,33 +382,99 @@ impl<'cfg, 'db, 'api> ProductionLine<'cfg, 'db, 'api> {
     }
 
     pub fn decryptor(&self) -> Option<Item> {
-        self.inner.decryptor.map(|type_id| Item::new(type_id))
+        self.db_line().decryptor()
     }
 
     pub fn installation_cost_for(&self, num_produced: f64) -> f64 {
-        self.installation_cost * (num_produced / self.portion() as f64)
+        self.installation_cost().total()
+            * (num_produced / self.expected_portion())
     }
 
     pub fn import_src_intermediate_production_line(
         &self,
-        type_id: &u32,
-    ) -> Option<Rc<ProductionLine<'cfg, 'db, 'api>>> {
-        self.import_src_intermediate_production_lines
-            .borrow()
-            .get(type_id)
-            .map(|pl| pl.clone())
+        item: &Item,
+    ) -> Option<Arc<ProductionLine<'cfg, 'db, 'api>>> {
+        match item.is_blueprint() {
+            true => self
+                .import_src_blueprint_production_lines
+                .read()
+                .unwrap()
+                .iter()
+                .find(|pl| pl.product() == *item)
+                .map(|pl| pl.clone()),
+            false => self
+                .import_src_intermediate_production_lines
+                .read()
+                .unwrap()
+                .get(&item.type_id)
+                .map(|pl| pl.clone()),
+        }
     }
 
     pub fn import_src_intermediate_pipe(
         &self,
-        type_id: &u32,
-    ) -> Option<Rc<DeliveryPipe<'cfg, 'db, 'api>>> {
-        self.import_src_intermediate_production_lines
-            .borrow()
-            .get(type_id)
+        item: &Item,
+    ) -> Option<Arc<DeliveryPipe<'cfg, 'db, 'api>>> {
+        self.import_src_intermediate_production_line(item)
             .map(|pl| pl.export_pipe.clone())
     }
 
+    pub fn intermediate_production_lines(
+        &self,
+    ) -> Vec<Arc<ProductionLine<'cfg, 'db, 'api>>> {
+        let mut production_lines: Vec<Arc<ProductionLine>> = Vec::new();
+        for (pl, _, _, _) in self.intermediate_minerals() {
+            if !production_lines.iter().any(|other| other.id() == pl.id()) {
+                production_lines.push(pl);
+            }
+        }
+        production_lines
+    }
+
+    // intermediate lines, the quantity each one has to deliver per build and
+    // the builds of it that takes, None if it can't be expected to deliver
+    fn intermediate_minerals(
+        &self,
+    ) -> Vec<(Arc<ProductionLine<'cfg, 'db, 'api>>, Item, i64, Option<i64>)>
+    {
+        self.minerals_i64()
+            .filter_map(|(item, quantity)| {
+                self.import_src_intermediate_production_line(&item)
+                    .map(|pl| {
+                        let num_builds = match item.is_blueprint() {
+                            // e.g. several invention jobs to feed one BPC job
+                            true => pl.builds_for(quantity),
+                            false => Some(1),
+                        };
+                        (pl, item, quantity, num_builds)
+                    })
+            })
+            .collect()
+    }
+
+    // number of builds expected to deliver the quantity, one at least
+    // None if a build is expected to deliver nothing
+    pub fn builds_for(&self, quantity: i64) -> Option<i64> {
+        let expected_portion = self.expected_portion();
+        match expected_portion > 0.0 {
+            true => Some(
+                ((quantity as f64 / expected_portion - 1e-9).ceil() as i64)
+                    .max(1),
+            ),
+            false => None,
+        }
+    }
+
+    // whether every intermediate line that would be built can deliver
+    fn intermediates_deliverable(&self) -> bool {
+        self.intermediate_minerals().into_iter().all(
+            |(pl, _, _, num_builds)| {
+                !pl.should_build_and_deliver()
+                    || (num_builds.is_some() && pl.intermediates_deliverable())
+            },
+        )
+    }
+
     pub fn export_pipe(&self) -> &DeliveryPipe<'cfg, 'db, 'api> {
         self.export_pipe.as_ref()
     }
@@ -126,18 +492,24 @@ impl<'cfg, 'db, 'api> ProductionLine<'cfg, 'db, 'api> {
         num_produced: Option<f64>,
     ) -> impl Iterator<Item = (config::Item, f64)> + '_ {
         let mult = match num_produced {
-            Some(num_produced) => num_produced / self.portion() as f64,
+            Some(num_produced) => num_produced / self.expected_portion(),
             None => 1.0,
         };
-        self.db_line
-            .minerals()
-            .map(move |(item, quantity)| (item, quantity as f64 * mult))
+        let bpc_runs_used = self.db_line().bpc_runs_used();
+        self.db_line().minerals().map(move |(item, quantity)| {
+            // a BPC is only charged for the runs the job uses
+            let mult = match item.is_bpc() {
+                true => mult * bpc_runs_used,
+                false => mult,
+            };
+            (item, quantity as f64 * mult)
+        })
     }
 
     pub fn minerals_i64(
         &self,
     ) -> impl Iterator<Item = (config::Item, i64)> + '_ {
-        self.db_line.minerals()
+        self.db_line().minerals()
     }
 
     fn import_src_market_locations<'this>(
@@ -186,15 +558,16 @@ impl<'cfg, 'db, 'api> ProductionLine<'cfg, 'db, 'api> {
     }
 
     pub fn product(&self) -> Item {
-        self.inner.transput.product
+        self.db_line().product()
     }
 
+    // the selected variant's, which may come from a blueprint source line
     pub fn blueprint(&self) -> Item {
-        self.inner.transput.blueprint
+        self.db_line().blueprint()
     }
 
     pub fn num_builds(&self) -> i64 {
-        *self.builds.borrow()
+        *self.builds.read().unwrap()
     }
 
     pub fn max_num_builds(&self) -> i64 {
@@ -209,7 +582,7 @@ impl<'cfg, 'db, 'api> ProductionLine<'cfg, 'db, 'api> {
         self.portion() * self.num_builds()
     }
 
-    fn unwrap_export_market(&self) -> &LocationMarket<'cfg, 'api> {
+    pub fn unwrap_export_market(&self) -> &LocationMarket<'cfg, 'api> {
         self.export_pipe.dst().unwrap_market()
     }
 
@@ -231,47 +604,136 @@ impl<'cfg, 'db, 'api> ProductionLine<'cfg, 'db, 'api> {
 
     pub fn max_slots(&self) -> config::IndustrySlots {
         let mut slots = IndustrySlots::from_slot(self.slot_kind());
-        for sub_production_line in self
-            .import_src_intermediate_production_lines
-            .borrow()
-            .values()
+        for (sub_production_line, _, _, num_builds) in
+            self.intermediate_minerals()
         {
-            slots.add(sub_production_line.max_slots());
+            for _ in 0..num_builds.unwrap_or(0) {
+                slots.add(sub_production_line.max_slots());
+            }
         }
         slots
     }
 
+    // slot time used by a build and the intermediate builds it needs
+    pub fn slot_hours(&self) -> f64 {
+        let mut hours = self.build_duration().as_secs_f64() / 3600.0;
+        for (sub_production_line, _, _, num_builds) in
+            self.intermediate_minerals()
+        {
+            hours += num_builds.unwrap_or(0) as f64
+                * sub_production_line.slot_hours();
+        }
+        hours
+    }
+
+    // the cheapest next order for a material, bought as is or as ore
+    fn cheapest_source(
+        &self,
+        overlay: Option<&ReservationOverlay>,
+        item: &Item,
+        type_volumes: &HashMap<Item, f64>,
+    ) -> Option<MaterialSource<'_, 'cfg, 'db, 'api>> {
+        let mut cheapest: Option<MaterialSource> = None;
+        for (pipe, orders) in self.import_src_market_pipes_with_orders() {
+            let delivery_rate = pipe.delivery_rate();
+            // ore is reprocessed where this line is, byproducts are wasted
+            let sources = iter::once((*item, 1.0, 0.0, 1)).chain(
+                self.location().reprocessing_sources(item).map(
+                    |(reprocessing, per_unit)| {
+                        (
+                            reprocessing.item(),
+                            per_unit,
+                            reprocessing.tax_per_unit(),
+                            reprocessing.portion(),
+                        )
+                    },
+                ),
+            );
+            for (bought, per_unit, tax, portion) in sources {
+                let order =
+                    match orders.next_available(overlay, &bought.type_id) {
+                        Some(order) => order,
+                        None => continue,
+                    };
+                let volume = type_volumes.get(&bought).copied().unwrap_or(0.0);
+                let fees = delivery_rate.m3_rate * volume + tax;
+                let value_rate =
+                    delivery_rate.collateral_rate + delivery_rate.capital_rate;
+                let price =
+                    (order.price * (1.0 + value_rate) + fees) / per_unit;
+                if cheapest.as_ref().map(|c| price < c.price).unwrap_or(true) {
+                    cheapest = Some(MaterialSource {
+                        pipe,
+                        orders,
+                        bought,
+                        per_unit,
+                        portion,
+                        price,
+                        units: order.volume,
+                        fees,
+                        value_rate,
+                    });
+                }
+            }
+        }
+        cheapest
+    }
+
+    // the capped structure and character pools that jobs come out of
+    fn slot_pools(&self) -> impl Iterator<Item = &Arc<RwLock<IndustrySlots>>> {
+        self.location_production()
+            .slots
+            .iter()
+            .chain(self.character_slots.iter())
+    }
+
+    // slots needed from each capped pool, including intermediate lines
+    fn max_pool_slots(&self, pools: &mut SlotPools) {
+        for pool in self.slot_pools() {
+            let slots = IndustrySlots::from_slot(self.slot_kind());
+            match pools.iter_mut().find(|(p, _)| Arc::ptr_eq(p, pool)) {
+                Some((_, needed)) => needed.add(slots),
+                None => pools.push((pool.clone(), slots)),
+            }
+        }
+        for (sub_production_line, _, _, num_builds) in
+            self.intermediate_minerals()
+        {
+            for _ in 0..num_builds.unwrap_or(0) {
+                sub_production_line.max_pool_slots(pools);
+            }
+        }
+    }
+
+    fn can_use_pool_slots(&self) -> bool {
+        let mut pools = Vec::new();
+        self.max_pool_slots(&mut pools);
+        pools
+            .iter()
+            .all(|(pool, needed)| pool.read().unwrap().can_use_slots(needed))
+    }
+
     fn permanent_reserve_from_market_and_deliver(
         &self,
         item: &Item,
         quantity: i64,
         type_volumes: &HashMap<Item, f64>,
     ) {
-        let volume = type_volumes.get(&item).copied().unwrap_or(0.0);
         let mut reserved = 0;
         while reserved < quantity {
-            let mut cheapest_market = None;
-            let mut cheapest_reservable = 0.0;
-            let mut cheapest_price_with_delivery = f64::INFINITY;
-            for (pipe, orders) in self.import_src_market_pipes_with_orders() {
-                if let Some(order) = orders.next_available(None, &item.type_id)
-                {
-                    let price_with_delivery = order.price
-                        + pipe.delivery_rate().m3_rate * volume
-                        + pipe.delivery_rate().collateral_rate * order.price;
-                    if price_with_delivery < cheapest_price_with_delivery {
-                        cheapest_market = Some((orders, pipe));
-                        cheapest_reservable = order.volume;
-                        cheapest_price_with_delivery = price_with_delivery;
-                    }
-                }
-            }
-            if let Some((orders, pipe)) = cheapest_market {
-                let cheapest_reserve =
-                    (cheapest_reservable as i64).min(quantity - reserved);
-                reserved += cheapest_reserve;
-                orders.reserve_i64(None, &item.type_id, cheapest_reserve);
-                pipe.deliver(*item, cheapest_reserve);
+            if let Some(source) = self.cheapest_source(None, item, type_volumes)
+            {
+                let units = source
+                    .units_for((quantity - reserved) as f64)
+                    .ceil()
+                    .max(1.0);
+                let (units, _) = source.purchase(None, units);
+                let units = units.round() as i64;
+                reserved += (units as f64 * source.per_unit).round() as i64;
+                source
+                    .orders
+                    .reserve_i64(None, &source.bought.type_id, units);
+                source.pipe.deliver(source.bought, units);
             } else {
                 let mut highest_volume_market = None;
                 let mut highest_volume = 0.0;
@@ -302,67 +764,195 @@ impl<'cfg, 'db, 'api> ProductionLine<'cfg, 'db, 'api> {
 
     fn reserve_from_market(
         &self,
-        context: u64,
+        overlay: &ReservationOverlay,
         item: &Item,
         quantity: f64,
         type_volumes: &HashMap<Item, f64>,
     ) -> Option<f64> {
-        let volume = type_volumes.get(&item).copied().unwrap_or(0.0);
         let mut reserved = 0.0;
         let mut type_cost = 0.0;
         while reserved < quantity {
-            let mut cheapest_market: Option<&LocationMarketOrders<'api>> = None;
-            let mut cheapest_reservable = 0.0;
-            let mut cheapest_price_with_delivery = f64::INFINITY;
-            for (pipe, orders) in self.import_src_market_pipes_with_orders() {
-                if let Some(order) =
-                    orders.next_available(Some(context), &item.type_id)
+            let source =
+                self.cheapest_source(Some(overlay), item, type_volumes)?;
+            let units = source.units_for(quantity - reserved);
+            let (units, cost) = source.purchase(Some(overlay), units);
+            reserved += units * source.per_unit;
+            type_cost += cost;
+            source
+                .orders
+                .reserve(Some(overlay), &source.bought.type_id, units);
+        }
+        Some(type_cost)
+    }
+
+    // orders for every mineral in the list and every ore that yields one
+    fn ore_mix_orders(
+        &self,
+        overlay: Option<&ReservationOverlay>,
+        minerals: &[(Item, f64)],
+        type_volumes: &HashMap<Item, f64>,
+    ) -> Vec<OreMixOrder<'_, 'cfg, 'db, 'api>> {
+        let ores = self
+            .location()
+            .reprocessing
+            .values()
+            .filter(|r| r.is_ore())
+            .filter(|r| {
+                minerals
+                    .iter()
+                    .any(|(item, _)| r.material_per_unit(item).is_some())
+            })
+            .collect::<Vec<_>>();
+        let mut mix_orders = Vec::new();
+        for (pipe, orders) in self.import_src_market_pipes_with_orders() {
+            let delivery_rate = pipe.delivery_rate();
+            let sources = minerals
+                .iter()
+                .map(|(mineral, _)| {
+                    let yields = minerals
+                        .iter()
+                        .map(|(item, _)| match item == mineral {
+                            true => 1.0,
+                            false => 0.0,
+                        })
+                        .collect::<Vec<_>>();
+                    (*mineral, yields, 0.0, 1)
+                })
+                .chain(ores.iter().map(|r| {
+                    let yields = minerals
+                        .iter()
+                        .map(|(item, _)| {
+                            r.material_per_unit(item).unwrap_or(0.0)
+                        })
+                        .collect::<Vec<_>>();
+                    (r.item(), yields, r.tax_per_unit(), r.portion())
+                }));
+            for (bought, yields, tax, portion) in sources {
+                // no more than what covers the largest need on its own
+                let max_units = minerals
+                    .iter()
+                    .zip(yields.iter())
+                    .filter(|(_, y)| **y > 0.0)
+                    .map(|((_, quantity), y)| quantity / y)
+                    .fold(0.0, f64::max);
+                let volume = type_volumes.get(&bought).copied().unwrap_or(0.0);
+                let fees = delivery_rate.m3_rate * volume + tax;
+                let value_rate =
+                    delivery_rate.collateral_rate + delivery_rate.capital_rate;
+                for order in
+                    OreMixOrder::levels(orders, overlay, &bought, max_units)
                 {
-                    let price_with_delivery = order.price
-                        + pipe.delivery_rate().m3_rate * volume
-                        + pipe.delivery_rate().collateral_rate * order.price;
-                    if price_with_delivery < cheapest_price_with_delivery {
-                        cheapest_market = Some(orders);
-                        cheapest_reservable = order.volume;
-                        cheapest_price_with_delivery = price_with_delivery;
-                    }
+                    mix_orders.push(OreMixOrder {
+                        pipe,
+                        orders,
+                        bought,
+                        portion,
+                        price: order.price * (1.0 + value_rate) + fees,
+                        units: order.volume,
+                        yields: yields.clone(),
+                        fees,
+                        value_rate,
+                    });
                 }
             }
-            let orders = cheapest_market?;
-            let cheapest_reserve = cheapest_reservable.min(quantity - reserved);
-            reserved += cheapest_reserve;
-            type_cost += cheapest_price_with_delivery * cheapest_reserve;
-            orders.reserve(Some(context), &item.type_id, cheapest_reserve);
         }
-        Some(type_cost)
+        mix_orders
     }
 
-    fn market_cost_with_delivery(
+    // reserves the cheapest mix of minerals and ore covering the minerals
+    // ore is bought in whole portions, priced by the orders those take
+    // without an overlay, it's delivered
+    fn reserve_ore_mix(
         &self,
-        context: u64,
-        num_produced: Option<f64>,
+        overlay: Option<&ReservationOverlay>,
+        minerals: &[(Item, f64)],
         type_volumes: &HashMap<Item, f64>,
     ) -> Option<f64> {
+        let needs = minerals.iter().map(|(_, q)| *q).collect::<Vec<_>>();
+        let mix = cheapest_ore_mix(
+            &needs,
+            self.ore_mix_orders(overlay, minerals, type_volumes),
+        )?;
+        // order levels of the same type are bought together
+        let mut purchases: Vec<(&OreMixOrder, f64)> = Vec::new();
+        for (order, units) in mix.iter() {
+            match purchases.iter_mut().find(|(o, _)| {
+                std::ptr::eq(o.orders, order.orders) && o.bought == order.bought
+            }) {
+                Some((_, total)) => *total += units,
+                None => purchases.push((order, *units)),
+            }
+        }
         let mut cost = 0.0;
-        for (item, quantity) in self.minerals(num_produced) {
-            match self.import_src_intermediate_production_line(&item.type_id) {
-                Some(_) => continue,
-                None => match self.reserve_from_market(
-                    context,
-                    &item,
-                    quantity,
-                    type_volumes,
-                ) {
-                    Some(type_cost) => cost += type_cost,
-                    None => return None,
-                },
+        for (order, units) in purchases.iter_mut() {
+            let portion = order.portion as f64;
+            *units = (*units / portion - 1e-9).ceil() * portion;
+            cost += order.purchase_cost(overlay, *units)?;
+        }
+        for (order, units) in purchases {
+            match overlay {
+                Some(_) => {
+                    order.orders.reserve(overlay, &order.bought.type_id, units)
+                }
+                None => {
+                    let units = units as i64;
+                    order.orders.reserve_i64(
+                        None,
+                        &order.bought.type_id,
+                        units,
+                    );
+                    order.pipe.deliver(order.bought, units);
+                }
             }
         }
         Some(cost)
     }
 
-    fn profit_context(&self) -> u64 {
-        ((self.inner.id as u64) << 32) | (*self.builds.borrow() as u64)
+    // market materials, split into those that ore could cover and the rest
+    fn market_minerals(
+        &self,
+        minerals: impl Iterator<Item = (Item, f64)>,
+    ) -> (MarketMinerals, MarketMinerals) {
+        minerals
+            .filter(|(item, _)| {
+                self.import_src_intermediate_production_line(item).is_none()
+            })
+            .partition(|(item, _)| {
+                self.location().reprocessing_sources(item).next().is_some()
+            })
+    }
+
+    fn market_cost_with_delivery(
+        &self,
+        overlay: &ReservationOverlay,
+        num_produced: Option<f64>,
+        type_volumes: &HashMap<Item, f64>,
+    ) -> Option<f64> {
+        let mut cost = 0.0;
+        let (ore_minerals, mut minerals) =
+            self.market_minerals(self.minerals(num_produced));
+        if !ore_minerals.is_empty() {
+            match self.reserve_ore_mix(
+                Some(overlay),
+                &ore_minerals,
+                type_volumes,
+            ) {
+                Some(mix_cost) => cost += mix_cost,
+                None => minerals.extend(ore_minerals),
+            }
+        }
+        for (item, quantity) in minerals {
+            match self.reserve_from_market(
+                overlay,
+                &item,
+                quantity,
+                type_volumes,
+            ) {
+                Some(type_cost) => cost += type_cost,
+                None => return None,
+            }
+        }
+        Some(cost)
     }
 
     fn revenue_with_delivery(
@@ -371,8 +961,7 @@ impl<'cfg, 'db, 'api> ProductionLine<'cfg, 'db, 'api> {
         type_volumes: &HashMap<Item, f64>,
         market_cost_with_delivery: f64,
     ) -> Profit {
-        let num_produced =
-            num_produced.unwrap_or(self.db_line.portion() as f64);
+        let num_produced = num_produced.unwrap_or(self.expected_portion());
         let volume = type_volumes.get(&self.product()).copied().unwrap_or(0.0);
         let delivery_rate = self.export_pipe().delivery_rate();
         let min_sell = match self.export_kind() {
@@ -383,15 +972,18 @@ impl<'cfg, 'db, 'api> ProductionLine<'cfg, 'db, 'api> {
             config::ProductionLineExportKind::Intermediate => None,
         };
         let delivery_m3_fee = delivery_rate.m3_rate * volume * num_produced;
-        let delivery_collateral_fee = delivery_rate.collateral_rate
-            * match min_sell {
-                Some(min_sell) => min_sell * num_produced,
-                None => {
-                    market_cost_with_delivery
-                        + self.installation_cost_for(num_produced)
-                }
-            };
-        let delivery_fee = delivery_m3_fee + delivery_collateral_fee;
+        let delivery_value = match min_sell {
+            Some(min_sell) => min_sell * num_produced,
+            None => {
+                market_cost_with_delivery
+                    + self.installation_cost_for(num_produced)
+            }
+        };
+        let delivery_collateral_fee =
+            delivery_rate.collateral_rate * delivery_value;
+        let delivery_capital_fee = delivery_rate.capital_rate * delivery_value;
+        let delivery_fee =
+            delivery_m3_fee + delivery_collateral_fee + delivery_capital_fee;
         let market_revenue = min_sell.unwrap_or(0.0) * num_produced;
         let (sales_tax, brokers_fee) = match self.export_kind() {
             config::ProductionLineExportKind::Product => (
@@ -405,14 +997,15 @@ impl<'cfg, 'db, 'api> ProductionLine<'cfg, 'db, 'api> {
 
     pub fn profit(
         &self,
-        context: Option<u64>,
+        overlay: Option<&ReservationOverlay>,
         num_produced: Option<f64>,
         type_volumes: &HashMap<Item, f64>,
     ) -> Option<Profit> {
-        let context = context.unwrap_or(self.profit_context());
+        let fresh_overlay = ReservationOverlay::default();
+        let overlay = overlay.unwrap_or(&fresh_overlay);
 
         let market_cost_with_delivery = self.market_cost_with_delivery(
-            context,
+            overlay,
             num_produced,
             type_volumes,
         )?;
@@ -425,27 +1018,232 @@ impl<'cfg, 'db, 'api> ProductionLine<'cfg, 'db, 'api> {
 
         let mut profit = revenue_with_delivery;
         profit.cost += market_cost_with_delivery;
+        profit.materials += market_cost_with_delivery;
         profit.cost += match num_produced {
             Some(num_produced) => self.installation_cost_for(num_produced),
-            None => self.installation_cost,
+            None => self.installation_cost().total(),
         };
 
         for (item, quantity) in self.minerals(num_produced) {
             if let Some(pl) =
-                self.import_src_intermediate_production_line(&item.type_id)
+                self.import_src_intermediate_production_line(&item)
             {
                 profit +=
-                    pl.profit(Some(context), Some(quantity), type_volumes)?;
+                    pl.profit(Some(overlay), Some(quantity), type_volumes)?;
             }
         }
 
         Some(profit)
     }
 
+    // market materials of a build, intermediates included, priced at what
+    // the plan paid for them on average, delivered
+    pub fn plan_materials(
+        &self,
+        type_volumes: &HashMap<Item, f64>,
+    ) -> Vec<(Item, f64, f64)> {
+        self.materials_priced(self.minerals(None), &|pl, item| {
+            pl.plan_price(item, type_volumes)
+        })
+    }
+
+    // market materials a build saves if its blueprint had `to` ME rather
+    // than `from`, only materials that ME reduces
+    pub fn me_savings(&self, from: i8, to: i8) -> Vec<(Item, f64, f64)> {
+        let saved = self
+            .db_line()
+            .minerals_with_me(from)
+            .zip(self.db_line().minerals_with_me(to))
+            .map(|((item, before), (_, after))| (item, (before - after) as f64))
+            .filter(|(_, quantity)| *quantity > 0.0)
+            .collect::<Vec<_>>();
+        self.materials_priced(saved.into_iter(), &Self::next_price)
+    }
+
+    fn materials_priced(
+        &self,
+        minerals: impl Iterator<Item = (Item, f64)>,
+        price: &dyn Fn(&Self, &Item) -> f64,
+    ) -> Vec<(Item, f64, f64)> {
+        let mut materials: Vec<(Item, f64, f64)> = Vec::new();
+        for (item, quantity) in minerals {
+            let item_materials =
+                match self.import_src_intermediate_production_line(&item) {
+                    Some(pl) => {
+                        pl.materials_priced(pl.minerals(Some(quantity)), price)
+                    }
+                    None => vec![(item, quantity, price(self, &item))],
+                };
+            for (item, quantity, price) in item_materials {
+                match materials.iter_mut().find(|(other, _, _)| *other == item)
+                {
+                    Some((_, total, _)) => *total += quantity,
+                    None => materials.push((item, quantity, price)),
+                }
+            }
+        }
+        materials
+    }
+
+    // the next unit at the cheapest market
+    fn next_price(&self, item: &Item) -> f64 {
+        self.import_src_market_pipes_with_orders()
+            .filter_map(|(_, orders)| orders.marginal_price(&item.type_id))
+            .min_by(|a, b| a.total_cmp(b))
+            .unwrap_or(0.0)
+    }
+
+    // what the plan paid for a unit on average at the markets this line
+    // buys from, before delivery, None if it bought none there
+    pub fn purchase_price(&self, item: &Item) -> Option<f64> {
+        let (units, cost) = self
+            .import_src_market_pipes_with_orders()
+            .map(|(_, orders)| orders.purchased(&item.type_id))
+            .fold((0, 0.0), |(units, cost), (bought, bought_cost)| {
+                (units + bought, cost + bought_cost)
+            });
+        match units {
+            0 => None,
+            units => Some(cost / units as f64),
+        }
+    }
+
+    // the average the plan paid for a unit, delivered, over the markets
+    // this line buys from, the next unit's price if it bought none
+    // ore reprocessed for it counts too, its cost split between what it
+    // yields by their value at the market
+    fn plan_price(
+        &self,
+        item: &Item,
+        type_volumes: &HashMap<Item, f64>,
+    ) -> f64 {
+        let volume =
+            |item: &Item| type_volumes.get(item).copied().unwrap_or(0.0);
+        let mut cost = 0.0;
+        let mut units = 0.0;
+        for (pipe, orders) in self.import_src_market_pipes_with_orders() {
+            let rate = pipe.delivery_rate();
+            let value_rate = rate.collateral_rate + rate.capital_rate;
+            let (bought, bought_cost) = orders.purchased(&item.type_id);
+            cost += bought_cost * (1.0 + value_rate)
+                + bought as f64 * rate.m3_rate * volume(item);
+            units += bought as f64;
+            let value = |item: &Item| orders.min_sell(&item.type_id);
+            for (ore, per_unit) in self.location().reprocessing_sources(item) {
+                let (bought, bought_cost) =
+                    orders.purchased(&ore.item().type_id);
+                let yield_value = ore.value_per_unit(value);
+                if bought == 0 || yield_value <= 0.0 {
+                    continue;
+                }
+                let ore_cost = bought_cost * (1.0 + value_rate)
+                    + bought as f64
+                        * (rate.m3_rate * volume(&ore.item())
+                            + ore.tax_per_unit());
+                let share = per_unit * value(item).unwrap_or(0.0) / yield_value;
+                cost += ore_cost * share;
+                units += bought as f64 * per_unit;
+            }
+        }
+        match units > 0.0 {
+            true => cost / units,
+            false => self.next_price(item),
+        }
+    }
+
+    // the profit of the next build, if it was priced at this market version
+    pub fn cached_profit(&self, version: u64) -> Option<Option<Profit>> {
+        match &*self.profit_cache.read().unwrap() {
+            Some((cached_version, profit)) if *cached_version == version => {
+                Some(profit.clone())
+            }
+            _ => None,
+        }
+    }
+
+    pub fn cache_profit(&self, version: u64, profit: Option<Profit>) {
+        *self.profit_cache.write().unwrap() = Some((version, profit));
+    }
+
+    // sum of the versions of every order book that materials, or ore for
+    // them, can be bought from, including those of intermediate lines
+    // versions only go up, so the sum changes whenever any of them does
+    pub fn market_version(&self) -> u64 {
+        let mut version = 0;
+        for (item, _) in self.minerals_i64() {
+            if let Some(pl) =
+                self.import_src_intermediate_production_line(&item)
+            {
+                version += pl.market_version();
+                continue;
+            }
+            for (_, orders) in self.import_src_market_pipes_with_orders() {
+                version += orders.version(&item.type_id);
+                for (reprocessing, _) in
+                    self.location().reprocessing_sources(&item)
+                {
+                    version += orders.version(&reprocessing.item().type_id);
+                }
+            }
+        }
+        version
+    }
+
+    // per unit (reprocessed, sold) at the export market, None if not a product
+    // reprocessing happens where the product is built, delivery is ignored
+    pub fn reprocessing_value(&self) -> Option<(f64, f64)> {
+        if self.export_kind() != ProductionLineExportKind::Product {
+            return None;
+        }
+        let reprocessing = self.location().reprocessing.get(&self.product())?;
+        let market = self.unwrap_export_market();
+        let after_fees = 1.0 - market.sales_tax() - market.brokers_fee();
+        let reprocessed = reprocessing
+            .value_per_unit(|item| market.orders.min_sell(&item.type_id))
+            * after_fees
+            - reprocessing.tax_per_unit();
+        let sold = market.orders.min_sell(&self.product().type_id)?;
+        Some((reprocessed, sold * after_fees))
+    }
+
     pub fn can_build(&self, slots: &IndustrySlots) -> bool {
         slots.can_use_slots(&self.max_slots())
+            && self.can_use_pool_slots()
             && self.num_builds() < self.max_num_builds()
             && self.should_build_and_deliver()
+            && self.bpo_available()
+            && self.intermediates_deliverable()
+    }
+
+    // BPOs aren't consumed, but each one can only be used by one job at a time
+    pub fn uses_bpo(&self) -> bool {
+        self.blueprint().is_bpo()
+            && matches!(
+                self.job_kind(),
+                config::ManufacturingKind::Manufacturing
+                    | config::ManufacturingKind::Copy
+            )
+    }
+
+    fn bpo_available(&self) -> bool {
+        self.bpos_available(1)
+    }
+
+    // checks this line and any intermediate lines that would be built, for
+    // a number of builds that all run at the same time
+    fn bpos_available(&self, num_builds: i64) -> bool {
+        let location = self.location();
+        (!self.uses_bpo()
+            || location.num_bpo_in_use(self.blueprint()) + num_builds
+                <= location.asset_quantity(self.blueprint()))
+            && self.intermediate_minerals().into_iter().all(
+                |(pl, _, _, pl_num_builds)| {
+                    !pl.should_build_and_deliver()
+                        || pl.bpos_available(
+                            num_builds * pl_num_builds.unwrap_or(0),
+                        )
+                },
+            )
     }
 
     pub fn should_build_and_deliver(&self) -> bool {
@@ -460,34 +1258,465 @@ impl<'cfg, 'db, 'api> ProductionLine<'cfg, 'db, 'api> {
     ) {
         // use build slot
         slots.use_slot(self.slot_kind());
+        for pool in self.slot_pools() {
+            pool.write().unwrap().use_slot(self.slot_kind());
+        }
 
         // increment builds
-        *self.builds.borrow_mut() += 1;
-
-        // purchase or buy and deliver minerals
-        for (item, quantity) in self.minerals_i64() {
-            match self.import_src_intermediate_production_line(&item.type_id) {
-                Some(pl) => {
-                    // generally, more will be built than delivered
-                    // so, intermediate lines don't always run
-                    if pl.should_build_and_deliver() {
-                        pl.build(slots, type_volumes);
-                        pl.export_pipe().deliver(item, quantity);
-                    } else {
-                        // import is not needed, we have enough already here
+        *self.builds.write().unwrap() += 1;
+
+        // build and deliver intermediates
+        for (pl, item, quantity, num_builds) in self.intermediate_minerals() {
+            // generally, more will be built than delivered
+            // so, intermediate lines don't always run
+            if pl.should_build_and_deliver() {
+                for _ in 0..num_builds.unwrap_or(0) {
+                    if !pl.should_build_and_deliver() {
+                        break;
                     }
+                    pl.build(slots, type_volumes);
                 }
-                None => self.permanent_reserve_from_market_and_deliver(
-                    &item,
-                    quantity,
-                    type_volumes,
-                ),
+                pl.export_pipe().deliver(item, quantity);
+            } else {
+                // import is not needed, we have enough already here
             }
         }
 
+        // purchase and deliver minerals, as ore where it's cheaper
+        let (ore_minerals, mut minerals) = self.market_minerals(
+            self.minerals_i64().map(|(item, q)| (item, q as f64)),
+        );
+        if !ore_minerals.is_empty()
+            && self
+                .reserve_ore_mix(None, &ore_minerals, type_volumes)
+                .is_none()
+        {
+            minerals.extend(ore_minerals);
+        }
+        for (item, quantity) in minerals {
+            self.permanent_reserve_from_market_and_deliver(
+                &item,
+                quantity as i64,
+                type_volumes,
+            );
+        }
+
         // export product if this is a product line
         if self.export_kind() == ProductionLineExportKind::Product {
             self.export_pipe().deliver(self.product(), self.portion());
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::IndustrySlot;
+    use crate::industry_db::Line;
+    use crate::runtime::tests::{line, Snapshot, TRITANIUM};
+
+    // line 1 needs 20 of 102 per build, delivered by a build of line 2
+    const BPO_CONFIG: &str = "
+locations:
+  - id: 1
+    name: hub
+    system_id: 10
+    market: {sales_tax: 0.036, brokers_fee: 0.015}
+    routes:
+      2: {id: 1, service_name: courier, m3_rate: 100.0, collateral_rate: 0.01}
+  - id: 2
+    name: factory
+    system_id: 20
+    production:
+      tax: {manufacturing: 0.01, invention: 0.01, reaction: 0.01, copy: 0.01}
+      rigs: [null, null, null]
+      structure_type_id: 35825
+      production_lines:
+        - {id: 1, blueprint: {type_id: 1101, runs: -1}, product: {type_id: 101},
+           kind: Manufacturing, export_kind: Product, export_pipe_id: 2,
+           import_src_market_pipe_ids: [1],
+           import_src_production_line_ids: {102: 2}, parallel: 2}
+        - {id: 2, blueprint: {type_id: 1102, runs: -1}, product: {type_id: 102},
+           kind: Manufacturing, export_kind: Intermediate, export_pipe_id: 3,
+           import_src_market_pipe_ids: [1], parallel: 4}
+    routes:
+      1: {id: 2, service_name: courier, m3_rate: 100.0, collateral_rate: 0.01}
+      2: {id: 3, service_name: hangar, m3_rate: 0.0, collateral_rate: 0.0}
+    pipes:
+      1: [1]
+      2: [2]
+      3: [3]
+skills: {}
+slots: {manufacturing: 6, reaction: 0, science: 0}
+max_time: {secs: 604800, nanos: 0}
+daily_flex_time: {secs: 3600, nanos: 0}
+min_profit: 0.0
+min_margin: 1.0
+";
+
+    fn bpo(type_id: u32) -> Item {
+        Item::new_blueprint(type_id, -1, 0, 0)
+    }
+
+    // builds of (line 1, line 2) with the BPOs owned at the factory
+    fn bpo_builds(product_bpos: i64, intermediate_bpos: i64) -> (i64, i64) {
+        config_builds(BPO_CONFIG, product_bpos, intermediate_bpos)
+    }
+
+    fn config_builds(
+        config: &str,
+        product_bpos: i64,
+        intermediate_bpos: i64,
+    ) -> (i64, i64) {
+        let db_lines = HashMap::from([
+            (
+                1,
+                vec![Line {
+                    blueprint: bpo(1101),
+                    minerals: vec![(Item::new(102), 20)],
+                    base_minerals: vec![(Item::new(102), 2)],
+                    ..line(101)
+                }],
+            ),
+            (
+                2,
+                vec![Line {
+                    blueprint: bpo(1102),
+                    ..line(102)
+                }],
+            ),
+        ]);
+        let snapshot = Snapshot::new(config, db_lines)
+            .orders(1, TRITANIUM, 5.0, 1_000_000.0)
+            .orders(1, 101, 30_000.0, 1_000.0)
+            .assets(2, bpo(1101), product_bpos)
+            .assets(2, bpo(1102), intermediate_bpos);
+        let mut runtime = snapshot.runtime();
+        runtime.build();
+        (
+            runtime.production_line(1).num_builds(),
+            runtime.production_line(2).num_builds(),
+        )
+    }
+
+    #[test]
+    fn bpo_jobs_limited_by_owned_bpos() {
+        assert_eq!(bpo_builds(0, 4), (0, 0));
+        assert_eq!(bpo_builds(1, 4), (1, 1));
+        assert_eq!(bpo_builds(2, 4), (2, 2));
+    }
+
+    #[test]
+    fn bpo_intermediate_needs_a_free_bpo() {
+        // the intermediate is built once for each build of line 1
+        assert_eq!(bpo_builds(2, 0), (0, 0));
+        assert_eq!(bpo_builds(2, 1), (1, 1));
+    }
+
+    // the factory's share of the structure's slots
+    fn location_slots(slots: &str) -> String {
+        BPO_CONFIG.replace(
+            "      structure_type_id: 35825",
+            &format!("      slots: {}\n      structure_type_id: 35825", slots),
+        )
+    }
+
+    // both lines installed by one character
+    fn character_slots(slots: &str) -> String {
+        BPO_CONFIG
+            .replace(
+                "import_src_production_line_ids: {102: 2}, parallel: 2}",
+                "import_src_production_line_ids: {102: 2}, parallel: 2,\n           character: alice}",
+            )
+            .replace(
+                "import_src_market_pipe_ids: [1], parallel: 4}",
+                "import_src_market_pipe_ids: [1], parallel: 4,\n           character: alice}",
+            )
+            .replace(
+                "skills: {}",
+                &format!("skills: {{}}\ncharacters:\n  alice: {{slots: {}}}", slots),
+            )
+    }
+
+    #[test]
+    fn location_pool_counts_intermediate_slots() {
+        // a build of line 1 takes its slot and one for line 2's build
+        let config = location_slots("{manufacturing: 4}");
+        assert_eq!(config_builds(&config, 4, 4), (2, 2));
+        let config = location_slots("{manufacturing: 3}");
+        assert_eq!(config_builds(&config, 4, 4), (1, 1));
+        let config = location_slots("{manufacturing: 1}");
+        assert_eq!(config_builds(&config, 4, 4), (0, 0));
+    }
+
+    #[test]
+    fn character_pool_counts_intermediate_slots() {
+        let config = character_slots("{manufacturing: 3}");
+        assert_eq!(config_builds(&config, 4, 4), (1, 1));
+        let config = character_slots("{manufacturing: 0}");
+        assert_eq!(config_builds(&config, 4, 4), (0, 0));
+    }
+
+    #[test]
+    fn omitted_slots_are_uncapped() {
+        // only science is capped, manufacturing is left to the global pool
+        let config = location_slots("{science: 0}");
+        assert_eq!(config_builds(&config, 4, 4), (2, 2));
+        let config = character_slots("{reaction: 1}");
+        assert_eq!(config_builds(&config, 4, 4), (2, 2));
+    }
+
+    #[test]
+    fn pools_add_and_use_slots() {
+        let mut pool = IndustrySlots {
+            manufacturing: Some(2),
+            reaction: None,
+            science: Some(0),
+        };
+        let mut needed = IndustrySlots::from_slot(IndustrySlot::Manufacturing);
+        needed.add(IndustrySlots::from_slot(IndustrySlot::Reaction));
+        needed.add(IndustrySlots::from_slot(IndustrySlot::Manufacturing));
+        assert_eq!(needed.manufacturing, Some(2));
+        assert_eq!(needed.reaction, Some(1));
+        assert!(pool.can_use_slots(&needed));
+        needed.add(IndustrySlots::from_slot(IndustrySlot::Manufacturing));
+        assert!(!pool.can_use_slots(&needed));
+        assert!(!pool.use_slot(IndustrySlot::Science));
+        // an uncapped pool never runs out and stays uncapped
+        assert!(pool.use_slot(IndustrySlot::Reaction));
+        pool.add(needed);
+        assert_eq!(pool.reaction, None);
+        assert_eq!(pool.manufacturing, Some(5));
+    }
+
+    // a day's courier each way between the hub and the factory
+    fn chain_config(max_time: u64) -> String {
+        BPO_CONFIG
+            .replace(
+                "collateral_rate: 0.01}",
+                "collateral_rate: 0.01,\n         transit_time: {secs: 86400, nanos: 0}}",
+            )
+            .replace(
+                "max_time: {secs: 604800, nanos: 0}",
+                &format!("max_time: {{secs: {}, nanos: 0}}", max_time),
+            )
+    }
+
+    fn chain_durations(max_time: u64) -> (Duration, bool, Duration, bool) {
+        let db_lines = HashMap::from([
+            (
+                1,
+                vec![Line {
+                    blueprint: bpo(1101),
+                    minerals: vec![(Item::new(102), 20)],
+                    ..line(101)
+                }],
+            ),
+            (
+                2,
+                vec![Line {
+                    blueprint: bpo(1102),
+                    ..line(102)
+                }],
+            ),
+        ]);
+        let config = chain_config(max_time);
+        let snapshot = Snapshot::new(&config, db_lines);
+        let runtime = snapshot.runtime();
+        let product = runtime.production_line(1);
+        let intermediate = runtime.production_line(2);
+        (
+            product.chain_duration(),
+            product.exceeds_window(),
+            intermediate.chain_duration(),
+            intermediate.exceeds_window(),
+        )
+    }
+
+    #[test]
+    fn chain_includes_delivery_to_market() {
+        // a day for materials, 10 hours for each build, a day to market
+        let hours = |hours: u64| Duration::from_secs(hours * 3600);
+        let (product, exceeds, intermediate, _) = chain_durations(604_800);
+        assert_eq!(intermediate, hours(34));
+        assert_eq!(product, hours(68));
+        assert!(!exceeds);
+    }
+
+    #[test]
+    fn chain_just_exceeding_window() {
+        let (_, exceeds, _, intermediate_exceeds) = chain_durations(68 * 3600);
+        assert!(!exceeds);
+        let (_, exceeds, _, intermediate_exceeds_too) =
+            chain_durations(68 * 3600 - 1);
+        assert!(exceeds);
+        assert!(!intermediate_exceeds && !intermediate_exceeds_too);
+    }
+
+    #[test]
+    fn build_duration_without_sequences() {
+        // a 10 hour job restarted daily doesn't fit in 20 hours
+        let config = chain_config(20 * 3600);
+        let daily = Line {
+            sequencing: industry_db::Sequencing::Daily,
+            ..line(102)
+        };
+        let db_lines = HashMap::from([(1, vec![line(101)]), (2, vec![daily])]);
+        let snapshot = Snapshot::new(&config, db_lines);
+        let runtime = snapshot.runtime();
+        let pl = runtime.production_line(2);
+        assert_eq!(pl.num_sequences(), 0);
+        assert_eq!(pl.build_duration(), Duration::ZERO);
+    }
+
+    const VELDSPAR: u32 = 1230;
+
+    // 100 veldspar reprocess into 400 tritanium at the factory
+    fn ore_snapshot() -> Snapshot {
+        let db_lines =
+            HashMap::from([(1, vec![line(101)]), (2, vec![line(102)])]);
+        let mut snapshot = Snapshot::new(BPO_CONFIG, db_lines)
+            .orders(1, TRITANIUM, 5.0, 1_000_000.0)
+            .orders(1, VELDSPAR, 10.0, 1_000_000.0);
+        let veldspar = Item::new(VELDSPAR);
+        snapshot.db_reprocessing.insert(
+            2,
+            HashMap::from([(
+                veldspar,
+                industry_db::Reprocessing {
+                    item: veldspar,
+                    portion: 100,
+                    materials: vec![(Item::new(TRITANIUM), 400)],
+                    tax: 0.0,
+                    ore: true,
+                },
+            )]),
+        );
+        snapshot
+    }
+
+    #[test]
+    fn ore_mix_priced_in_whole_portions() {
+        let snapshot = ore_snapshot();
+        let runtime = snapshot.runtime();
+        let pl = runtime.production_line(2);
+        // 10,100 tritanium is 2,525 veldspar, bought as 2,600
+        // at 10 ISK, 1% collateral and 1 ISK a unit for its 0.01 m3
+        let minerals = [(Item::new(TRITANIUM), 10_100.0)];
+        let expected = 2_600.0 * 11.1;
+        let overlay = ReservationOverlay::default();
+        let cost = pl
+            .reserve_ore_mix(Some(&overlay), &minerals, &snapshot.type_volumes)
+            .unwrap();
+        assert!((cost - expected).abs() < 1e-6, "{}", cost);
+        // the committed purchase costs what the check said it would
+        let cost = pl
+            .reserve_ore_mix(None, &minerals, &snapshot.type_volumes)
+            .unwrap();
+        assert!((cost - expected).abs() < 1e-6, "{}", cost);
+        let market = &pl.import_src_market_pipes().next().unwrap().src();
+        let orders = &market.unwrap_market().orders;
+        assert_eq!(orders.num_purchased(None, &VELDSPAR), 2_600);
+        assert_eq!(orders.num_purchased(None, &TRITANIUM), 0);
+    }
+
+    #[test]
+    fn ore_portions_take_the_next_order() {
+        // 150 veldspar at 10 ISK, then more at 20 ISK
+        let mut snapshot = ore_snapshot();
+        snapshot.api.market_orders.get_mut(&1).unwrap().insert(
+            VELDSPAR,
+            api_data::TypeMarketOrders {
+                orders: vec![
+                    api_data::MarketOrder {
+                        price: 10.0,
+                        volume: 150.0,
+                    },
+                    api_data::MarketOrder {
+                        price: 20.0,
+                        volume: 1_000.0,
+                    },
+                ],
+                total: 1_150.0,
+            },
+        );
+        let runtime = snapshot.runtime();
+        let pl = runtime.production_line(2);
+        let tritanium = Item::new(TRITANIUM);
+        // 700 tritanium is 175 veldspar, bought as 200 across both orders
+        let overlay = ReservationOverlay::default();
+        let cost = pl
+            .reserve_from_market(
+                &overlay,
+                &tritanium,
+                700.0,
+                &snapshot.type_volumes,
+            )
+            .unwrap();
+        let expected = 150.0 * 11.1 + 50.0 * 21.2;
+        assert!((cost - expected).abs() < 1e-6, "{}", cost);
+        pl.permanent_reserve_from_market_and_deliver(
+            &tritanium,
+            700,
+            &snapshot.type_volumes,
+        );
+        let market = &pl.import_src_market_pipes().next().unwrap().src();
+        let orders = &market.unwrap_market().orders;
+        assert_eq!(orders.num_purchased(None, &VELDSPAR), 200);
+        assert_eq!(orders.num_purchased(None, &TRITANIUM), 0);
+    }
+
+    #[test]
+    fn plan_price_from_ore_bought() {
+        let snapshot = ore_snapshot();
+        let runtime = snapshot.runtime();
+        let pl = runtime.production_line(2);
+        let tritanium = Item::new(TRITANIUM);
+        // nothing bought yet, the next unit on the market
+        assert_eq!(pl.plan_price(&tritanium, &snapshot.type_volumes), 5.0);
+        let minerals = [(tritanium, 10_100.0)];
+        pl.reserve_ore_mix(None, &minerals, &snapshot.type_volumes)
+            .unwrap();
+        // 2,600 veldspar at 11.1 ISK delivered yield 10,400 tritanium
+        let price = pl.plan_price(&tritanium, &snapshot.type_volumes);
+        assert!(
+            (price - 2_600.0 * 11.1 / 10_400.0).abs() < 1e-9,
+            "{}",
+            price
+        );
+        // tritanium bought outright, with 1% collateral and its 0.01 m3
+        let market = pl.import_src_market_pipes().next().unwrap().src();
+        market
+            .unwrap_market()
+            .orders
+            .reserve_i64(None, &TRITANIUM, 10_400);
+        let price = pl.plan_price(&tritanium, &snapshot.type_volumes);
+        let direct = 10_400.0 * (5.0 * 1.01 + 1.0);
+        let expected = (2_600.0 * 11.1 + direct) / 20_800.0;
+        assert!((price - expected).abs() < 1e-9, "{}", price);
+    }
+
+    #[test]
+    fn ore_mix_levels_capped() {
+        let type_orders = api_data::TypeMarketOrders {
+            orders: (1..=10)
+                .map(|price| api_data::MarketOrder {
+                    price: price as f64,
+                    volume: 1.0,
+                })
+                .collect(),
+            total: 10.0,
+        };
+        let api_orders = HashMap::from([(VELDSPAR, type_orders)]);
+        let orders = LocationMarketOrders::new(1, Some(&api_orders));
+        let veldspar = Item::new(VELDSPAR);
+        let levels = OreMixOrder::levels(&orders, None, &veldspar, 10.0);
+        assert_eq!(levels.len(), 8);
+        assert_eq!(levels[6].price, 7.0);
+        // the rest are one level, at the highest price
+        assert_eq!(levels[7].price, 10.0);
+        assert_eq!(levels[7].volume, 3.0);
+        let levels = OreMixOrder::levels(&orders, None, &veldspar, 3.0);
+        assert_eq!(levels.len(), 3);
+    }
+}
@@ -1,14 +1,20 @@
+use crate::config::Objective;
 use std::ops::{Add, AddAssign, Sub};
 
 #[derive(Debug, Clone)]
 pub struct Profit {
     pub cost: f64,
     pub revenue: f64,
+    pub materials: f64, // part of the cost, market materials delivered
 }
 
 impl Profit {
     pub fn new(cost: f64, revenue: f64) -> Self {
-        Self { cost, revenue }
+        Self {
+            cost,
+            revenue,
+            materials: 0.0,
+        }
     }
 
     pub fn margin(&self) -> f64 {
@@ -22,12 +28,39 @@ impl Profit {
     pub fn profit(&self) -> f64 {
         self.revenue - self.cost
     }
+
+    // None if no capital is tied up
+    pub fn return_on_capital(&self) -> Option<f64> {
+        match self.cost > 0.0 {
+            true => Some(self.profit() / self.cost),
+            false => None,
+        }
+    }
+
+    // higher is better, None if the objective rules it out
+    pub fn score(&self, objective: Objective, slot_hours: f64) -> Option<f64> {
+        match objective {
+            Objective::Profit => Some(self.profit()),
+            Objective::ProfitPerSlotHour => match slot_hours > 0.0 {
+                true => Some(self.profit() / slot_hours),
+                false => None,
+            },
+            Objective::ReturnOnCapital => self.return_on_capital(),
+            Objective::ProfitWithMinRoi(min_roi) => {
+                match self.return_on_capital()? >= min_roi {
+                    true => Some(self.profit()),
+                    false => None,
+                }
+            }
+        }
+    }
 }
 
 impl AddAssign for Profit {
     fn add_assign(&mut self, other: Self) {
         self.cost += other.cost;
         self.revenue += other.revenue;
+        self.materials += other.materials;
     }
 }
 
@@ -38,6 +71,46 @@ impl Add for Profit {
         Self {
             cost: self.cost + other.cost,
             revenue: self.revenue + other.revenue,
+            materials: self.materials + other.materials,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn score_by_objective() {
+        // 100M spent for 125M
+        let profit = Profit::new(100e6, 125e6);
+        let score = |objective| profit.score(objective, 50.0);
+        assert_eq!(score(Objective::Profit), Some(25e6));
+        assert_eq!(score(Objective::ProfitPerSlotHour), Some(500e3));
+        assert_eq!(score(Objective::ReturnOnCapital), Some(0.25));
+        assert_eq!(score(Objective::ProfitWithMinRoi(0.25)), Some(25e6));
+        assert_eq!(score(Objective::ProfitWithMinRoi(0.3)), None);
+    }
+
+    #[test]
+    fn score_losses() {
+        let profit = Profit::new(100e6, 80e6);
+        let score = |objective| profit.score(objective, 50.0);
+        assert_eq!(score(Objective::Profit), Some(-20e6));
+        assert_eq!(score(Objective::ReturnOnCapital), Some(-0.2));
+        assert_eq!(score(Objective::ProfitWithMinRoi(0.0)), None);
+    }
+
+    #[test]
+    fn score_without_capital() {
+        // nothing to divide by, so no return to rank
+        let profit = Profit::new(0.0, 10e6);
+        assert_eq!(profit.return_on_capital(), None);
+        assert_eq!(profit.score(Objective::ReturnOnCapital, 50.0), None);
+        let objective = Objective::ProfitWithMinRoi(0.1);
+        assert_eq!(profit.score(objective, 50.0), None);
+        assert_eq!(profit.score(Objective::Profit, 50.0), Some(10e6));
+        // nor any slot time to divide by
+        assert_eq!(profit.score(Objective::ProfitPerSlotHour, 0.0), None);
+    }
+}
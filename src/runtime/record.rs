@@ -0,0 +1,241 @@
+use super::*;
+
+// what a plan expects, in owned data so it outlives the runtime
+pub struct PlanRecord {
+    pub profit: f64,
+    pub cost: f64,
+    pub output: String, // the plan's output.json
+    pub builds: Vec<PlanBuild>,
+    pub purchases: Vec<PlanPurchase>,
+    pub deliveries: Vec<PlanDelivery>,
+}
+
+pub struct PlanBuild {
+    pub production_line_id: u32,
+    pub location_id: u64,
+    pub market_location_id: Option<u64>, // where products are sold
+    pub product: Item,
+    pub product_name: String,
+    pub builds: i64,
+    pub runs: i64,              // over all builds
+    pub installation_cost: f64, // over all builds
+    pub profit: Option<f64>,    // over all builds, None for intermediates
+    pub sell_price: Option<f64>,
+    pub revenue_per_price: Option<f64>, // per build
+    pub materials: Vec<PlanMaterial>,   // bought from markets, per build
+}
+
+pub struct PlanMaterial {
+    pub item: Item,
+    pub name: String,
+    pub quantity: f64,
+    pub price: f64, // paid on average, before delivery
+}
+
+pub struct PlanPurchase {
+    pub location_id: u64,
+    pub type_id: u32,
+    pub quantity: i64,
+    pub cost: f64,
+}
+
+pub struct PlanDelivery {
+    pub src_location_id: u64,
+    pub dst_location_id: u64,
+    pub service_name: String,
+    pub item: Item,
+    pub quantity: i64,
+}
+
+impl PlanRecord {
+    pub fn new(
+        locations: &[Arc<Location>],
+        output: &OutputLocations,
+        type_names: &HashMap<Item, String>,
+        type_volumes: &HashMap<Item, f64>,
+    ) -> serde_json::Result<Self> {
+        let mut record = Self {
+            profit: 0.0,
+            cost: output.cost(),
+            output: output.to_json()?,
+            builds: Vec::new(),
+            purchases: Vec::new(),
+            deliveries: Vec::new(),
+        };
+        for location in locations {
+            record.add_builds(location, type_names, type_volumes);
+            record.add_purchases(location);
+            record.add_deliveries(location);
+        }
+        Ok(record)
+    }
+
+    fn add_builds(
+        &mut self,
+        location: &Location,
+        type_names: &HashMap<Item, String>,
+        type_volumes: &HashMap<Item, f64>,
+    ) {
+        for production_line in location.production_lines().iter_all() {
+            let num_builds = production_line.num_builds();
+            if num_builds == 0 {
+                continue;
+            }
+            let profits = production_line.profits.read().unwrap();
+            let profit = match profits.is_empty() {
+                true => None,
+                false => Some(profits.iter().map(|p| p.profit()).sum()),
+            };
+            self.profit += profit.unwrap_or(0.0);
+            let sensitivity =
+                PriceSensitivity::new(production_line, type_volumes);
+            let materials = match &sensitivity {
+                Some(sensitivity) => sensitivity
+                    .materials
+                    .iter()
+                    .map(|(item, quantity, price)| PlanMaterial {
+                        item: *item,
+                        name: type_names[item].clone(),
+                        quantity: *quantity,
+                        // ore bought instead is priced by what it yields
+                        price: production_line
+                            .purchase_price(item)
+                            .unwrap_or(*price),
+                    })
+                    .collect(),
+                None => Vec::new(),
+            };
+            self.builds.push(PlanBuild {
+                production_line_id: production_line.id(),
+                location_id: location.id(),
+                market_location_id: match production_line.export_kind() {
+                    config::ProductionLineExportKind::Product => {
+                        Some(production_line.export_pipe().dst().id())
+                    }
+                    config::ProductionLineExportKind::Intermediate => None,
+                },
+                product: production_line.product(),
+                product_name: type_names[&production_line.product()].clone(),
+                builds: num_builds,
+                runs: production_line.runs() * num_builds,
+                installation_cost: production_line.installation_cost().total()
+                    * num_builds as f64,
+                profit,
+                sell_price: sensitivity.as_ref().map(|s| s.sell_price),
+                revenue_per_price: sensitivity
+                    .as_ref()
+                    .map(|s| s.revenue_per_price),
+                materials,
+            });
+        }
+    }
+
+    fn add_purchases(&mut self, location: &Location) {
+        let location_market = match &location.market {
+            Some(location_market) => location_market,
+            None => return,
+        };
+        for (&type_id, orders) in location_market.orders.inner.iter() {
+            let mut cost = 0.0;
+            let (quantity, _) =
+                orders.num_purchased_with_stats(None, &mut cost);
+            if quantity > 0 {
+                self.purchases.push(PlanPurchase {
+                    location_id: location.id(),
+                    type_id,
+                    quantity,
+                    cost,
+                });
+            }
+        }
+    }
+
+    fn add_deliveries(&mut self, location: &Location) {
+        for route in location.routes().iter_transit(location.id()) {
+            for delivery_pipe in route.pipes().iter() {
+                for (item, quantity) in delivery_pipe.deliveries().iter() {
+                    // pipes sharing the route are one delivery of the type
+                    match self.deliveries.iter_mut().find(|delivery| {
+                        delivery.src_location_id == location.id()
+                            && delivery.dst_location_id == route.dst.id()
+                            && delivery.service_name == route.service_name()
+                            && delivery.item.type_id == item.type_id
+                    }) {
+                        Some(delivery) => delivery.quantity += quantity,
+                        None => self.deliveries.push(PlanDelivery {
+                            src_location_id: location.id(),
+                            dst_location_id: route.dst.id(),
+                            service_name: route.service_name().to_string(),
+                            item,
+                            quantity,
+                        }),
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::tests::{line, Snapshot, TRITANIUM};
+
+    // two pipes from the hub share its only route to the factory
+    const CONFIG: &str = "
+locations:
+  - id: 1
+    name: hub
+    system_id: 10
+    market: {sales_tax: 0.036, brokers_fee: 0.015}
+    routes:
+      2: {id: 1, service_name: courier, m3_rate: 100.0, collateral_rate: 0.01}
+    pipes:
+      1: [1]
+      3: [1]
+  - id: 2
+    name: factory
+    system_id: 20
+    production:
+      tax: {manufacturing: 0.01, invention: 0.01, reaction: 0.01, copy: 0.01}
+      rigs: [null, null, null]
+      structure_type_id: 35825
+      production_lines:
+        - {id: 1, blueprint: {type_id: 1101, runs: -1}, product: {type_id: 101},
+           kind: Manufacturing, export_kind: Product, export_pipe_id: 2,
+           import_src_market_pipe_ids: [1, 3], parallel: 1}
+    routes:
+      1: {id: 2, service_name: courier, m3_rate: 100.0, collateral_rate: 0.01}
+    pipes:
+      2: [2]
+skills: {}
+slots: {manufacturing: 1, reaction: 0, science: 0}
+max_time: {secs: 604800, nanos: 0}
+daily_flex_time: {secs: 3600, nanos: 0}
+min_profit: 0.0
+min_margin: 1.0
+";
+
+    #[test]
+    fn deliveries_merged_across_pipes() {
+        let snapshot =
+            Snapshot::new(CONFIG, HashMap::from([(1, vec![line(101)])]));
+        let runtime = snapshot.runtime();
+        let hub = runtime.locations.iter().find(|l| l.id() == 1).unwrap();
+        let tritanium = Item::new(TRITANIUM);
+        for pipe in hub.export_pipes.read().unwrap().iter() {
+            pipe.deliver(tritanium, 1_000);
+        }
+        let record = runtime
+            .record(&snapshot.type_names, &snapshot.type_volumes)
+            .unwrap();
+        assert_eq!(record.deliveries.len(), 1);
+        let delivery = &record.deliveries[0];
+        assert_eq!(
+            (delivery.src_location_id, delivery.dst_location_id),
+            (1, 2)
+        );
+        assert_eq!(delivery.item, tritanium);
+        assert_eq!(delivery.quantity, 2_000);
+    }
+}
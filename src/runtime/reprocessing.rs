@@ -0,0 +1,66 @@
+use super::*;
+
+// an item that can be reprocessed at a location, with its tax priced in
+pub struct ReprocessingYield<'db> {
+    inner: &'db industry_db::Reprocessing,
+    tax_per_portion: f64,
+}
+
+impl<'db> ReprocessingYield<'db> {
+    pub fn new(
+        inner: &'db industry_db::Reprocessing,
+        adjusted_prices: &HashMap<u32, f64>,
+    ) -> Self {
+        // tax is charged on the estimated value of the materials
+        let estimated_value = inner
+            .materials
+            .iter()
+            .map(|(item, quantity)| {
+                adjusted_prices.get(&item.type_id).copied().unwrap_or(0.0)
+                    * *quantity as f64
+            })
+            .sum::<f64>();
+        Self {
+            inner,
+            tax_per_portion: estimated_value * inner.tax,
+        }
+    }
+
+    pub fn item(&self) -> Item {
+        self.inner.item
+    }
+
+    pub fn portion(&self) -> i64 {
+        self.inner.portion
+    }
+
+    pub fn is_ore(&self) -> bool {
+        self.inner.ore
+    }
+
+    // quantity of the material from a single unit
+    pub fn material_per_unit(&self, material: &Item) -> Option<f64> {
+        self.inner
+            .materials
+            .iter()
+            .find(|(item, _)| item == material)
+            .map(|(_, quantity)| *quantity as f64 / self.portion() as f64)
+    }
+
+    pub fn tax_per_unit(&self) -> f64 {
+        self.tax_per_portion / self.portion() as f64
+    }
+
+    // value of the materials from a single unit, before tax
+    pub fn value_per_unit(&self, price: impl Fn(&Item) -> Option<f64>) -> f64 {
+        let value = self
+            .inner
+            .materials
+            .iter()
+            .map(|(item, quantity)| {
+                price(item).unwrap_or(0.0) * *quantity as f64
+            })
+            .sum::<f64>();
+        value / self.portion() as f64
+    }
+}
@@ -0,0 +1,227 @@
+use super::*;
+use crate::config::ManufacturingKind;
+
+// whether researching a blueprint pays for itself over the planned builds
+pub struct ResearchRecommendation<'pl, 'cfg, 'db, 'api> {
+    pub production_line: &'pl ProductionLine<'cfg, 'db, 'api>,
+    pub planned_runs: i64, // manufacturing runs using the blueprint
+    pub material_savings: f64, // from ME research
+    pub time_savings: Duration, // from TE research
+    pub time_savings_value: f64, // profit of the saved slot time
+}
+
+impl<'pl, 'cfg, 'db, 'api> ResearchRecommendation<'pl, 'cfg, 'db, 'api> {
+    pub fn new(
+        production_line: &'pl ProductionLine<'cfg, 'db, 'api>,
+        locations: &[Arc<Location<'cfg, 'db, 'api>>],
+        type_volumes: &HashMap<Item, f64>,
+    ) -> Self {
+        let (from, to) =
+            (production_line.blueprint(), production_line.product());
+        // portion of TE affected time saved
+        let te_savings =
+            1.0 - (1.0 - to.te as f64 / 100.0) / (1.0 - from.te as f64 / 100.0);
+
+        let mut planned_runs = 0;
+        let mut material_savings = 0.0;
+        let mut time_savings = Duration::ZERO;
+        let mut time_savings_value = 0.0;
+        for location in locations {
+            for consumer in location.production_lines().iter_all() {
+                if consumer.job_kind() != ManufacturingKind::Manufacturing
+                    || consumer.blueprint() != from
+                    || consumer.num_builds() == 0
+                {
+                    continue;
+                }
+                planned_runs += consumer.num_builds() * consumer.runs();
+                // priced at the next available orders, without delivery
+                material_savings += consumer
+                    .me_savings(from.me, to.me)
+                    .into_iter()
+                    .map(|(_, quantity, price)| quantity * price)
+                    .sum::<f64>()
+                    * consumer.num_builds() as f64;
+                let build_duration =
+                    consumer.duration() * consumer.num_sequences() as u32;
+                let saved = build_duration
+                    .mul_f64(consumer.num_builds() as f64 * te_savings);
+                time_savings += saved;
+                // the saved slot time is worth what the consumer makes in it
+                let profit = consumer
+                    .profit(None, None, type_volumes)
+                    .map(|profit| profit.profit().max(0.0))
+                    .unwrap_or(0.0);
+                time_savings_value +=
+                    profit * saved.as_secs_f64() / build_duration.as_secs_f64();
+            }
+        }
+
+        Self {
+            production_line,
+            planned_runs,
+            material_savings,
+            time_savings,
+            time_savings_value,
+        }
+    }
+
+    pub fn installation_cost(&self) -> f64 {
+        self.production_line.installation_cost().total()
+    }
+
+    // None if no planned builds use the blueprint
+    pub fn pays_off(&self) -> Option<bool> {
+        match self.planned_runs {
+            0 => None,
+            _ => Some(
+                self.material_savings + self.time_savings_value
+                    > self.installation_cost(),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::industry_db::Line;
+    use crate::runtime::tests::{line, Snapshot, TRITANIUM};
+
+    const MORPHITE: u32 = 11399;
+
+    // a BPO built into 101, researched by line 2 and line 3
+    const CONFIG: &str = "
+locations:
+  - id: 1
+    name: hub
+    system_id: 10
+    market: {sales_tax: 0.036, brokers_fee: 0.015}
+    routes:
+      2: {id: 1, service_name: courier, m3_rate: 100.0, collateral_rate: 0.01}
+  - id: 2
+    name: factory
+    system_id: 20
+    production:
+      tax: {manufacturing: 0.01, invention: 0.01, reaction: 0.01, copy: 0.01}
+      rigs: [null, null, null]
+      structure_type_id: 35825
+      production_lines:
+        - {id: 1, blueprint: {type_id: 1101, runs: -1}, product: {type_id: 101},
+           kind: Manufacturing, export_kind: Product, export_pipe_id: 2,
+           import_src_market_pipe_ids: [1], parallel: 1}
+        - {id: 2, blueprint: {type_id: 1101, runs: -1},
+           product: {type_id: 1101, runs: -1, me: 10},
+           kind: ResearchMe, export_kind: Intermediate, export_pipe_id: 2,
+           parallel: 1}
+        - {id: 3, blueprint: {type_id: 1101, runs: -1},
+           product: {type_id: 1101, runs: -1, te: 20},
+           kind: ResearchTe, export_kind: Intermediate, export_pipe_id: 2,
+           parallel: 1}
+    routes:
+      1: {id: 2, service_name: courier, m3_rate: 100.0, collateral_rate: 0.01}
+    pipes:
+      1: [1]
+      2: [2]
+skills: {}
+slots: {manufacturing: 1, reaction: 0, science: 0}
+max_time: {secs: 604800, nanos: 0}
+daily_flex_time: {secs: 3600, nanos: 0}
+min_profit: 0.0
+min_margin: 1.0
+";
+
+    fn research_line(product: Item) -> Line {
+        Line {
+            blueprint: Item::new_blueprint(1101, -1, 0, 0),
+            product,
+            minerals: Vec::new(),
+            base_minerals: Vec::new(),
+            eiv_multiplier: 0.02,
+            runs: 10,
+            ..line(1101)
+        }
+    }
+
+    // 10 runs of 1,000 tritanium and 1 morphite, morphite is always 10
+    fn snapshot() -> Snapshot {
+        let manufacturing = Line {
+            blueprint: Item::new_blueprint(1101, -1, 0, 0),
+            minerals: vec![
+                (Item::new(TRITANIUM), 10_000),
+                (Item::new(MORPHITE), 10),
+            ],
+            base_minerals: vec![
+                (Item::new(TRITANIUM), 1_000),
+                (Item::new(MORPHITE), 1),
+            ],
+            ..line(101)
+        };
+        let db_lines = HashMap::from([
+            (1, vec![manufacturing]),
+            (2, vec![research_line(Item::new_blueprint(1101, -1, 10, 0))]),
+            (3, vec![research_line(Item::new_blueprint(1101, -1, 0, 20))]),
+        ]);
+        Snapshot::new(CONFIG, db_lines)
+            .orders(1, TRITANIUM, 5.0, 1_000_000.0)
+            .orders(1, MORPHITE, 1_000.0, 1_000.0)
+            .orders(1, 101, 20_000.0, 1_000.0)
+            .assets(2, Item::new_blueprint(1101, -1, 0, 0), 1)
+    }
+
+    #[test]
+    fn me_savings_only_materials_me_reduces() {
+        let snapshot = snapshot();
+        let mut runtime = snapshot.runtime();
+        runtime.build();
+        let consumer = runtime.production_line(1);
+        assert!(consumer.num_builds() > 0);
+        let research = runtime.production_line(2);
+        let recommendation = ResearchRecommendation::new(
+            &research,
+            &runtime.locations,
+            &snapshot.type_volumes,
+        );
+        assert_eq!(recommendation.planned_runs, consumer.num_builds() * 10);
+        // 1,000 tritanium saved per build, morphite stays at 1 per run
+        let expected = 1_000.0 * 5.0 * consumer.num_builds() as f64;
+        assert!(
+            (recommendation.material_savings - expected).abs() < 1e-6,
+            "{}",
+            recommendation.material_savings
+        );
+        assert_eq!(recommendation.time_savings, Duration::ZERO);
+        assert_eq!(recommendation.pays_off(), Some(true));
+    }
+
+    #[test]
+    fn te_research_pays_off_in_slot_time() {
+        let snapshot = snapshot();
+        let mut runtime = snapshot.runtime();
+        runtime.build();
+        let consumer = runtime.production_line(1);
+        let research = runtime.production_line(3);
+        let recommendation = ResearchRecommendation::new(
+            &research,
+            &runtime.locations,
+            &snapshot.type_volumes,
+        );
+        assert_eq!(recommendation.material_savings, 0.0);
+        // 20% of 10 hours per build
+        assert_eq!(
+            recommendation.time_savings,
+            Duration::from_secs(7_200 * consumer.num_builds() as u64)
+        );
+        let profit = consumer
+            .profit(None, None, &snapshot.type_volumes)
+            .unwrap()
+            .profit();
+        let expected = 0.2 * profit * consumer.num_builds() as f64;
+        assert!(
+            (recommendation.time_savings_value - expected).abs() < 1e-6,
+            "{}",
+            recommendation.time_savings_value
+        );
+        assert_eq!(recommendation.pays_off(), Some(true));
+    }
+}
@@ -0,0 +1,375 @@
+use super::*;
+use crate::config::IndustrySlot;
+
+// a single job, one sequence of a build, laid out in the planning window
+pub struct ScheduledJob<'cfg, 'db, 'api> {
+    pub production_line: Arc<ProductionLine<'cfg, 'db, 'api>>,
+    pub build: i64,
+    pub sequence: i64,
+    pub location_slot: Option<usize>, // none if the location is uncapped
+    pub character_slot: Option<usize>, // none if the character is uncapped
+    pub start: Duration,
+    pub end: Duration,
+    pub late: bool, // ends after the planning window
+}
+
+// when each slot becomes free, per slot kind
+struct SlotLanes {
+    manufacturing: Vec<Duration>,
+    reaction: Vec<Duration>,
+    science: Vec<Duration>,
+}
+
+impl SlotLanes {
+    // uncapped kinds get no lanes and never hold a job back
+    fn new(slots: &IndustrySlots) -> Self {
+        let lanes = |slot| vec![Duration::ZERO; slots.get(slot).unwrap_or(0)];
+        Self {
+            manufacturing: lanes(IndustrySlot::Manufacturing),
+            reaction: lanes(IndustrySlot::Reaction),
+            science: lanes(IndustrySlot::Science),
+        }
+    }
+
+    fn lanes(&mut self, slot: IndustrySlot) -> &mut Vec<Duration> {
+        match slot {
+            IndustrySlot::Manufacturing => &mut self.manufacturing,
+            IndustrySlot::Reaction => &mut self.reaction,
+            IndustrySlot::Science => &mut self.science,
+        }
+    }
+
+    // the slot that frees up first, and when
+    fn earliest(&mut self, slot: IndustrySlot) -> Option<(usize, Duration)> {
+        self.lanes(slot)
+            .iter()
+            .copied()
+            .enumerate()
+            .min_by_key(|(_, free)| *free)
+    }
+
+    fn occupy(&mut self, slot: IndustrySlot, lane: usize, until: Duration) {
+        self.lanes(slot)[lane] = until;
+    }
+}
+
+// lays out every build, intermediate lines first, each in the first slot
+// that's free in the global, structure and character pools
+// the upstream jobs feeding each build are assumed to be spread evenly
+// and their products only usable once delivered
+pub fn schedule_builds<'cfg, 'db, 'api>(
+    locations: &[Arc<Location<'cfg, 'db, 'api>>],
+    slots: &IndustrySlots,
+    characters: &HashMap<String, config::Character>,
+    max_time: Duration,
+) -> Vec<ScheduledJob<'cfg, 'db, 'api>> {
+    let mut order = Vec::new();
+    for location in locations.iter() {
+        for production_line in location.production_lines().iter_all() {
+            visit(production_line, &mut order);
+        }
+    }
+
+    let mut global_lanes = SlotLanes::new(slots);
+    let mut location_lanes = HashMap::new();
+    let mut character_lanes = HashMap::new();
+    let mut finishes = HashMap::<u32, Vec<Duration>>::new();
+    let mut jobs = Vec::new();
+
+    for production_line in order {
+        let num_builds = production_line.num_builds();
+        let slot = production_line.slot_kind();
+        let location_id = production_line.location().id();
+        let capped_location = production_line
+            .location()
+            .production
+            .as_ref()
+            .and_then(|p| p.inner.slots.as_ref());
+        let capped_character = production_line.character().and_then(|name| {
+            characters
+                .get(name)
+                .and_then(|c| c.slots.as_ref())
+                .map(|slots| (name, slots))
+        });
+        let upstream = production_line
+            .intermediate_production_lines()
+            .into_iter()
+            .filter_map(|pl| {
+                let transit_time = pl.export_pipe().transit_time();
+                finishes.get(&pl.id()).map(|finishes| {
+                    let mut arrivals = finishes
+                        .iter()
+                        .map(|finish| *finish + transit_time)
+                        .collect::<Vec<_>>();
+                    arrivals.sort();
+                    arrivals
+                })
+            })
+            .filter(|arrivals| !arrivals.is_empty())
+            .collect::<Vec<_>>();
+        let market_arrival = production_line.import_market_transit_time();
+
+        for build in 0..num_builds {
+            // the share of upstream deliveries this build waits for
+            let ready = upstream
+                .iter()
+                .map(|arrivals| {
+                    let needed = ((build + 1) as usize * arrivals.len())
+                        .div_ceil(num_builds as usize);
+                    arrivals[needed - 1]
+                })
+                .max()
+                .unwrap_or(Duration::ZERO)
+                .max(market_arrival);
+
+            let (lane, free) =
+                global_lanes.earliest(slot).unwrap_or((0, Duration::ZERO));
+            let mut start = ready.max(free);
+            let mut location_lane = None;
+            if let Some(cap) = capped_location {
+                let lanes = location_lanes
+                    .entry(location_id)
+                    .or_insert_with(|| SlotLanes::new(cap));
+                if let Some((lane, free)) = lanes.earliest(slot) {
+                    start = start.max(free);
+                    location_lane = Some(lane);
+                }
+            }
+            let mut character_lane = None;
+            if let Some((name, cap)) = capped_character {
+                let lanes = character_lanes
+                    .entry(name)
+                    .or_insert_with(|| SlotLanes::new(cap));
+                if let Some((lane, free)) = lanes.earliest(slot) {
+                    start = start.max(free);
+                    character_lane = Some(lane);
+                }
+            }
+
+            let end = start + production_line.build_duration();
+            if !global_lanes.lanes(slot).is_empty() {
+                global_lanes.occupy(slot, lane, end);
+            }
+            if let Some(lane) = location_lane {
+                location_lanes
+                    .get_mut(&location_id)
+                    .unwrap()
+                    .occupy(slot, lane, end);
+            }
+            if let (Some(lane), Some((name, _))) =
+                (character_lane, capped_character)
+            {
+                character_lanes
+                    .get_mut(name)
+                    .unwrap()
+                    .occupy(slot, lane, end);
+            }
+
+            let sequence_interval =
+                production_line.duration() + production_line.extra_duration();
+            for sequence in 0..production_line.num_sequences() {
+                let sequence_start =
+                    start + sequence_interval * sequence as u32;
+                jobs.push(ScheduledJob {
+                    production_line: production_line.clone(),
+                    build,
+                    sequence,
+                    location_slot: location_lane,
+                    character_slot: character_lane,
+                    start: sequence_start,
+                    end: sequence_start + production_line.duration(),
+                    late: sequence_start + production_line.duration()
+                        > max_time,
+                });
+            }
+            finishes
+                .entry(production_line.id())
+                .or_insert_with(Vec::new)
+                .push(end);
+        }
+    }
+
+    jobs.sort_by_key(|job| (job.start, job.production_line.id(), job.build));
+    jobs
+}
+
+// depth first, so intermediate lines come before their consumers
+fn visit<'cfg, 'db, 'api>(
+    production_line: &Arc<ProductionLine<'cfg, 'db, 'api>>,
+    order: &mut Vec<Arc<ProductionLine<'cfg, 'db, 'api>>>,
+) {
+    if production_line.num_builds() == 0
+        || order.iter().any(|pl| pl.id() == production_line.id())
+    {
+        return;
+    }
+    for sub_production_line in production_line.intermediate_production_lines() {
+        visit(&sub_production_line, order);
+    }
+    order.push(production_line.clone());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::industry_db::Line;
+    use crate::runtime::tests::{line, Snapshot, TRITANIUM};
+
+    // line 1 needs 20 of 102 per build, each delivered by a build of line 2
+    // both installed by alice, who has four manufacturing slots
+    const CONFIG: &str = "
+locations:
+  - id: 1
+    name: hub
+    system_id: 10
+    market: {sales_tax: 0.036, brokers_fee: 0.015}
+    routes:
+      2: {id: 1, service_name: courier, m3_rate: 100.0, collateral_rate: 0.01}
+  - id: 2
+    name: factory
+    system_id: 20
+    production:
+      tax: {manufacturing: 0.01, invention: 0.01, reaction: 0.01, copy: 0.01}
+      rigs: [null, null, null]
+      structure_type_id: 35825
+      production_lines:
+        - {id: 1, blueprint: {type_id: 1101, runs: -1}, product: {type_id: 101},
+           kind: Manufacturing, export_kind: Product, export_pipe_id: 2,
+           import_src_market_pipe_ids: [1],
+           import_src_production_line_ids: {102: 2}, parallel: 2,
+           character: alice}
+        - {id: 2, blueprint: {type_id: 1102, runs: -1}, product: {type_id: 102},
+           kind: Manufacturing, export_kind: Intermediate, export_pipe_id: 3,
+           import_src_market_pipe_ids: [1], parallel: 4, character: alice}
+    routes:
+      1: {id: 2, service_name: courier, m3_rate: 100.0, collateral_rate: 0.01}
+      2: {id: 3, service_name: hangar, m3_rate: 0.0, collateral_rate: 0.0}
+    pipes:
+      1: [1]
+      2: [2]
+      3: [3]
+skills: {}
+characters:
+  alice: {slots: {manufacturing: 4}}
+slots: {manufacturing: 6, reaction: 0, science: 0}
+max_time: {secs: 604800, nanos: 0}
+daily_flex_time: {secs: 3600, nanos: 0}
+min_profit: 0.0
+min_margin: 1.0
+";
+
+    fn bpo(type_id: u32) -> Item {
+        Item::new_blueprint(type_id, -1, 0, 0)
+    }
+
+    fn snapshot() -> Snapshot {
+        let db_lines = HashMap::from([
+            (
+                1,
+                vec![Line {
+                    blueprint: bpo(1101),
+                    minerals: vec![(Item::new(102), 20)],
+                    base_minerals: vec![(Item::new(102), 2)],
+                    ..line(101)
+                }],
+            ),
+            (
+                2,
+                vec![Line {
+                    blueprint: bpo(1102),
+                    ..line(102)
+                }],
+            ),
+        ]);
+        Snapshot::new(CONFIG, db_lines)
+            .orders(1, TRITANIUM, 5.0, 1_000_000.0)
+            .orders(1, 101, 30_000.0, 1_000.0)
+            .assets(2, bpo(1101), 2)
+            .assets(2, bpo(1102), 4)
+    }
+
+    fn scheduled<'cfg, 'db, 'api>(
+        runtime: &RuntimeData<'cfg, 'db, 'api>,
+        max_time: Duration,
+    ) -> Vec<ScheduledJob<'cfg, 'db, 'api>> {
+        schedule_builds(
+            &runtime.locations,
+            runtime.cfg_slots,
+            runtime.cfg_characters,
+            max_time,
+        )
+    }
+
+    #[test]
+    fn intermediates_scheduled_first() {
+        let snapshot = snapshot();
+        let mut runtime = snapshot.runtime();
+        runtime.build();
+        let jobs = scheduled(&runtime, runtime.max_time);
+        let ids = jobs
+            .iter()
+            .map(|job| job.production_line.id())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec![2, 2, 1, 1]);
+        // each build of line 1 waits for a build of line 2, 10 hours
+        let transit_time =
+            runtime.production_line(2).export_pipe().transit_time();
+        for job in jobs.iter() {
+            let start = match job.production_line.id() {
+                2 => Duration::ZERO,
+                _ => Duration::from_secs(36_000) + transit_time,
+            };
+            assert_eq!(job.start, start);
+            assert_eq!(job.end, start + Duration::from_secs(36_000));
+            assert!(!job.late);
+        }
+    }
+
+    #[test]
+    fn slots_within_character_and_location() {
+        let snapshot = snapshot();
+        let mut runtime = snapshot.runtime();
+        runtime.build();
+        let jobs = scheduled(&runtime, runtime.max_time);
+        // the factory is uncapped, alice has four slots
+        assert!(jobs.iter().all(|job| job.location_slot.is_none()));
+        let mut slots = jobs
+            .iter()
+            .map(|job| job.character_slot.unwrap())
+            .collect::<Vec<_>>();
+        slots.sort();
+        assert_eq!(slots, vec![0, 1, 2, 3]);
+
+        let config = CONFIG.replace(
+            "      structure_type_id: 35825",
+            "      slots: {manufacturing: 4}\n      structure_type_id: 35825",
+        );
+        let snapshot = Snapshot {
+            cfg: serde_yaml::from_str(&config).unwrap(),
+            ..snapshot
+        };
+        let mut runtime = snapshot.runtime();
+        runtime.build();
+        let jobs = scheduled(&runtime, runtime.max_time);
+        let mut slots = jobs
+            .iter()
+            .map(|job| job.location_slot.unwrap())
+            .collect::<Vec<_>>();
+        slots.sort();
+        assert_eq!(slots, vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn jobs_ending_after_window_are_late() {
+        let snapshot = snapshot();
+        let mut runtime = snapshot.runtime();
+        runtime.build();
+        // line 1 ends 20 hours in
+        let jobs = scheduled(&runtime, Duration::from_secs(20 * 3600));
+        assert!(jobs.iter().all(|job| !job.late));
+        let jobs = scheduled(&runtime, Duration::from_secs(20 * 3600 - 1));
+        for job in jobs.iter() {
+            assert_eq!(job.late, job.production_line.id() == 1);
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use super::*;
+use crate::config::ProductionLineExportKind;
+
+// prices moving against a build, sell prices down and material prices up
+const PRICE_SWING: f64 = 0.03;
+
+// how the profit of a planned product line reacts to price changes, per build
+// materials are priced at what the plan paid for them, and so is the profit
+pub struct PriceSensitivity {
+    pub profit: f64,
+    pub sell_price: f64,
+    pub revenue_per_price: f64, // profit change per ISK of sell price
+    pub materials: Vec<(Item, f64, f64)>, // (item, quantity, price paid)
+}
+
+impl PriceSensitivity {
+    pub fn new(
+        production_line: &ProductionLine,
+        type_volumes: &HashMap<Item, f64>,
+    ) -> Option<Self> {
+        if production_line.export_kind() != ProductionLineExportKind::Product {
+            return None;
+        }
+        let profits = production_line.profits.read().unwrap();
+        if profits.is_empty() {
+            return None;
+        }
+        // the builds as picked, but with materials bought as the plan did
+        let materials = production_line.plan_materials(type_volumes);
+        let material_cost = materials
+            .iter()
+            .map(|(_, quantity, price)| quantity * price)
+            .sum::<f64>();
+        let profit = profits
+            .iter()
+            .map(|p| p.profit() + p.materials)
+            .sum::<f64>()
+            / profits.len() as f64
+            - material_cost;
+        let market = production_line.unwrap_export_market();
+        let sell_price =
+            market.orders.min_sell(&production_line.product().type_id)?;
+        // sales tax, brokers fee and delivery fees all scale with the price
+        let delivery_rate = production_line.export_pipe().delivery_rate();
+        let revenue_per_price = production_line.expected_portion()
+            * (1.0
+                - market.sales_tax()
+                - market.brokers_fee()
+                - delivery_rate.collateral_rate
+                - delivery_rate.capital_rate);
+        Some(Self {
+            profit,
+            sell_price,
+            revenue_per_price,
+            materials,
+        })
+    }
+
+    pub fn material_cost(&self) -> f64 {
+        self.materials
+            .iter()
+            .map(|(_, quantity, price)| quantity * price)
+            .sum()
+    }
+
+    pub fn break_even_sell_price(&self) -> f64 {
+        self.sell_price - self.profit / self.revenue_per_price
+    }
+
+    // portion that every material price can rise by, None without materials
+    pub fn break_even_material_change(&self) -> Option<f64> {
+        match self.material_cost() {
+            cost if cost > 0.0 => Some(self.profit / cost),
+            _ => None,
+        }
+    }
+
+    pub fn swung_profit(&self) -> f64 {
+        self.profit
+            - PRICE_SWING
+                * (self.sell_price * self.revenue_per_price
+                    + self.material_cost())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 10 units sold at 1,000 ISK with 10% taken off, 4,000 ISK of materials
+    fn sensitivity() -> PriceSensitivity {
+        PriceSensitivity {
+            profit: 2_000.0,
+            sell_price: 1_000.0,
+            revenue_per_price: 9.0,
+            materials: vec![
+                (Item::new(34), 500.0, 5.0),
+                (Item::new(35), 150.0, 10.0),
+            ],
+        }
+    }
+
+    #[test]
+    fn break_even_sell_price() {
+        let sensitivity = sensitivity();
+        let price = sensitivity.break_even_sell_price();
+        assert!((price - (1_000.0 - 2_000.0 / 9.0)).abs() < 1e-9);
+        // selling there loses exactly the profit
+        let lost = (sensitivity.sell_price - price) * 9.0;
+        assert!((lost - sensitivity.profit).abs() < 1e-9);
+    }
+
+    #[test]
+    fn break_even_material_change() {
+        let sensitivity = sensitivity();
+        assert_eq!(sensitivity.material_cost(), 4_000.0);
+        assert_eq!(sensitivity.break_even_material_change(), Some(0.5));
+        let sensitivity = PriceSensitivity {
+            materials: Vec::new(),
+            ..sensitivity
+        };
+        assert_eq!(sensitivity.break_even_material_change(), None);
+    }
+
+    #[test]
+    fn swung_profit() {
+        // 3% of 9,000 ISK revenue and of 4,000 ISK of materials
+        let sensitivity = sensitivity();
+        assert!((sensitivity.swung_profit() - 1_610.0).abs() < 1e-9);
+        let sensitivity = PriceSensitivity {
+            profit: 300.0,
+            ..sensitivity
+        };
+        assert!(sensitivity.swung_profit() < 0.0);
+    }
+}
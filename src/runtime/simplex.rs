@@ -0,0 +1,227 @@
+// a small dense two-phase simplex, for mixes of a few dozen market orders
+
+const EPSILON: f64 = 1e-9;
+
+// minimizes c.x subject to a.x >= b and 0 <= x <= upper, None if infeasible
+pub fn minimize(
+    c: &[f64],
+    a: &[Vec<f64>],
+    b: &[f64],
+    upper: &[f64],
+) -> Option<Vec<f64>> {
+    let (m, n) = (b.len(), c.len());
+    // columns are x, surplus, upper bound slack, artificial, then the rhs
+    let (surplus, slack, artificial) = (n, n + m, 2 * n + m);
+    let cols = 2 * n + 2 * m;
+    let mut t = vec![vec![0.0; cols + 1]; m + n];
+    let mut basis = vec![0; m + n];
+    for i in 0..m {
+        t[i][..n].copy_from_slice(&a[i]);
+        t[i][surplus + i] = -1.0;
+        t[i][artificial + i] = 1.0;
+        t[i][cols] = b[i];
+        basis[i] = artificial + i;
+    }
+    for j in 0..n {
+        t[m + j][j] = 1.0;
+        t[m + j][slack + j] = 1.0;
+        t[m + j][cols] = upper[j];
+        basis[m + j] = slack + j;
+    }
+
+    // phase 1, find a feasible basis by driving out the artificials
+    let mut cost = vec![0.0; cols];
+    cost[artificial..].iter_mut().for_each(|c| *c = 1.0);
+    solve(&mut t, &mut basis, &cost, cols)?;
+    let infeasibility = objective(&t, &basis, &cost);
+    if infeasibility > EPSILON * (1.0 + b.iter().sum::<f64>()) {
+        return None;
+    }
+    // artificials left in the basis are at zero, swap them for anything else
+    for r in 0..m + n {
+        if basis[r] >= artificial {
+            if let Some(j) = (0..artificial).find(|&j| t[r][j].abs() > EPSILON)
+            {
+                pivot(&mut t, r, j);
+                basis[r] = j;
+            }
+        }
+    }
+
+    // phase 2, artificials may not re-enter
+    cost.iter_mut().for_each(|c| *c = 0.0);
+    cost[..n].copy_from_slice(c);
+    solve(&mut t, &mut basis, &cost, artificial)?;
+
+    let mut x = vec![0.0; n];
+    for (r, &j) in basis.iter().enumerate() {
+        if j < n {
+            x[j] = t[r][cols].max(0.0);
+        }
+    }
+    Some(x)
+}
+
+fn objective(t: &[Vec<f64>], basis: &[usize], cost: &[f64]) -> f64 {
+    let rhs = cost.len();
+    basis
+        .iter()
+        .enumerate()
+        .map(|(r, &j)| cost[j] * t[r][rhs])
+        .sum()
+}
+
+fn reduced_cost(
+    t: &[Vec<f64>],
+    basis: &[usize],
+    cost: &[f64],
+    col: usize,
+) -> f64 {
+    cost[col]
+        - basis
+            .iter()
+            .enumerate()
+            .map(|(r, &j)| cost[j] * t[r][col])
+            .sum::<f64>()
+}
+
+// pivots until optimal, using bland's rule so it can't cycle
+// None if unbounded, which can't happen with upper bounds on every x
+fn solve(
+    t: &mut [Vec<f64>],
+    basis: &mut [usize],
+    cost: &[f64],
+    entering_cols: usize,
+) -> Option<()> {
+    let rhs = cost.len();
+    loop {
+        let entering = match (0..entering_cols).find(|&j| {
+            !basis.contains(&j) && reduced_cost(t, basis, cost, j) < -EPSILON
+        }) {
+            Some(j) => j,
+            None => return Some(()),
+        };
+        let mut leaving: Option<(usize, f64)> = None;
+        for r in 0..t.len() {
+            if t[r][entering] > EPSILON {
+                let ratio = t[r][rhs] / t[r][entering];
+                let better = match leaving {
+                    None => true,
+                    Some((l, best)) => {
+                        ratio < best - EPSILON
+                            || (ratio < best + EPSILON && basis[r] < basis[l])
+                    }
+                };
+                if better {
+                    leaving = Some((r, ratio));
+                }
+            }
+        }
+        let (r, _) = leaving?;
+        pivot(t, r, entering);
+        basis[r] = entering;
+    }
+}
+
+fn pivot(t: &mut [Vec<f64>], row: usize, col: usize) {
+    let divisor = t[row][col];
+    t[row].iter_mut().for_each(|v| *v /= divisor);
+    let pivot_row = t[row].clone();
+    for (r, other) in t.iter_mut().enumerate() {
+        let factor = other[col];
+        if r != row && factor != 0.0 {
+            other
+                .iter_mut()
+                .zip(pivot_row.iter())
+                .for_each(|(v, p)| *v -= factor * p);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_solution(x: &[f64], expected: &[f64]) {
+        assert_eq!(x.len(), expected.len());
+        for (x, expected) in x.iter().zip(expected) {
+            assert!((x - expected).abs() < 1e-6, "{:?} != {:?}", x, expected);
+        }
+    }
+
+    #[test]
+    fn minimize_feasible() {
+        // x + 2y >= 4, 3x + y >= 6, meet at (1.6, 1.2)
+        let x = minimize(
+            &[1.0, 1.0],
+            &[vec![1.0, 2.0], vec![3.0, 1.0]],
+            &[4.0, 6.0],
+            &[100.0, 100.0],
+        )
+        .unwrap();
+        assert_solution(&x, &[1.6, 1.2]);
+    }
+
+    #[test]
+    fn minimize_infeasible() {
+        // x + y >= 10 with neither above 4
+        let x = minimize(&[1.0, 1.0], &[vec![1.0, 1.0]], &[10.0], &[4.0, 4.0]);
+        assert!(x.is_none());
+    }
+
+    #[test]
+    fn minimize_upper_bounded() {
+        // the cheaper x runs out at 4, y covers the rest
+        let x =
+            minimize(&[1.0, 2.0], &[vec![1.0, 1.0]], &[10.0], &[4.0, 100.0])
+                .unwrap();
+        assert_solution(&x, &[4.0, 6.0]);
+        // exactly enough
+        let x = minimize(&[1.0, 2.0], &[vec![1.0, 1.0]], &[8.0], &[4.0, 4.0])
+            .unwrap();
+        assert_solution(&x, &[4.0, 4.0]);
+    }
+
+    #[test]
+    fn minimize_nothing_needed() {
+        let x = minimize(&[1.0, 2.0], &[vec![1.0, 1.0]], &[0.0], &[4.0, 4.0])
+            .unwrap();
+        assert_solution(&x, &[0.0, 0.0]);
+    }
+
+    #[test]
+    fn minimize_degenerate() {
+        // redundant constraints and equal costs tie at every vertex
+        let x = minimize(
+            &[1.0, 1.0, 1.0],
+            &[
+                vec![1.0, 1.0, 0.0],
+                vec![1.0, 0.0, 1.0],
+                vec![0.0, 1.0, 1.0],
+                vec![1.0, 1.0, 1.0],
+                vec![1.0, 1.0, 1.0],
+            ],
+            &[1.0, 1.0, 1.0, 1.5, 1.5],
+            &[1.0, 1.0, 1.0],
+        )
+        .unwrap();
+        assert!((x.iter().sum::<f64>() - 1.5).abs() < 1e-6, "{:?}", x);
+    }
+
+    #[test]
+    fn minimize_beale_does_not_cycle() {
+        // beale's example, which cycles under the largest coefficient rule
+        // max 3/4 x1 - 20 x2 + 1/2 x3 - 6 x4
+        // 1/4 x1 - 8 x2 - x3 + 9 x4 <= 0
+        // 1/2 x1 - 12 x2 - 1/2 x3 + 3 x4 <= 0
+        // x3 <= 1
+        let x = minimize(
+            &[-0.75, 20.0, -0.5, 6.0],
+            &[vec![-0.25, 8.0, 1.0, -9.0], vec![-0.5, 12.0, 0.5, -3.0]],
+            &[0.0, 0.0],
+            &[100.0, 100.0, 1.0, 100.0],
+        )
+        .unwrap();
+        assert_solution(&x, &[1.0, 0.0, 1.0, 0.0]);
+    }
+}